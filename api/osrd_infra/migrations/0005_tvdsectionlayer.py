@@ -0,0 +1,48 @@
+# Generated by Django 4.1 on 2022-09-20 10:12
+
+import django.contrib.gis.db.models.fields
+import django.db.models.deletion
+from django.db import migrations, models
+
+
+class Migration(migrations.Migration):
+
+    dependencies = [
+        ("osrd_infra", "0004_make_speed_category_optional"),
+    ]
+
+    operations = [
+        migrations.CreateModel(
+            name="TVDSectionLayer",
+            fields=[
+                ("id", models.AutoField(auto_created=True, primary_key=True, serialize=False, verbose_name="ID")),
+                ("obj_id", models.CharField(max_length=255)),
+                ("geographic", django.contrib.gis.db.models.fields.MultiLineStringField(srid=3857)),
+                ("schematic", django.contrib.gis.db.models.fields.MultiLineStringField(srid=3857)),
+                ("data", models.JSONField()),
+            ],
+            options={
+                "verbose_name_plural": "generated tvd section layer",
+            },
+        ),
+        migrations.RunSQL(
+            """ALTER TABLE osrd_infra_tvdsectionlayer
+                ADD infra_id INTEGER,
+                ADD CONSTRAINT osrd_infra_tvdsectionlayer_fkey FOREIGN KEY (infra_id) REFERENCES osrd_infra_infra(id) ON DELETE CASCADE
+            """,
+            state_operations=[
+                migrations.AddField(
+                    model_name="tvdsectionlayer",
+                    name="infra",
+                    field=models.ForeignKey(
+                        on_delete=django.db.models.deletion.CASCADE,
+                        to="osrd_infra.infra",
+                    ),
+                ),
+            ],
+        ),
+        migrations.AlterUniqueTogether(
+            name="tvdsectionlayer",
+            unique_together={("infra", "obj_id")},
+        ),
+    ]
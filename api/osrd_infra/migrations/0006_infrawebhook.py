@@ -0,0 +1,29 @@
+# Generated by Django 4.1 on 2022-10-03 09:41
+
+import django.db.models.deletion
+from django.db import migrations, models
+
+
+class Migration(migrations.Migration):
+
+    dependencies = [
+        ("osrd_infra", "0005_tvdsectionlayer"),
+    ]
+
+    operations = [
+        migrations.CreateModel(
+            name="InfraWebhook",
+            fields=[
+                ("id", models.AutoField(auto_created=True, primary_key=True, serialize=False, verbose_name="ID")),
+                ("url", models.URLField(max_length=2048)),
+                ("secret", models.CharField(max_length=64)),
+                (
+                    "infra",
+                    models.ForeignKey(on_delete=django.db.models.deletion.CASCADE, to="osrd_infra.infra"),
+                ),
+            ],
+            options={
+                "verbose_name_plural": "infra webhooks",
+            },
+        ),
+    ]
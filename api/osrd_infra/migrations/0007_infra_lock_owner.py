@@ -0,0 +1,28 @@
+# Generated by Django 4.1 on 2022-10-05 14:12
+
+from django.db import migrations, models
+
+
+class Migration(migrations.Migration):
+
+    dependencies = [
+        ("osrd_infra", "0006_infrawebhook"),
+    ]
+
+    operations = [
+        migrations.AddField(
+            model_name="infra",
+            name="locked_by",
+            field=models.CharField(blank=True, max_length=255, null=True),
+        ),
+        migrations.AddField(
+            model_name="infra",
+            name="lock_reason",
+            field=models.TextField(blank=True, null=True),
+        ),
+        migrations.AddField(
+            model_name="infra",
+            name="locked_until",
+            field=models.BigIntegerField(blank=True, help_text="Lock expiry as a unix timestamp", null=True),
+        ),
+    ]
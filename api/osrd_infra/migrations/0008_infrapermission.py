@@ -0,0 +1,32 @@
+# Generated by Django 4.1 on 2022-10-06 10:27
+
+import django.db.models.deletion
+from django.db import migrations, models
+
+
+class Migration(migrations.Migration):
+
+    dependencies = [
+        ("osrd_infra", "0007_infra_lock_owner"),
+    ]
+
+    operations = [
+        migrations.CreateModel(
+            name="InfraPermission",
+            fields=[
+                ("id", models.AutoField(auto_created=True, primary_key=True, serialize=False, verbose_name="ID")),
+                ("user", models.UUIDField()),
+                (
+                    "role",
+                    models.CharField(choices=[("read", "Read"), ("edit", "Edit"), ("admin", "Admin")], max_length=16),
+                ),
+                (
+                    "infra",
+                    models.ForeignKey(on_delete=django.db.models.deletion.CASCADE, to="osrd_infra.infra"),
+                ),
+            ],
+            options={
+                "unique_together": {("infra", "user")},
+            },
+        ),
+    ]
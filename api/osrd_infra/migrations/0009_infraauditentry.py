@@ -0,0 +1,42 @@
+# Generated by Django 4.1 on 2022-10-07 16:03
+
+from django.db import migrations, models
+
+
+class Migration(migrations.Migration):
+
+    dependencies = [
+        ("osrd_infra", "0008_infrapermission"),
+    ]
+
+    operations = [
+        migrations.CreateModel(
+            name="InfraAuditEntry",
+            fields=[
+                ("id", models.AutoField(auto_created=True, primary_key=True, serialize=False, verbose_name="ID")),
+                ("infra_id", models.IntegerField(db_index=True)),
+                ("user", models.UUIDField()),
+                ("timestamp", models.BigIntegerField(help_text="Unix timestamp of the change")),
+                (
+                    "action",
+                    models.CharField(
+                        choices=[
+                            ("create", "Create"),
+                            ("delete", "Delete"),
+                            ("edit", "Edit"),
+                            ("lock", "Lock"),
+                            ("unlock", "Unlock"),
+                            ("refresh", "Refresh"),
+                        ],
+                        max_length=16,
+                    ),
+                ),
+                ("version_before", models.CharField(max_length=40, null=True)),
+                ("version_after", models.CharField(max_length=40, null=True)),
+                ("summary", models.JSONField()),
+            ],
+            options={
+                "verbose_name_plural": "infra audit entries",
+            },
+        ),
+    ]
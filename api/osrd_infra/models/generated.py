@@ -118,6 +118,18 @@ class OperationalPointLayer(models.Model):
         unique_together = (("infra", "obj_id"),)
 
 
+class TVDSectionLayer(models.Model):
+    infra = models.ForeignKey("Infra", on_delete=models.CASCADE)
+    obj_id = models.CharField(max_length=255)
+    geographic = models.MultiLineStringField(srid=settings.MAPBOX_SRID)
+    schematic = models.MultiLineStringField(srid=settings.MAPBOX_SRID)
+    data = models.JSONField()
+
+    class Meta:
+        verbose_name_plural = "generated tvd section layer"
+        unique_together = (("infra", "obj_id"),)
+
+
 class CatenaryLayer(models.Model):
     infra = models.ForeignKey("Infra", on_delete=models.CASCADE)
     obj_id = models.CharField(max_length=255)
@@ -28,11 +28,57 @@ class Infra(models.Model):
     version = models.CharField(editable=False, max_length=40, default="1")
     generated_version = models.CharField(editable=False, max_length=40, null=True)
     locked = models.BooleanField(default=False)
+    locked_by = models.CharField(max_length=255, null=True, blank=True)
+    lock_reason = models.TextField(null=True, blank=True)
+    locked_until = models.BigIntegerField(null=True, blank=True, help_text="Lock expiry as a unix timestamp")
 
     def __str__(self):
         return self.name
 
 
+class InfraWebhook(models.Model):
+    infra = models.ForeignKey(Infra, on_delete=models.CASCADE)
+    url = models.URLField(max_length=2048)
+    secret = models.CharField(max_length=64)
+
+    class Meta:
+        verbose_name_plural = "infra webhooks"
+
+
+class InfraPermission(models.Model):
+    ROLES = [("read", "Read"), ("edit", "Edit"), ("admin", "Admin")]
+
+    infra = models.ForeignKey(Infra, on_delete=models.CASCADE)
+    user = models.UUIDField()
+    role = models.CharField(max_length=16, choices=ROLES)
+
+    class Meta:
+        unique_together = [["infra", "user"]]
+
+
+class InfraAuditEntry(models.Model):
+    ACTIONS = [
+        ("create", "Create"),
+        ("delete", "Delete"),
+        ("edit", "Edit"),
+        ("lock", "Lock"),
+        ("unlock", "Unlock"),
+        ("refresh", "Refresh"),
+    ]
+
+    # Not a foreign key for the entries to outlive the infra
+    infra_id = models.IntegerField(db_index=True)
+    user = models.UUIDField()
+    timestamp = models.BigIntegerField(help_text="Unix timestamp of the change")
+    action = models.CharField(max_length=16, choices=ACTIONS)
+    version_before = models.CharField(max_length=40, null=True)
+    version_after = models.CharField(max_length=40, null=True)
+    summary = models.JSONField()
+
+    class Meta:
+        verbose_name_plural = "infra audit entries"
+
+
 class OperationalPointModel(models.Model):
     infra = models.ForeignKey(Infra, on_delete=models.CASCADE)
     obj_id = models.CharField(max_length=255)
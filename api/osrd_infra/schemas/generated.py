@@ -87,6 +87,11 @@ class OverlappingTrackLinks(InfraWarningTrait):
     reference: infra.ObjectReference
 
 
+class UncoveredSwitchGroup(InfraWarningTrait):
+    error_type: Literal["uncovered_switch_group"] = Field(default="uncovered_switch_group")
+    group: str
+
+
 # Generic error
 class InfraError(BaseModel):
     __root__: Union[
@@ -104,4 +109,5 @@ class InfraError(BaseModel):
         NoBufferStop,
         OverlappingSwitches,
         OverlappingTrackLinks,
+        UncoveredSwitchGroup,
     ] = Field(discriminator="error_type")
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use diesel::PgConnection;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use rocket::http::Status;
+use rocket::request::{self, FromRequest, Request};
+use rocket::{Outcome, State};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::client::AuthConfig;
+use crate::error::{ApiError, EditoastError};
+use crate::models::audit::audit_creator;
+use crate::models::infra::InfraError;
+use crate::models::permissions::{InfraPermission, Role};
+use crate::models::Infra;
+
+#[derive(Debug, Clone, Error)]
+pub enum AuthError {
+    #[error("Missing bearer token")]
+    MissingCredentials,
+    #[error("Invalid bearer token: {0}")]
+    InvalidToken(String),
+    #[error("The '{required}' role is required on infra '{infra}'")]
+    Forbidden { infra: i32, required: Role },
+}
+
+impl ApiError for AuthError {
+    fn get_status(&self) -> Status {
+        match self {
+            AuthError::MissingCredentials => Status::Unauthorized,
+            AuthError::InvalidToken(_) => Status::Unauthorized,
+            AuthError::Forbidden { .. } => Status::Forbidden,
+        }
+    }
+
+    fn get_type(&self) -> &'static str {
+        match self {
+            AuthError::MissingCredentials => "editoast:auth:MissingCredentials",
+            AuthError::InvalidToken(_) => "editoast:auth:InvalidToken",
+            AuthError::Forbidden { .. } => "editoast:auth:Forbidden",
+        }
+    }
+
+    fn extra(&self) -> Option<Map<String, Value>> {
+        match self {
+            AuthError::Forbidden { infra, required } => json!({
+                "infra": infra,
+                "required_role": required,
+            })
+            .as_object()
+            .cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// Claims expected in JSON Web Tokens, the expiry is checked when decoding
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: Uuid,
+}
+
+/// Authentication failure of a request, kept for the catcher to report it
+struct AuthFailure(Option<AuthError>);
+
+/// The authenticated user of a request
+#[derive(Debug, Clone)]
+pub struct User {
+    pub id: Uuid,
+    /// Set when authentication is disabled, the requested lock owners are then trusted
+    superuser: bool,
+    /// Administrator of all infras, set when authentication is disabled or for the admin users
+    admin: bool,
+}
+
+impl User {
+    fn authenticate(config: &AuthConfig, authorization: Option<&str>) -> Result<Self, AuthError> {
+        if config.disable_auth {
+            return Ok(Self {
+                id: Uuid::nil(),
+                superuser: true,
+                admin: true,
+            });
+        }
+        let token = authorization
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .ok_or(AuthError::MissingCredentials)?
+            .trim();
+        if let Some(id) = config.token_user(token) {
+            return Ok(Self::authenticated(config, id));
+        }
+        let secret = config
+            .jwt_secret
+            .as_ref()
+            .ok_or_else(|| AuthError::InvalidToken("unknown token".into()))?;
+        let claims = decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(secret.as_bytes()),
+            &Validation::new(Algorithm::HS256),
+        )
+        .map_err(|err| AuthError::InvalidToken(err.to_string()))?
+        .claims;
+        Ok(Self::authenticated(config, claims.sub))
+    }
+
+    fn authenticated(config: &AuthConfig, id: Uuid) -> Self {
+        Self {
+            id,
+            superuser: false,
+            admin: config.admin_users.contains(&id),
+        }
+    }
+
+    /// Owner of the locks taken by the user.
+    /// Only when authentication is disabled can the requested owner be used.
+    pub fn lock_owner(&self, requested: Option<String>) -> Option<String> {
+        if self.superuser {
+            requested
+        } else {
+            Some(self.id.to_string())
+        }
+    }
+
+    /// Role of the user on an infra, its owner is an administrator
+    pub fn role(&self, conn: &PgConnection, infra: &Infra) -> Result<Option<Role>, EditoastError> {
+        if self.admin || infra.owner == self.id {
+            return Ok(Some(Role::Admin));
+        }
+        Ok(InfraPermission::get_role(conn, infra.id, &self.id)?)
+    }
+
+    /// Check the user has at least the `required` role on an infra, returning its role
+    pub fn check_role(
+        &self,
+        conn: &PgConnection,
+        infra_id: i32,
+        required: Role,
+    ) -> Result<Role, EditoastError> {
+        let infra = Infra::retrieve(conn, infra_id)?;
+        match self.role(conn, &infra)? {
+            Some(role) if role >= required => Ok(role),
+            _ => Err(AuthError::Forbidden {
+                infra: infra_id,
+                required,
+            }
+            .into()),
+        }
+    }
+
+    /// Check the user can read the audit log of an infra, which outlives the infra.
+    /// Once the infra is deleted, only its owner, recorded as the author of its creation,
+    /// can read it.
+    pub fn check_audit_access(
+        &self,
+        conn: &PgConnection,
+        infra_id: i32,
+    ) -> Result<(), EditoastError> {
+        if Infra::exists(conn, infra_id)? {
+            self.check_role(conn, infra_id, Role::Read)?;
+            return Ok(());
+        }
+        if self.admin {
+            return Ok(());
+        }
+        match audit_creator(conn, infra_id)? {
+            Some(owner) if owner == self.id => Ok(()),
+            Some(_) => Err(AuthError::Forbidden {
+                infra: infra_id,
+                required: Role::Admin,
+            }
+            .into()),
+            None => Err(InfraError::NotFound(infra_id).into()),
+        }
+    }
+
+    /// Keep the infras the user has at least the `required` role on
+    pub fn filter_infras(
+        &self,
+        conn: &PgConnection,
+        infras: Vec<Infra>,
+        required: Role,
+    ) -> Result<Vec<Infra>, EditoastError> {
+        if self.admin {
+            return Ok(infras);
+        }
+        let roles: HashMap<_, _> = InfraPermission::user_roles(conn, &self.id)?
+            .into_iter()
+            .collect();
+        Ok(infras
+            .into_iter()
+            .filter(|infra| {
+                infra.owner == self.id || roles.get(&infra.id).is_some_and(|r| *r >= required)
+            })
+            .collect())
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for User {
+    type Error = AuthError;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let config = request
+            .guard::<State<AuthConfig>>()
+            .succeeded()
+            .expect("The authentication configuration isn't managed");
+        let authorization = request.headers().get_one("Authorization");
+        match User::authenticate(&config, authorization) {
+            Ok(user) => Outcome::Success(user),
+            Err(err) => {
+                request.local_cache(|| AuthFailure(Some(err.clone())));
+                Outcome::Failure((err.get_status(), err))
+            }
+        }
+    }
+}
+
+/// Report authentication failures with the same format as other errors
+#[catch(401)]
+pub fn unauthorized(request: &Request) -> EditoastError {
+    request
+        .local_cache(|| AuthFailure(None))
+        .0
+        .clone()
+        .unwrap_or(AuthError::MissingCredentials)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuthError, User};
+    use crate::client::AuthConfig;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn config() -> AuthConfig {
+        AuthConfig {
+            auth_tokens: vec!["s3cr3t:00000000-0000-0000-0000-000000000000"
+                .parse()
+                .unwrap()],
+            jwt_secret: Some("jwt_key".into()),
+            admin_users: vec![Uuid::parse_str("123e4567-e89b-12d3-a456-426614174000").unwrap()],
+            disable_auth: false,
+        }
+    }
+
+    fn jwt(key: &str, sub: Uuid, exp: u64) -> String {
+        encode(
+            &Header::default(),
+            &json!({ "sub": sub, "exp": exp }),
+            &EncodingKey::from_secret(key.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn static_token() {
+        let user = User::authenticate(&config(), Some("Bearer s3cr3t")).unwrap();
+        assert_eq!(user.id, Uuid::nil());
+        assert!(!user.superuser);
+        assert!(!user.admin);
+    }
+
+    #[test]
+    fn missing_or_unknown_token() {
+        assert!(matches!(
+            User::authenticate(&config(), None),
+            Err(AuthError::MissingCredentials)
+        ));
+        assert!(matches!(
+            User::authenticate(&config(), Some("Basic czNjcjN0")),
+            Err(AuthError::MissingCredentials)
+        ));
+        assert!(matches!(
+            User::authenticate(&config(), Some("Bearer unknown")),
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
+
+    #[test]
+    fn json_web_token() {
+        let sub = Uuid::new_v4();
+        let token = jwt("jwt_key", sub, 32503680000);
+        let user = User::authenticate(&config(), Some(&format!("Bearer {}", token))).unwrap();
+        assert_eq!(user.id, sub);
+
+        let forged = jwt("other_key", sub, 32503680000);
+        assert!(User::authenticate(&config(), Some(&format!("Bearer {}", forged))).is_err());
+        let expired = jwt("jwt_key", sub, 1);
+        assert!(User::authenticate(&config(), Some(&format!("Bearer {}", expired))).is_err());
+    }
+
+    #[test]
+    fn admin_user() {
+        let admin = config().admin_users[0];
+        let token = jwt("jwt_key", admin, 32503680000);
+        let user = User::authenticate(&config(), Some(&format!("Bearer {}", token))).unwrap();
+        assert!(user.admin);
+        assert_eq!(
+            user.lock_owner(Some("other".into())),
+            Some(admin.to_string())
+        );
+    }
+
+    #[test]
+    fn disabled_authentication() {
+        let config = AuthConfig::default();
+        let user = User::authenticate(&config, None).unwrap();
+        assert!(user.superuser);
+        assert!(user.admin);
+    }
+}
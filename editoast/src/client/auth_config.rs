@@ -0,0 +1,117 @@
+use std::str::FromStr;
+
+use clap::Args;
+use derivative::Derivative;
+use uuid::Uuid;
+
+/// A bearer token validated locally, given as `<token>:<user uuid>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticToken {
+    pub token: String,
+    pub user: Uuid,
+}
+
+impl FromStr for StaticToken {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (token, user) = value
+            .rsplit_once(':')
+            .ok_or_else(|| format!("Token '{}' must be given as '<token>:<user uuid>'", value))?;
+        if token.is_empty() {
+            return Err("Tokens can't be empty".into());
+        }
+        let user = Uuid::parse_str(user).map_err(|err| format!("Invalid user uuid: {}", err))?;
+        Ok(Self {
+            token: token.into(),
+            user,
+        })
+    }
+}
+
+#[derive(Args, Debug, Clone, Derivative)]
+#[derivative(Default)]
+pub struct AuthConfig {
+    #[clap(
+        long,
+        env = "EDITOAST_AUTH_TOKENS",
+        value_delimiter = ',',
+        help = "Bearer tokens accepted by the server, as '<token>:<user uuid>' separated by commas"
+    )]
+    pub auth_tokens: Vec<StaticToken>,
+    #[clap(
+        long,
+        env = "EDITOAST_JWT_SECRET",
+        help = "Key checking the signature of HS256 JSON Web Tokens, whose subject is the user uuid"
+    )]
+    pub jwt_secret: Option<String>,
+    #[clap(
+        long,
+        env = "EDITOAST_ADMIN_USERS",
+        value_delimiter = ',',
+        help = "Uuids of the users administrating all infras, separated by commas. \
+                Infras created before authentication was enabled are owned by the nil uuid \
+                and only reachable by these users, who can grant roles on them."
+    )]
+    pub admin_users: Vec<Uuid>,
+    /// The default configuration, used by tests, doesn't authenticate requests
+    #[derivative(Default(value = "true"))]
+    #[clap(
+        long,
+        env = "EDITOAST_DISABLE_AUTH",
+        help = "Accept every request as an administrator of all infras"
+    )]
+    pub disable_auth: bool,
+}
+
+impl AuthConfig {
+    /// Return the user owning a static token
+    pub fn token_user(&self, token: &str) -> Option<Uuid> {
+        self.auth_tokens
+            .iter()
+            .find(|static_token| constant_time_eq(static_token.token.as_bytes(), token.as_bytes()))
+            .map(|static_token| static_token.user)
+    }
+}
+
+/// Compare two byte strings in a time that doesn't depend on their content
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuthConfig, StaticToken};
+    use uuid::Uuid;
+
+    #[test]
+    fn parse_static_token() {
+        let token: StaticToken = "s3cr3t:123e4567-e89b-12d3-a456-426614174000"
+            .parse()
+            .unwrap();
+        assert_eq!(token.token, "s3cr3t");
+        assert_eq!(
+            token.user,
+            Uuid::parse_str("123e4567-e89b-12d3-a456-426614174000").unwrap()
+        );
+        assert!("s3cr3t".parse::<StaticToken>().is_err());
+        assert!("s3cr3t:alice".parse::<StaticToken>().is_err());
+        assert!(":123e4567-e89b-12d3-a456-426614174000"
+            .parse::<StaticToken>()
+            .is_err());
+    }
+
+    #[test]
+    fn token_user() {
+        let user = Uuid::new_v4();
+        let config = AuthConfig {
+            auth_tokens: vec![StaticToken {
+                token: "s3cr3t".into(),
+                user,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(config.token_user("s3cr3t"), Some(user));
+        assert_eq!(config.token_user("s3cr3"), None);
+    }
+}
@@ -1,7 +1,15 @@
+use std::path::PathBuf;
+
 use clap::Args;
+use colored::Colorize;
 use derivative::Derivative;
 
-#[derive(Args, Debug, Derivative)]
+use crate::layer::{
+    ChartosBackend, Invalidation, InvalidationBackend, InvalidationBackendKind, InvalidationQueue,
+    NoopBackend, RecordingBackend,
+};
+
+#[derive(Args, Clone, Debug, Derivative)]
 #[derivative(Default)]
 pub struct ChartosConfig {
     #[derivative(Default(value = r#""http://localhost:7000/".into()"#))]
@@ -9,6 +17,28 @@ pub struct ChartosConfig {
     chartos_url: String,
     #[clap(long, env, default_value_t)]
     pub chartos_token: String,
+    #[clap(
+        long,
+        env,
+        arg_enum,
+        default_value = "chartos",
+        help = "How to invalidate the layer caches of the tile server"
+    )]
+    pub invalidation_backend: InvalidationBackendKind,
+    #[derivative(Default(value = r#""pending_invalidations.json".into()"#))]
+    #[clap(
+        long,
+        env,
+        default_value = "pending_invalidations.json",
+        help = "File where the server keeps the invalidations not sent yet"
+    )]
+    pub pending_invalidations_file: PathBuf,
+    #[clap(skip)]
+    client: reqwest::blocking::Client,
+    #[clap(skip)]
+    recorder: RecordingBackend,
+    #[clap(skip)]
+    queue: Option<InvalidationQueue>,
 }
 
 impl ChartosConfig {
@@ -19,4 +49,56 @@ impl ChartosConfig {
             format!("{}/", self.chartos_url)
         }
     }
+
+    /// Return the configured invalidation backend
+    pub fn invalidation_backend(&self) -> Box<dyn InvalidationBackend> {
+        match self.invalidation_backend {
+            InvalidationBackendKind::Chartos => Box::new(ChartosBackend {
+                url: self.url(),
+                token: self.chartos_token.clone(),
+                client: self.client.clone(),
+            }),
+            InvalidationBackendKind::Noop => Box::new(NoopBackend),
+            InvalidationBackendKind::Recording => Box::new(self.recorder.clone()),
+        }
+    }
+
+    /// Invalidations kept by the recording backend, oldest first
+    pub fn recorded_invalidations(&self) -> Vec<Invalidation> {
+        self.recorder.records()
+    }
+
+    /// Send invalidations from a background worker instead of inline
+    pub fn start_invalidation_worker(&mut self) {
+        let queue = InvalidationQueue::new(Some(self.pending_invalidations_file.clone()));
+        queue.spawn_worker(self.invalidation_backend());
+        self.queue = Some(queue);
+    }
+
+    /// Queue of invalidations, if the background worker is started
+    pub fn invalidation_queue(&self) -> Option<&InvalidationQueue> {
+        self.queue.as_ref()
+    }
+
+    /// Copy of the configuration holding invalidations back until [Self::send_deferred].
+    /// Used in transactions, for chartos not to refetch data that isn't committed yet.
+    pub fn deferred(&self) -> Self {
+        Self {
+            queue: Some(InvalidationQueue::new(None)),
+            ..self.clone()
+        }
+    }
+
+    /// Send the invalidations held back by a deferred configuration
+    pub fn send_deferred(&self, deferred: Self) {
+        let held = deferred.queue.expect("not a deferred configuration");
+        match &self.queue {
+            Some(queue) => queue.push_all(&held),
+            None => {
+                if let Err(err) = held.flush(self.invalidation_backend().as_ref()) {
+                    eprintln!("{}", format!("Failed to invalidate layers: {err}").red());
+                }
+            }
+        }
+    }
 }
@@ -1,9 +1,12 @@
+mod auth_config;
 mod chartos_config;
 mod postgres_config;
 
+pub use auth_config::AuthConfig;
 pub use chartos_config::ChartosConfig;
 use clap::{Args, Parser, Subcommand};
 pub use postgres_config::PostgresConfig;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap(author, version)]
@@ -12,6 +15,8 @@ pub struct Client {
     pub postgres_config: PostgresConfig,
     #[clap(flatten)]
     pub chartos_config: ChartosConfig,
+    #[clap(flatten)]
+    pub auth_config: AuthConfig,
     #[clap(subcommand)]
     pub command: Commands,
 }
@@ -27,6 +32,24 @@ pub enum Commands {
 pub struct RunserverArgs {
     #[clap(long, env = "EDITOAST_PORT", default_value_t = 8090)]
     pub port: u16,
+    #[clap(
+        long,
+        env = "EDITOAST_TILE_SERVER",
+        help = "Serve layers vector tiles, Chartos is then not needed"
+    )]
+    pub tile_server: bool,
+    #[clap(
+        long,
+        env = "EDITOAST_INFRA_CACHE_BUDGET",
+        help = "Memory budget of the infra caches in MiB, least recently used ones are evicted beyond it"
+    )]
+    pub infra_cache_budget: Option<usize>,
+    #[clap(
+        long,
+        env = "EDITOAST_INFRA_CACHE_SNAPSHOTS",
+        help = "Directory where infra caches are saved, to be reloaded at startup while their infra is unchanged"
+    )]
+    pub infra_cache_snapshots: Option<PathBuf>,
 }
 
 #[derive(Args, Debug)]
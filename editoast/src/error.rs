@@ -100,6 +100,9 @@ impl<'f> From<JsonError<'f>> for EditoastError {
 
 pub struct InfraLockedError {
     pub infra_id: i32,
+    pub locked_by: Option<String>,
+    pub lock_reason: Option<String>,
+    pub locked_until: Option<i64>,
 }
 
 impl From<InfraLockedError> for EditoastError {
@@ -110,6 +113,9 @@ impl From<InfraLockedError> for EditoastError {
             Status::BadRequest,
             json!({
                 "infra_id": err.infra_id,
+                "locked_by": err.locked_by,
+                "lock_reason": err.lock_reason,
+                "locked_until": err.locked_until,
             })
             .as_object()
             .cloned(),
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use derivative::Derivative;
+use rocket::http::Status;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::error::ApiError;
+use crate::objects::operation::OperationResult;
+
+/// Interval between two keep alive comments of an idle event stream.
+/// Writing them is also how disconnected clients are detected.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// An event occurring on an infra
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InfraEvent {
+    /// A batch of operations was applied
+    Operations { operations: Vec<OperationResult> },
+    /// The infra version was bumped
+    Version { version: String },
+    /// The infra was locked or unlocked
+    Lock { locked: bool },
+    /// Errors and generated data were regenerated up to the given version
+    ErrorsGenerated { generated_version: String },
+}
+
+impl InfraEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            InfraEvent::Operations { .. } => "operations",
+            InfraEvent::Version { .. } => "version",
+            InfraEvent::Lock { .. } => "lock",
+            InfraEvent::ErrorsGenerated { .. } => "errors_generated",
+        }
+    }
+
+    /// Format the event as a Server-Sent Event message
+    fn to_sse(&self) -> String {
+        format!(
+            "event: {}\ndata: {}\n\n",
+            self.name(),
+            serde_json::to_string(self).unwrap()
+        )
+    }
+}
+
+/// Maximum number of open streams of a bus created with `Default`
+const DEFAULT_MAX_STREAMS: usize = 8;
+
+#[derive(Debug, Error)]
+#[error("Too many open event streams, at most {0} are allowed")]
+pub struct TooManyStreams(usize);
+
+impl ApiError for TooManyStreams {
+    fn get_status(&self) -> Status {
+        Status::ServiceUnavailable
+    }
+
+    fn get_type(&self) -> &'static str {
+        "editoast:events:TooManyStreams"
+    }
+
+    fn extra(&self) -> Option<Map<String, Value>> {
+        json!({ "max_streams": self.0 }).as_object().cloned()
+    }
+}
+
+/// Dispatch infra events to the streams of the clients subscribed to the infra.
+/// Each open stream holds a server worker thread, their number is capped not to starve
+/// the other requests.
+#[derive(Derivative)]
+#[derivative(Default)]
+pub struct InfraEventBus {
+    subscribers: Mutex<HashMap<i32, Vec<Sender<InfraEvent>>>>,
+    open_streams: Arc<AtomicUsize>,
+    #[derivative(Default(value = "DEFAULT_MAX_STREAMS"))]
+    max_streams: usize,
+}
+
+impl InfraEventBus {
+    pub fn new(max_streams: usize) -> Self {
+        Self {
+            max_streams,
+            ..Default::default()
+        }
+    }
+
+    /// Subscribe to the events of an infra, unless too many streams are open
+    pub fn subscribe(&self, infra_id: i32) -> Result<EventStream, TooManyStreams> {
+        self.open_streams
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                (open < self.max_streams).then_some(open + 1)
+            })
+            .map_err(|_| TooManyStreams(self.max_streams))?;
+        let (sender, receiver) = channel();
+        self.subscribers
+            .lock()
+            .unwrap()
+            .entry(infra_id)
+            .or_default()
+            .push(sender);
+        Ok(EventStream::new(receiver, self.open_streams.clone()))
+    }
+
+    /// Send events to the subscribers of an infra, dropping the ones that disconnected
+    pub fn publish(&self, infra_id: i32, events: &[InfraEvent]) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if let Some(senders) = subscribers.get_mut(&infra_id) {
+            senders.retain(|sender| {
+                events
+                    .iter()
+                    .all(|event| sender.send(event.clone()).is_ok())
+            });
+            if senders.is_empty() {
+                subscribers.remove(&infra_id);
+            }
+        }
+    }
+}
+
+/// Server-Sent Events stream of infra events.
+/// Returns a `WouldBlock` error after each message to make rocket flush it to the client.
+pub struct EventStream {
+    receiver: Receiver<InfraEvent>,
+    buffer: Vec<u8>,
+    position: usize,
+    flush: bool,
+    /// Number of open streams of the bus, decremented when the stream is dropped
+    open_streams: Arc<AtomicUsize>,
+}
+
+impl EventStream {
+    fn new(receiver: Receiver<InfraEvent>, open_streams: Arc<AtomicUsize>) -> Self {
+        Self {
+            receiver,
+            buffer: vec![],
+            position: 0,
+            flush: false,
+            open_streams,
+        }
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.open_streams.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Read for EventStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.buffer.len() {
+            if self.flush {
+                self.flush = false;
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            let message = match self.receiver.recv_timeout(KEEP_ALIVE_INTERVAL) {
+                Ok(event) => event.to_sse(),
+                Err(RecvTimeoutError::Timeout) => ": keep-alive\n\n".into(),
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+            self.buffer = message.into_bytes();
+            self.position = 0;
+        }
+        let size = buf.len().min(self.buffer.len() - self.position);
+        buf[..size].copy_from_slice(&self.buffer[self.position..self.position + size]);
+        self.position += size;
+        self.flush = self.position == self.buffer.len();
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InfraEvent, InfraEventBus};
+    use std::io::{ErrorKind, Read};
+
+    #[test]
+    fn subscribers_receive_their_infra_events() {
+        let bus = InfraEventBus::default();
+        let mut stream = bus.subscribe(1).unwrap();
+        let other = bus.subscribe(2).unwrap();
+
+        bus.publish(1, &[InfraEvent::Lock { locked: true }]);
+
+        let mut buf = [0; 1024];
+        let size = stream.read(&mut buf).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..size]).unwrap(),
+            "event: lock\ndata: {\"type\":\"lock\",\"locked\":true}\n\n"
+        );
+        // The message is flushed before waiting for the next one
+        assert_eq!(
+            stream.read(&mut buf).unwrap_err().kind(),
+            ErrorKind::WouldBlock
+        );
+        assert!(other.receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn messages_larger_than_buffer() {
+        let bus = InfraEventBus::default();
+        let mut stream = bus.subscribe(1).unwrap();
+        bus.publish(
+            1,
+            &[InfraEvent::Version {
+                version: "42".into(),
+            }],
+        );
+        let mut message = vec![];
+        let mut buf = [0; 8];
+        loop {
+            match stream.read(&mut buf) {
+                Ok(size) => message.extend_from_slice(&buf[..size]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => panic!("{}", err),
+            }
+        }
+        assert_eq!(
+            String::from_utf8(message).unwrap(),
+            "event: version\ndata: {\"type\":\"version\",\"version\":\"42\"}\n\n"
+        );
+    }
+
+    #[test]
+    fn disconnected_subscribers_are_dropped() {
+        let bus = InfraEventBus::default();
+        drop(bus.subscribe(1).unwrap());
+        bus.publish(1, &[InfraEvent::Lock { locked: false }]);
+        assert!(bus.subscribers.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn open_streams_are_capped() {
+        let bus = InfraEventBus::new(2);
+        let first = bus.subscribe(1).unwrap();
+        let _second = bus.subscribe(2).unwrap();
+        assert!(bus.subscribe(1).is_err());
+        // Closing a stream frees its slot
+        drop(first);
+        assert!(bus.subscribe(1).is_ok());
+    }
+}
@@ -1,9 +1,10 @@
 use crate::client::ChartosConfig;
 use crate::error::ApiError;
 use crate::infra_cache::InfraCache;
-use crate::layer::InvalidationZone;
 use crate::layer::Layer;
+use crate::layer::LayerInvalidationZones;
 use crate::models::errors::generate_errors;
+use crate::models::tvd_sections;
 use crate::models::DBConnection;
 use crate::models::Infra;
 use crate::objects::operation::OperationResult;
@@ -45,6 +46,9 @@ pub fn refresh(
     // Generate errors
     generate_errors(conn, infra.id, infra_cache, chartos_config)?;
 
+    // Generate TVD sections
+    tvd_sections::refresh_layer(conn, infra.id, infra_cache, chartos_config)?;
+
     // Update generated infra version
     infra.bump_generated_version(conn)?;
     Ok(true)
@@ -56,19 +60,19 @@ pub fn update(
     infra_id: i32,
     operations: &Vec<OperationResult>,
     cache: &InfraCache,
-    zone: &InvalidationZone,
+    zones: &LayerInvalidationZones,
     chartos_config: &ChartosConfig,
 ) -> Result<(), Box<dyn ApiError>> {
-    TrackSection::update(conn, infra_id, operations, cache, zone, chartos_config)?;
-    Signal::update(conn, infra_id, operations, cache, zone, chartos_config)?;
-    SpeedSection::update(conn, infra_id, operations, cache, zone, chartos_config)?;
-    TrackSectionLink::update(conn, infra_id, operations, cache, zone, chartos_config)?;
-    Switch::update(conn, infra_id, operations, cache, zone, chartos_config)?;
-    Detector::update(conn, infra_id, operations, cache, zone, chartos_config)?;
-    BufferStop::update(conn, infra_id, operations, cache, zone, chartos_config)?;
-    Route::update(conn, infra_id, operations, cache, zone, chartos_config)?;
-    OperationalPoint::update(conn, infra_id, operations, cache, zone, chartos_config)?;
-    Catenary::update(conn, infra_id, operations, cache, zone, chartos_config)?;
+    TrackSection::update(conn, infra_id, operations, cache, zones, chartos_config)?;
+    Signal::update(conn, infra_id, operations, cache, zones, chartos_config)?;
+    SpeedSection::update(conn, infra_id, operations, cache, zones, chartos_config)?;
+    TrackSectionLink::update(conn, infra_id, operations, cache, zones, chartos_config)?;
+    Switch::update(conn, infra_id, operations, cache, zones, chartos_config)?;
+    Detector::update(conn, infra_id, operations, cache, zones, chartos_config)?;
+    BufferStop::update(conn, infra_id, operations, cache, zones, chartos_config)?;
+    Route::update(conn, infra_id, operations, cache, zones, chartos_config)?;
+    OperationalPoint::update(conn, infra_id, operations, cache, zones, chartos_config)?;
+    Catenary::update(conn, infra_id, operations, cache, zones, chartos_config)?;
 
     Ok(())
 }
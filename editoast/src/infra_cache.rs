@@ -1,13 +1,31 @@
+use crate::error::ApiError;
 use crate::objects::operation::{OperationResult, RailjsonObject};
 use crate::objects::*;
-use diesel::sql_types::{Double, Integer, Nullable, Text};
+use diesel::pg::Pg;
+use diesel::sql_types::{Integer, Nullable, Text};
 use diesel::PgConnection;
 use diesel::{sql_query, QueryableByName, RunQueryDsl};
 use enum_map::EnumMap;
+use rocket::http::Status;
+use serde::{de, Deserialize, Serialize};
+use serde_json::{json, Map, Value};
 use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::mem::size_of;
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Rough estimate of the heap memory used by a cached object (ids, geometries, paths...)
+const OBJECT_HEAP_ESTIMATE: usize = 128;
+
+/// Version of the snapshot format, to bump when the cached objects change
+const SNAPSHOT_FORMAT: u32 = 1;
 
 /// Contains infra cached data used to generate layers and errors
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct InfraCache {
     /// Map track section id to the list of objects that depend on it
     /// Contains all referenced track sections (not only existing ones)
@@ -17,6 +35,73 @@ pub struct InfraCache {
     objects: EnumMap<ObjectType, HashMap<String, ObjectCache>>,
 }
 
+/// Error raised when an infra cache can't be loaded from the database
+#[derive(Debug, Clone, Error)]
+pub enum InfraCacheError {
+    #[error("Failed to load the {obj_type:?} objects of infra '{infra}': {reason}")]
+    Query {
+        infra: i32,
+        obj_type: ObjectType,
+        reason: String,
+    },
+    #[error("Invalid {:?} '{}' in infra '{infra}': {reason}", .obj_ref.obj_type, .obj_ref.obj_id)]
+    InvalidObject {
+        infra: i32,
+        obj_ref: ObjectRef,
+        reason: String,
+    },
+    #[error("The cache of infra '{0}' isn't loaded")]
+    NotLoaded(i32),
+}
+
+impl ApiError for InfraCacheError {
+    fn get_status(&self) -> Status {
+        match self {
+            InfraCacheError::NotLoaded(_) => Status::NotFound,
+            _ => Status::InternalServerError,
+        }
+    }
+
+    fn get_type(&self) -> &'static str {
+        match self {
+            InfraCacheError::Query { .. } => "editoast:infra_cache:Query",
+            InfraCacheError::InvalidObject { .. } => "editoast:infra_cache:InvalidObject",
+            InfraCacheError::NotLoaded(_) => "editoast:infra_cache:NotLoaded",
+        }
+    }
+
+    fn extra(&self) -> Option<Map<String, Value>> {
+        match self {
+            InfraCacheError::Query {
+                infra, obj_type, ..
+            } => json!({ "infra": infra, "obj_type": obj_type }),
+            InfraCacheError::InvalidObject { infra, obj_ref, .. } => {
+                json!({ "infra": infra, "object": obj_ref })
+            }
+            InfraCacheError::NotLoaded(infra) => json!({ "infra": infra }),
+        }
+        .as_object()
+        .cloned()
+    }
+}
+
+/// Differences between a cache and a reference one, usually freshly loaded from the database
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct CacheDiff {
+    /// Objects of the reference cache missing from the cache
+    pub missing: Vec<ObjectRef>,
+    /// Objects of the cache absent from the reference cache
+    pub extra: Vec<ObjectRef>,
+    /// Objects that differ from the reference cache
+    pub mismatched: Vec<ObjectRef>,
+}
+
+impl CacheDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+}
+
 pub trait Cache: OSRDObject {
     /// Return the list of track section ids referenced by the object
     fn get_track_referenced_id(&self) -> Vec<&String>;
@@ -25,7 +110,7 @@ pub trait Cache: OSRDObject {
     fn get_object_cache(&self) -> ObjectCache;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum ObjectCache {
     TrackSection(TrackSectionCache),
     Signal(SignalCache),
@@ -186,28 +271,46 @@ impl ObjectCache {
     }
 }
 
+/// Return a value selected as text, failing if it is missing
+fn required(value: Option<String>, field: &'static str) -> Result<String, serde_json::Error> {
+    value.ok_or_else(|| de::Error::missing_field(field))
+}
+
+/// Parse a number selected as text
+fn parse_number<N>(value: String, field: &'static str) -> Result<N, serde_json::Error>
+where
+    N: FromStr,
+    N::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|err| de::Error::custom(format!("invalid `{field}` '{value}': {err}")))
+}
+
 #[derive(QueryableByName, Debug, Clone)]
 pub struct TrackQueryable {
     #[sql_type = "Text"]
     pub obj_id: String,
-    #[sql_type = "Double"]
-    pub length: f64,
+    #[sql_type = "Nullable<Text>"]
+    pub length: Option<String>,
     #[sql_type = "Text"]
     pub geo: String,
     #[sql_type = "Text"]
     pub sch: String,
 }
 
-impl From<TrackQueryable> for TrackSectionCache {
-    fn from(track: TrackQueryable) -> Self {
-        let geo: LineString = serde_json::from_str(&track.geo).unwrap();
-        let sch: LineString = serde_json::from_str(&track.sch).unwrap();
-        Self {
+impl TryFrom<TrackQueryable> for TrackSectionCache {
+    type Error = serde_json::Error;
+
+    fn try_from(track: TrackQueryable) -> Result<Self, Self::Error> {
+        let geo: LineString = serde_json::from_str(&track.geo)?;
+        let sch: LineString = serde_json::from_str(&track.sch)?;
+        Ok(Self {
             obj_id: track.obj_id,
-            length: track.length,
+            length: parse_number(required(track.length, "length")?, "length")?,
             bbox_geo: geo.get_bbox(),
             bbox_sch: sch.get_bbox(),
-        }
+        })
     }
 }
 
@@ -217,22 +320,27 @@ pub struct SpeedSectionQueryable {
     pub obj_id: String,
     #[sql_type = "Text"]
     pub track_ranges: String,
-    #[sql_type = "Nullable<Double>"]
-    pub speed_limit: Option<f64>,
+    #[sql_type = "Nullable<Text>"]
+    pub speed_limit: Option<String>,
     #[sql_type = "Text"]
     pub speed_limit_by_tag: String,
 }
 
-impl From<SpeedSectionQueryable> for SpeedSection {
-    fn from(speed: SpeedSectionQueryable) -> Self {
+impl TryFrom<SpeedSectionQueryable> for SpeedSection {
+    type Error = serde_json::Error;
+
+    fn try_from(speed: SpeedSectionQueryable) -> Result<Self, Self::Error> {
         let track_ranges: Vec<ApplicableDirectionsTrackRange> =
-            serde_json::from_str(&speed.track_ranges).unwrap();
-        SpeedSection {
+            serde_json::from_str(&speed.track_ranges)?;
+        Ok(SpeedSection {
             id: speed.obj_id.clone(),
-            speed_limit: speed.speed_limit,
-            speed_limit_by_tag: serde_json::from_str(&speed.speed_limit_by_tag).unwrap(),
+            speed_limit: speed
+                .speed_limit
+                .map(|speed_limit| parse_number(speed_limit, "speed_limit"))
+                .transpose()?,
+            speed_limit_by_tag: serde_json::from_str(&speed.speed_limit_by_tag)?,
             track_ranges,
-        }
+        })
     }
 }
 
@@ -250,20 +358,21 @@ pub struct RouteQueryable {
     pub path: String,
 }
 
-impl From<RouteQueryable> for Route {
-    fn from(route: RouteQueryable) -> Self {
-        let entry_point: ObjectRef = serde_json::from_str(&route.entry_point).unwrap();
-        let exit_point: ObjectRef = serde_json::from_str(&route.exit_point).unwrap();
-        let release_detectors: Vec<ObjectRef> =
-            serde_json::from_str(&route.release_detectors).unwrap();
-        let path: Vec<DirectionalTrackRange> = serde_json::from_str(&route.path).unwrap();
-        Route {
+impl TryFrom<RouteQueryable> for Route {
+    type Error = serde_json::Error;
+
+    fn try_from(route: RouteQueryable) -> Result<Self, Self::Error> {
+        let entry_point: ObjectRef = serde_json::from_str(&route.entry_point)?;
+        let exit_point: ObjectRef = serde_json::from_str(&route.exit_point)?;
+        let release_detectors: Vec<ObjectRef> = serde_json::from_str(&route.release_detectors)?;
+        let path: Vec<DirectionalTrackRange> = serde_json::from_str(&route.path)?;
+        Ok(Route {
             id: route.obj_id,
             entry_point,
             exit_point,
             release_detectors,
             path,
-        }
+        })
     }
 }
 
@@ -279,14 +388,16 @@ pub struct TrackSectionLinkQueryable {
     pub navigability: String,
 }
 
-impl From<TrackSectionLinkQueryable> for TrackSectionLink {
-    fn from(link: TrackSectionLinkQueryable) -> Self {
-        Self {
+impl TryFrom<TrackSectionLinkQueryable> for TrackSectionLink {
+    type Error = serde_json::Error;
+
+    fn try_from(link: TrackSectionLinkQueryable) -> Result<Self, Self::Error> {
+        Ok(Self {
             id: link.obj_id.clone(),
-            src: serde_json::from_str(&link.src).unwrap(),
-            dst: serde_json::from_str(&link.dst).unwrap(),
-            navigability: serde_json::from_str(&link.navigability).unwrap(),
-        }
+            src: serde_json::from_str(&link.src)?,
+            dst: serde_json::from_str(&link.dst)?,
+            navigability: serde_json::from_str(&link.navigability)?,
+        })
     }
 }
 
@@ -300,13 +411,15 @@ pub struct SwitchQueryable {
     pub ports: String,
 }
 
-impl From<SwitchQueryable> for SwitchCache {
-    fn from(switch: SwitchQueryable) -> Self {
-        Self {
+impl TryFrom<SwitchQueryable> for SwitchCache {
+    type Error = serde_json::Error;
+
+    fn try_from(switch: SwitchQueryable) -> Result<Self, Self::Error> {
+        Ok(Self {
             obj_id: switch.obj_id,
             switch_type: switch.switch_type,
-            ports: serde_json::from_str(&switch.ports).unwrap(),
-        }
+            ports: serde_json::from_str(&switch.ports)?,
+        })
     }
 }
 
@@ -320,13 +433,15 @@ pub struct SwitchTypeQueryable {
     pub groups: String,
 }
 
-impl From<SwitchTypeQueryable> for SwitchType {
-    fn from(switch_type: SwitchTypeQueryable) -> Self {
-        SwitchType {
+impl TryFrom<SwitchTypeQueryable> for SwitchType {
+    type Error = serde_json::Error;
+
+    fn try_from(switch_type: SwitchTypeQueryable) -> Result<Self, Self::Error> {
+        Ok(SwitchType {
             id: switch_type.obj_id,
-            ports: serde_json::from_str(&switch_type.ports).unwrap(),
-            groups: serde_json::from_str(&switch_type.groups).unwrap(),
-        }
+            ports: serde_json::from_str(&switch_type.ports)?,
+            groups: serde_json::from_str(&switch_type.groups)?,
+        })
     }
 }
 
@@ -336,14 +451,28 @@ pub struct OperationalPointQueryable {
     pub obj_id: String,
     #[sql_type = "Text"]
     pub parts: String,
+    #[sql_type = "Nullable<Text>"]
+    pub uic: Option<String>,
+    #[sql_type = "Text"]
+    pub trigram: String,
+    #[sql_type = "Text"]
+    pub name: String,
+    #[sql_type = "Text"]
+    pub ch: String,
 }
 
-impl From<OperationalPointQueryable> for OperationalPointCache {
-    fn from(op: OperationalPointQueryable) -> Self {
-        Self {
+impl TryFrom<OperationalPointQueryable> for OperationalPointCache {
+    type Error = serde_json::Error;
+
+    fn try_from(op: OperationalPointQueryable) -> Result<Self, Self::Error> {
+        Ok(Self {
             obj_id: op.obj_id,
-            parts: serde_json::from_str(&op.parts).unwrap(),
-        }
+            parts: serde_json::from_str(&op.parts)?,
+            uic: parse_number(required(op.uic, "uic")?, "uic")?,
+            trigram: op.trigram,
+            name: op.name,
+            ch: op.ch,
+        })
     }
 }
 
@@ -351,23 +480,95 @@ impl From<OperationalPointQueryable> for OperationalPointCache {
 pub struct CatenaryQueryable {
     #[sql_type = "Text"]
     pub obj_id: String,
-    #[sql_type = "Double"]
-    pub voltage: f64,
+    #[sql_type = "Nullable<Text>"]
+    pub voltage: Option<String>,
     #[sql_type = "Text"]
     pub track_ranges: String,
 }
 
-impl From<CatenaryQueryable> for Catenary {
-    fn from(catenary: CatenaryQueryable) -> Self {
-        Self {
+impl TryFrom<CatenaryQueryable> for Catenary {
+    type Error = serde_json::Error;
+
+    fn try_from(catenary: CatenaryQueryable) -> Result<Self, Self::Error> {
+        Ok(Self {
             id: catenary.obj_id,
-            voltage: catenary.voltage,
-            track_ranges: serde_json::from_str(&catenary.track_ranges).unwrap(),
-        }
+            voltage: parse_number(required(catenary.voltage, "voltage")?, "voltage")?,
+            track_ranges: serde_json::from_str(&catenary.track_ranges)?,
+        })
+    }
+}
+
+/// Track and position of the signals, detectors and buffer stops
+#[derive(QueryableByName, Debug, Clone)]
+pub struct TrackPointQueryable {
+    #[sql_type = "Text"]
+    pub obj_id: String,
+    #[sql_type = "Nullable<Text>"]
+    pub track: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    pub position: Option<String>,
+}
+
+impl TrackPointQueryable {
+    fn parse(self) -> Result<(String, String, f64), serde_json::Error> {
+        let track = required(self.track, "track")?;
+        let position = parse_number(required(self.position, "position")?, "position")?;
+        Ok((self.obj_id, track, position))
+    }
+}
+
+impl TryFrom<TrackPointQueryable> for SignalCache {
+    type Error = serde_json::Error;
+
+    fn try_from(signal: TrackPointQueryable) -> Result<Self, Self::Error> {
+        let (obj_id, track, position) = signal.parse()?;
+        Ok(Self {
+            obj_id,
+            track,
+            position,
+        })
+    }
+}
+
+impl TryFrom<TrackPointQueryable> for DetectorCache {
+    type Error = serde_json::Error;
+
+    fn try_from(detector: TrackPointQueryable) -> Result<Self, Self::Error> {
+        let (obj_id, track, position) = detector.parse()?;
+        Ok(Self {
+            obj_id,
+            track,
+            position,
+        })
+    }
+}
+
+impl TryFrom<TrackPointQueryable> for BufferStopCache {
+    type Error = serde_json::Error;
+
+    fn try_from(buffer_stop: TrackPointQueryable) -> Result<Self, Self::Error> {
+        let (obj_id, track, position) = buffer_stop.parse()?;
+        Ok(Self {
+            obj_id,
+            track,
+            position,
+        })
     }
 }
 
 impl InfraCache {
+    /// Rough estimate of the memory used by the cache, in bytes.
+    /// It only depends on the number of objects to be cheap to compute.
+    pub fn estimated_size(&self) -> usize {
+        let objects: usize = self.objects.values().map(HashMap::len).sum();
+        let refs: usize = self.track_sections_refs.values().map(HashSet::len).sum();
+        size_of::<Self>()
+            + objects * (size_of::<String>() + size_of::<ObjectCache>() + OBJECT_HEAP_ESTIMATE)
+            + self.track_sections_refs.len()
+                * (size_of::<String>() + size_of::<HashSet<ObjectRef>>())
+            + refs * size_of::<ObjectRef>()
+    }
+
     /// Add an object to the cache.
     /// If the object already exists, it will fails.
     pub fn add<T: Cache>(&mut self, obj: T) {
@@ -438,99 +639,210 @@ impl InfraCache {
         &self.objects[ObjectType::Catenary]
     }
 
-    /// Given an infra id load infra cache from database
-    pub fn load(conn: &PgConnection, infra_id: i32) -> InfraCache {
+    /// Retrieve the cache of objects of a given type
+    pub fn get_objects_by_type(&self, obj_type: ObjectType) -> &HashMap<String, ObjectCache> {
+        &self.objects[obj_type]
+    }
+
+    /// Given an infra id load infra cache from database.
+    /// Fails on the first object that can't be loaded.
+    pub fn load(conn: &PgConnection, infra_id: i32) -> Result<InfraCache, InfraCacheError> {
         let mut infra_cache = Self::default();
 
         // Load track sections list
-        sql_query(
-            "SELECT obj_id, (data->>'length')::float as length, data->>'geo' as geo, data->>'sch' as sch FROM osrd_infra_tracksectionmodel WHERE infra_id = $1",
-        )
-        .bind::<Integer, _>(infra_id)
-        .load::<TrackQueryable>(conn)
-        .expect("Error loading track sections").into_iter().for_each(|track| infra_cache.add::<TrackSectionCache>(track.into()));
+        infra_cache.load_objects::<TrackQueryable, TrackSectionCache>(
+            conn,
+            infra_id,
+            ObjectType::TrackSection,
+            "SELECT obj_id, data->>'length' AS length, data->>'geo' as geo, data->>'sch' as sch FROM osrd_infra_tracksectionmodel WHERE infra_id = $1",
+            |track| &track.obj_id,
+        )?;
 
         // Load signal tracks references
-        sql_query(
-            "SELECT obj_id, data->'track'->>'id' AS track, (data->>'position')::float AS position FROM osrd_infra_signalmodel WHERE infra_id = $1")
-        .bind::<Integer, _>(infra_id)
-        .load::<SignalCache>(conn).expect("Error loading signal refs").into_iter().for_each(|signal| 
-            infra_cache.add(signal)
-        );
+        infra_cache.load_objects::<TrackPointQueryable, SignalCache>(
+            conn,
+            infra_id,
+            ObjectType::Signal,
+            "SELECT obj_id, data->'track'->>'id' AS track, data->>'position' AS position FROM osrd_infra_signalmodel WHERE infra_id = $1",
+            |signal| &signal.obj_id,
+        )?;
 
         // Load speed sections tracks references
-        sql_query(
-            "SELECT obj_id, data->>'track_ranges' AS track_ranges, (data->>'speed_limit')::float AS speed_limit, data->>'speed_limit_by_tag' AS speed_limit_by_tag FROM osrd_infra_speedsectionmodel WHERE infra_id = $1")
-        .bind::<Integer, _>(infra_id)
-        .load::<SpeedSectionQueryable>(conn).expect("Error loading speed section refs").into_iter().for_each(|speed| 
-            infra_cache.add::<SpeedSection>(speed.into())
-        );
+        infra_cache.load_objects::<SpeedSectionQueryable, SpeedSection>(
+            conn,
+            infra_id,
+            ObjectType::SpeedSection,
+            "SELECT obj_id, data->>'track_ranges' AS track_ranges, data->>'speed_limit' AS speed_limit, data->>'speed_limit_by_tag' AS speed_limit_by_tag FROM osrd_infra_speedsectionmodel WHERE infra_id = $1",
+            |speed| &speed.obj_id,
+        )?;
 
         // Load routes tracks references
-        sql_query(
-            "SELECT obj_id, data->>'entry_point' AS entry_point, data->>'exit_point' AS exit_point, data->>'release_detectors' AS release_detectors, data->>'path' AS path FROM osrd_infra_routemodel WHERE infra_id = $1")
-        .bind::<Integer, _>(infra_id)
-        .load::<RouteQueryable>(conn).expect("Error loading route refs").into_iter().for_each(|route| 
-            infra_cache.add::<Route>(route.into())
-        );
+        infra_cache.load_objects::<RouteQueryable, Route>(
+            conn,
+            infra_id,
+            ObjectType::Route,
+            "SELECT obj_id, data->>'entry_point' AS entry_point, data->>'exit_point' AS exit_point, data->>'release_detectors' AS release_detectors, data->>'path' AS path FROM osrd_infra_routemodel WHERE infra_id = $1",
+            |route| &route.obj_id,
+        )?;
 
         // Load operational points tracks references
-        sql_query(
-            "SELECT obj_id, data->>'parts' AS parts FROM osrd_infra_operationalpointmodel WHERE infra_id = $1")
-        .bind::<Integer, _>(infra_id)
-        .load::<OperationalPointQueryable>(conn).expect("Error loading operational point refs").into_iter().for_each(|op| 
-            infra_cache.add::<OperationalPointCache>(op.into())
-        );
+        infra_cache.load_objects::<OperationalPointQueryable, OperationalPointCache>(
+            conn,
+            infra_id,
+            ObjectType::OperationalPoint,
+            "SELECT obj_id, data->>'parts' AS parts, data->>'uic' AS uic, data->>'trigram' AS trigram, data->>'name' AS name, data->>'ch' AS ch FROM osrd_infra_operationalpointmodel WHERE infra_id = $1",
+            |op| &op.obj_id,
+        )?;
 
         // Load track section links tracks references
-        sql_query(
-            "SELECT obj_id, data->>'src' AS src, data->>'dst' AS dst, (data->'navigability')::text as navigability FROM osrd_infra_tracksectionlinkmodel WHERE infra_id = $1")
-        .bind::<Integer, _>(infra_id)
-        .load::<TrackSectionLinkQueryable>(conn).expect("Error loading track section link refs").into_iter().for_each(|link| 
-            infra_cache.add::<TrackSectionLink>(link.into())
-        );
+        infra_cache.load_objects::<TrackSectionLinkQueryable, TrackSectionLink>(
+            conn,
+            infra_id,
+            ObjectType::TrackSectionLink,
+            "SELECT obj_id, data->>'src' AS src, data->>'dst' AS dst, (data->'navigability')::text as navigability FROM osrd_infra_tracksectionlinkmodel WHERE infra_id = $1",
+            |link| &link.obj_id,
+        )?;
 
         // Load switch tracks references
-        sql_query(
-            "SELECT obj_id, data->'switch_type'->>'id' AS switch_type, data->>'ports' AS ports FROM osrd_infra_switchmodel WHERE infra_id = $1")
-        .bind::<Integer, _>(infra_id)
-        .load::<SwitchQueryable>(conn).expect("Error loading switch refs").into_iter().for_each(|switch| {
-            infra_cache.add::<SwitchCache>(switch.into());
-        });
+        infra_cache.load_objects::<SwitchQueryable, SwitchCache>(
+            conn,
+            infra_id,
+            ObjectType::Switch,
+            "SELECT obj_id, data->'switch_type'->>'id' AS switch_type, data->>'ports' AS ports FROM osrd_infra_switchmodel WHERE infra_id = $1",
+            |switch| &switch.obj_id,
+        )?;
 
         // Load switch types references
-        sql_query(
-            "SELECT obj_id, data->>'ports' AS ports, data->>'groups' AS groups FROM osrd_infra_switchtypemodel WHERE infra_id = $1")
-        .bind::<Integer, _>(infra_id)
-        .load::<SwitchTypeQueryable>(conn).expect("Error loading switch types refs").into_iter().for_each(|switch_type| 
-            infra_cache.add::<SwitchType>(switch_type.into())
-        );
+        infra_cache.load_objects::<SwitchTypeQueryable, SwitchType>(
+            conn,
+            infra_id,
+            ObjectType::SwitchType,
+            "SELECT obj_id, data->>'ports' AS ports, data->>'groups' AS groups FROM osrd_infra_switchtypemodel WHERE infra_id = $1",
+            |switch_type| &switch_type.obj_id,
+        )?;
 
         // Load detector tracks references
-        sql_query(
-            "SELECT obj_id, data->'track'->>'id' AS track, (data->>'position')::float AS position FROM osrd_infra_detectormodel WHERE infra_id = $1")
-        .bind::<Integer, _>(infra_id)
-        .load::<DetectorCache>(conn).expect("Error loading detector refs").into_iter().for_each(|detector| 
-            infra_cache.add(detector)
-        );
+        infra_cache.load_objects::<TrackPointQueryable, DetectorCache>(
+            conn,
+            infra_id,
+            ObjectType::Detector,
+            "SELECT obj_id, data->'track'->>'id' AS track, data->>'position' AS position FROM osrd_infra_detectormodel WHERE infra_id = $1",
+            |detector| &detector.obj_id,
+        )?;
 
         // Load buffer stop tracks references
-        sql_query(
-            "SELECT obj_id, data->'track'->>'id' AS track, (data->>'position')::float AS position FROM osrd_infra_bufferstopmodel WHERE infra_id = $1")
-        .bind::<Integer, _>(infra_id)
-        .load::<BufferStopCache>(conn).expect("Error loading buffer stop refs").into_iter().for_each(|buffer_stop| 
-            infra_cache.add(buffer_stop)
-        );
+        infra_cache.load_objects::<TrackPointQueryable, BufferStopCache>(
+            conn,
+            infra_id,
+            ObjectType::BufferStop,
+            "SELECT obj_id, data->'track'->>'id' AS track, data->>'position' AS position FROM osrd_infra_bufferstopmodel WHERE infra_id = $1",
+            |buffer_stop| &buffer_stop.obj_id,
+        )?;
 
         // Load catenary tracks references
-        sql_query(
-            "SELECT obj_id, (data->>'voltage')::float AS voltage, data->>'track_ranges' AS track_ranges FROM osrd_infra_catenarymodel WHERE infra_id = $1")
-        .bind::<Integer, _>(infra_id)
-        .load::<CatenaryQueryable>(conn).expect("Error loading catenary refs").into_iter().for_each(|catenary| 
-            infra_cache.add::<Catenary>(catenary.into())
-        );
-
-        infra_cache
+        infra_cache.load_objects::<CatenaryQueryable, Catenary>(
+            conn,
+            infra_id,
+            ObjectType::Catenary,
+            "SELECT obj_id, data->>'voltage' AS voltage, data->>'track_ranges' AS track_ranges FROM osrd_infra_catenarymodel WHERE infra_id = $1",
+            |catenary| &catenary.obj_id,
+        )?;
+
+        Ok(infra_cache)
+    }
+
+    /// Run a query loading objects of a given type and add them to the cache
+    fn load_objects<Q, T>(
+        &mut self,
+        conn: &PgConnection,
+        infra_id: i32,
+        obj_type: ObjectType,
+        query: &str,
+        obj_id: fn(&Q) -> &String,
+    ) -> Result<(), InfraCacheError>
+    where
+        Q: diesel::deserialize::QueryableByName<Pg>,
+        T: Cache + TryFrom<Q>,
+        T::Error: ToString,
+    {
+        let rows = sql_query(query)
+            .bind::<Integer, _>(infra_id)
+            .load::<Q>(conn)
+            .map_err(|err| InfraCacheError::Query {
+                infra: infra_id,
+                obj_type,
+                reason: err.to_string(),
+            })?;
+        for row in rows {
+            let obj_ref = ObjectRef::new(obj_type, obj_id(&row));
+            let obj = T::try_from(row).map_err(|err| InfraCacheError::InvalidObject {
+                infra: infra_id,
+                obj_ref,
+                reason: err.to_string(),
+            })?;
+            self.add(obj);
+        }
+        Ok(())
+    }
+
+    /// Write a binary snapshot of the cache, tagged with the infra version it reflects
+    pub fn save_snapshot(&self, path: &Path, version: &str) -> bincode::Result<()> {
+        // Write then rename to never leave a truncated file
+        let tmp_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        bincode::serialize_into(&mut writer, &(SNAPSHOT_FORMAT, version))?;
+        bincode::serialize_into(&mut writer, self)?;
+        writer.flush()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Read a snapshot of the cache.
+    /// Return `None` if there is none or if it was taken at another infra version.
+    pub fn load_snapshot(path: &Path, version: &str) -> bincode::Result<Option<InfraCache>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let mut reader = BufReader::new(File::open(path)?);
+        let (format, snapshot_version): (u32, String) = bincode::deserialize_from(&mut reader)?;
+        if format != SNAPSHOT_FORMAT || snapshot_version != version {
+            return Ok(None);
+        }
+        Ok(Some(bincode::deserialize_from(&mut reader)?))
+    }
+
+    /// Compare the cache with a reference one.
+    /// Objects are compared through their serialization, not to rely on their `PartialEq` that
+    /// only compares ids.
+    pub fn diff(&self, reference: &InfraCache) -> CacheDiff {
+        let mut diff = CacheDiff::default();
+        for (obj_type, objects) in self.objects.iter() {
+            let reference_objects = &reference.objects[obj_type];
+            for (obj_id, obj) in objects {
+                match reference_objects.get(obj_id) {
+                    None => diff.extra.push(ObjectRef::new(obj_type, obj_id)),
+                    Some(reference_obj)
+                        if serde_json::to_value(obj).unwrap()
+                            != serde_json::to_value(reference_obj).unwrap() =>
+                    {
+                        diff.mismatched.push(ObjectRef::new(obj_type, obj_id))
+                    }
+                    Some(_) => (),
+                }
+            }
+            diff.missing.extend(
+                reference_objects
+                    .keys()
+                    .filter(|obj_id| !objects.contains_key(*obj_id))
+                    .map(|obj_id| ObjectRef::new(obj_type, obj_id)),
+            );
+        }
+        for refs in [&mut diff.missing, &mut diff.extra, &mut diff.mismatched] {
+            refs.sort_by(|a, b| {
+                (a.obj_type as usize, &a.obj_id).cmp(&(b.obj_type as usize, &b.obj_id))
+            });
+        }
+        diff
     }
 
     /// Get all track sections references of a given track and type
@@ -629,14 +941,17 @@ pub mod tests {
     };
 
     use super::{
-        BufferStopCache, DetectorCache, OperationalPointCache, SignalCache, TrackSectionCache,
+        BufferStopCache, DetectorCache, InfraCacheError, OperationalPointCache, SignalCache,
+        TrackPointQueryable, TrackSectionCache,
     };
+    use diesel::sql_types::Integer;
+    use diesel::{sql_query, RunQueryDsl};
 
     #[test]
     fn load_track_section() {
         test_transaction(|conn, infra| {
             let track = create_track(conn, infra.id, Default::default());
-            let infra_cache = InfraCache::load(conn, infra.id);
+            let infra_cache = InfraCache::load(conn, infra.id).unwrap();
             assert_eq!(infra_cache.track_sections().len(), 1);
             assert!(infra_cache.track_sections().contains_key(track.get_id()));
         });
@@ -647,7 +962,7 @@ pub mod tests {
         test_transaction(|conn, infra| {
             let signal = create_signal(conn, infra.id, Default::default());
 
-            let infra_cache = InfraCache::load(conn, infra.id);
+            let infra_cache = InfraCache::load(conn, infra.id).unwrap();
 
             assert!(infra_cache.signals().contains_key(signal.get_id()));
             let refs = infra_cache.track_sections_refs;
@@ -667,7 +982,7 @@ pub mod tests {
                 },
             );
 
-            let infra_cache = InfraCache::load(conn, infra.id);
+            let infra_cache = InfraCache::load(conn, infra.id).unwrap();
 
             assert!(infra_cache.speed_sections().contains_key(speed.get_id()));
             let refs = infra_cache.track_sections_refs;
@@ -687,7 +1002,7 @@ pub mod tests {
                 },
             );
 
-            let infra_cache = InfraCache::load(conn, infra.id);
+            let infra_cache = InfraCache::load(conn, infra.id).unwrap();
 
             assert!(infra_cache.routes().contains_key(route.get_id()));
             let refs = infra_cache.track_sections_refs;
@@ -695,6 +1010,90 @@ pub mod tests {
         })
     }
 
+    #[test]
+    fn load_invalid_object() {
+        test_transaction(|conn, infra| {
+            let route = create_route(conn, infra.id, Default::default());
+            sql_query(
+                "UPDATE osrd_infra_routemodel SET data = jsonb_set(data, '{path}', '\"oops\"') WHERE infra_id = $1",
+            )
+            .bind::<Integer, _>(infra.id)
+            .execute(conn)
+            .unwrap();
+
+            match InfraCache::load(conn, infra.id) {
+                Err(InfraCacheError::InvalidObject { obj_ref, .. }) => {
+                    assert_eq!(obj_ref, route.get_ref())
+                }
+                res => panic!(
+                    "expected an invalid object error, got {:?}",
+                    res.map(|_| ())
+                ),
+            }
+        })
+    }
+
+    #[test]
+    fn parse_track_point() {
+        let queryable = |track: Option<&str>, position: Option<&str>| TrackPointQueryable {
+            obj_id: "signal".into(),
+            track: track.map(Into::into),
+            position: position.map(Into::into),
+        };
+        let signal = SignalCache::try_from(queryable(Some("track"), Some("42.5"))).unwrap();
+        assert_eq!(signal.track, "track");
+        assert_eq!(signal.position, 42.5);
+        assert!(SignalCache::try_from(queryable(None, Some("42.5"))).is_err());
+        assert!(DetectorCache::try_from(queryable(Some("track"), Some("oops"))).is_err());
+        assert!(BufferStopCache::try_from(queryable(Some("track"), None)).is_err());
+    }
+
+    #[test]
+    fn load_signal_without_track() {
+        test_transaction(|conn, infra| {
+            let signal = create_signal(conn, infra.id, Default::default());
+            sql_query(
+                "UPDATE osrd_infra_signalmodel SET data = data - 'track' WHERE infra_id = $1",
+            )
+            .bind::<Integer, _>(infra.id)
+            .execute(conn)
+            .unwrap();
+
+            match InfraCache::load(conn, infra.id) {
+                Err(InfraCacheError::InvalidObject { obj_ref, .. }) => {
+                    assert_eq!(obj_ref, signal.get_ref())
+                }
+                res => panic!(
+                    "expected an invalid object error, got {:?}",
+                    res.map(|_| ())
+                ),
+            }
+        })
+    }
+
+    #[test]
+    fn load_operational_point_invalid_uic() {
+        test_transaction(|conn, infra| {
+            let op = create_op(conn, infra.id, Default::default());
+            sql_query(
+                "UPDATE osrd_infra_operationalpointmodel SET data = jsonb_set(data, '{uic}', '\"oops\"') WHERE infra_id = $1",
+            )
+            .bind::<Integer, _>(infra.id)
+            .execute(conn)
+            .unwrap();
+
+            match InfraCache::load(conn, infra.id) {
+                Err(InfraCacheError::InvalidObject { obj_ref, .. }) => {
+                    assert_eq!(obj_ref, op.get_ref())
+                }
+                res => panic!(
+                    "expected an invalid object error, got {:?}",
+                    res.map(|_| ())
+                ),
+            }
+        })
+    }
+
     #[test]
     fn load_operational_point() {
         test_transaction(|conn, infra| {
@@ -707,7 +1106,7 @@ pub mod tests {
                 },
             );
 
-            let infra_cache = InfraCache::load(conn, infra.id);
+            let infra_cache = InfraCache::load(conn, infra.id).unwrap();
 
             assert!(infra_cache.operational_points().contains_key(op.get_id()));
             let refs = infra_cache.track_sections_refs;
@@ -719,7 +1118,7 @@ pub mod tests {
     fn load_track_section_link() {
         test_transaction(|conn, infra| {
             let link = create_link(conn, infra.id, Default::default());
-            let infra_cache = InfraCache::load(conn, infra.id);
+            let infra_cache = InfraCache::load(conn, infra.id).unwrap();
             assert!(infra_cache
                 .track_section_links()
                 .contains_key(link.get_id()));
@@ -737,7 +1136,7 @@ pub mod tests {
                     ..Default::default()
                 },
             );
-            let infra_cache = InfraCache::load(conn, infra.id);
+            let infra_cache = InfraCache::load(conn, infra.id).unwrap();
             assert!(infra_cache.switches().contains_key(switch.get_id()));
         })
     }
@@ -746,7 +1145,7 @@ pub mod tests {
     fn load_switch_type() {
         test_transaction(|conn, infra| {
             let s_type = create_switch_type(conn, infra.id, Default::default());
-            let infra_cache = InfraCache::load(conn, infra.id);
+            let infra_cache = InfraCache::load(conn, infra.id).unwrap();
             assert!(infra_cache.switch_types().contains_key(s_type.get_id()));
         })
     }
@@ -756,7 +1155,7 @@ pub mod tests {
         test_transaction(|conn, infra| {
             let detector = create_detector(conn, infra.id, Default::default());
 
-            let infra_cache = InfraCache::load(conn, infra.id);
+            let infra_cache = InfraCache::load(conn, infra.id).unwrap();
 
             assert!(infra_cache.detectors().contains_key(detector.get_id()));
             let refs = infra_cache.track_sections_refs;
@@ -769,7 +1168,7 @@ pub mod tests {
         test_transaction(|conn, infra| {
             let bs = create_buffer_stop(conn, infra.id, Default::default());
 
-            let infra_cache = InfraCache::load(conn, infra.id);
+            let infra_cache = InfraCache::load(conn, infra.id).unwrap();
 
             assert!(infra_cache.buffer_stops().contains_key(bs.get_id()));
             let refs = infra_cache.track_sections_refs;
@@ -789,7 +1188,7 @@ pub mod tests {
                 },
             );
 
-            let infra_cache = InfraCache::load(conn, infra.id);
+            let infra_cache = InfraCache::load(conn, infra.id).unwrap();
 
             assert!(infra_cache.catenaries().contains_key(catenary.get_id()));
             let refs = infra_cache.track_sections_refs;
@@ -853,13 +1252,13 @@ pub mod tests {
         track: T,
         position: f64,
     ) -> OperationalPointCache {
-        OperationalPointCache {
-            obj_id: obj_id.as_ref().into(),
-            parts: vec![OperationalPointPart {
+        OperationalPointCache::new(
+            obj_id.as_ref().into(),
+            vec![OperationalPointPart {
                 track: ObjectRef::new(ObjectType::TrackSection, track.as_ref()),
                 position,
             }],
-        }
+        )
     }
 
     pub fn create_speed_section_cache<T: AsRef<str>>(
@@ -1091,4 +1490,30 @@ pub mod tests {
         assert!(routes::generate_errors(&small_infra_cache, &graph).is_empty());
         assert!(operational_points::generate_errors(&small_infra_cache).is_empty());
     }
+
+    #[test]
+    fn diff_with_reference() {
+        let reference = create_small_infra_cache();
+        let mut infra_cache = create_small_infra_cache();
+        assert!(infra_cache.diff(&reference).is_empty());
+
+        infra_cache.apply_delete(&ObjectRef::new(ObjectType::BufferStop, "BF1"));
+        infra_cache.apply_delete(&ObjectRef::new(ObjectType::Detector, "D1"));
+        infra_cache.add(create_detector_cache("D1", "B", 300.));
+        infra_cache.add(create_track_section_cache("E".into(), 500.));
+
+        let diff = infra_cache.diff(&reference);
+        assert_eq!(
+            diff.missing,
+            vec![ObjectRef::new(ObjectType::BufferStop, "BF1")]
+        );
+        assert_eq!(
+            diff.extra,
+            vec![ObjectRef::new(ObjectType::TrackSection, "E")]
+        );
+        assert_eq!(
+            diff.mismatched,
+            vec![ObjectRef::new(ObjectType::Detector, "D1")]
+        );
+    }
 }
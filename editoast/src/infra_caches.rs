@@ -0,0 +1,469 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chashmap::{CHashMap, ReadGuard, WriteGuard};
+use colored::Colorize;
+use diesel::PgConnection;
+
+use crate::infra_cache::{CacheDiff, InfraCache, InfraCacheError};
+use crate::models::Infra;
+
+#[derive(Debug, Clone, Copy)]
+struct Usage {
+    last_access: u64,
+    /// Estimated size of the cache, updated on each access
+    size: usize,
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    clock: u64,
+    usages: HashMap<i32, Usage>,
+}
+
+/// Caches of the infras, loaded on first access.
+/// When a memory budget is set, the least recently used caches are evicted to stay under it.
+/// When a snapshot directory is set, caches loaded from the database are saved to it and
+/// reloaded from it as long as the infra version doesn't change.
+/// An infra whose cache fails to load is marked as broken and isn't loaded again until reloaded.
+///
+/// The map lock of a cache must never be taken while holding the LRU lock: evicting a cache
+/// waits for the requests using it to release it.
+#[derive(Debug, Default)]
+pub struct InfraCaches {
+    caches: CHashMap<i32, InfraCache>,
+    lru: Mutex<LruState>,
+    /// Memory budget in bytes, caches are never evicted if not set
+    memory_budget: Option<usize>,
+    /// Directory of the cache snapshots, caches aren't snapshotted if not set
+    snapshot_dir: Option<PathBuf>,
+    /// Infras whose cache failed to load
+    broken: Mutex<HashMap<i32, InfraCacheError>>,
+}
+
+impl InfraCaches {
+    pub fn new(memory_budget: Option<usize>, snapshot_dir: Option<PathBuf>) -> Self {
+        Self {
+            memory_budget,
+            snapshot_dir,
+            ..Default::default()
+        }
+    }
+
+    /// Return the cache of an infra, loading it if needed.
+    /// The infra must exist, an unknown infra gets an empty cache.
+    pub fn get(
+        &self,
+        conn: &PgConnection,
+        infra_id: i32,
+    ) -> Result<ReadGuard<'_, i32, InfraCache>, InfraCacheError> {
+        self.get_or_load(infra_id, || self.load(conn, infra_id))
+    }
+
+    /// Return the cache of an infra to update it, loading it if needed.
+    /// The infra must exist, an unknown infra gets an empty cache.
+    pub fn get_mut(
+        &self,
+        conn: &PgConnection,
+        infra_id: i32,
+    ) -> Result<WriteGuard<'_, i32, InfraCache>, InfraCacheError> {
+        self.get_mut_or_load(infra_id, || self.load(conn, infra_id))
+    }
+
+    /// Return the cache of an infra if it is loaded, without loading it
+    pub fn get_loaded(&self, infra_id: i32) -> Option<ReadGuard<'_, i32, InfraCache>> {
+        self.caches.get(&infra_id)
+    }
+
+    /// Load the cache of the infras whose snapshot matches their current version,
+    /// as long as they fit in the memory budget. Return the number of loaded caches.
+    pub fn load_snapshots(&self, conn: &PgConnection) -> usize {
+        let mut total_size = 0;
+        let mut loaded = 0;
+        for infra in Infra::list(conn) {
+            let cache = match self.read_snapshot(infra.id, &infra.version) {
+                Some(cache) => cache,
+                None => continue,
+            };
+            total_size += cache.estimated_size();
+            if matches!(self.memory_budget, Some(budget) if total_size > budget) {
+                break;
+            }
+            self.insert_new(infra.id, cache);
+            loaded += 1;
+        }
+        loaded
+    }
+
+    /// Reload the cache of an infra from the database, replacing the loaded one if any.
+    /// Return the differences the loaded cache had with the database.
+    /// Edits of the infra wait for the reload to complete.
+    /// On failure, the loaded cache is dropped and the infra is marked as broken.
+    pub fn reload(&self, conn: &PgConnection, infra_id: i32) -> Result<CacheDiff, InfraCacheError> {
+        self.replace(infra_id, || self.load_from_db(conn, infra_id))
+    }
+
+    fn replace<F>(&self, infra_id: i32, load: F) -> Result<CacheDiff, InfraCacheError>
+    where
+        F: FnOnce() -> Result<InfraCache, InfraCacheError>,
+    {
+        let mut result = Ok(CacheDiff::default());
+        let mut size = 0;
+        self.caches.alter(infra_id, |previous| match load() {
+            Ok(cache) => {
+                if let Some(previous) = previous {
+                    result = Ok(previous.diff(&cache));
+                }
+                size = cache.estimated_size();
+                Some(cache)
+            }
+            Err(err) => {
+                result = Err(err);
+                None
+            }
+        });
+        match &result {
+            Ok(_) => {
+                self.broken.lock().unwrap().remove(&infra_id);
+                self.touch(infra_id, size);
+                self.evict(infra_id);
+            }
+            Err(err) => {
+                self.lru.lock().unwrap().usages.remove(&infra_id);
+                self.mark_broken(infra_id, err);
+            }
+        }
+        result
+    }
+
+    /// Load the cache of an infra from its snapshot if it is up to date, else from the database
+    fn load(&self, conn: &PgConnection, infra_id: i32) -> Result<InfraCache, InfraCacheError> {
+        let snapshot = self
+            .snapshot_dir
+            .as_ref()
+            .and_then(|_| Infra::retrieve(conn, infra_id).ok())
+            .and_then(|infra| self.read_snapshot(infra_id, &infra.version));
+        match snapshot {
+            Some(cache) => Ok(cache),
+            None => self.load_from_db(conn, infra_id),
+        }
+    }
+
+    /// Load the cache of an infra from the database and snapshot it.
+    /// The snapshot is tagged with the version read before loading the cache,
+    /// a concurrent edit can only make the snapshot outdated.
+    fn load_from_db(
+        &self,
+        conn: &PgConnection,
+        infra_id: i32,
+    ) -> Result<InfraCache, InfraCacheError> {
+        let version = match (&self.snapshot_dir, Infra::retrieve(conn, infra_id)) {
+            (Some(_), Ok(infra)) => infra.version,
+            _ => return InfraCache::load(conn, infra_id),
+        };
+        let cache = InfraCache::load(conn, infra_id)?;
+        self.write_snapshot(infra_id, &version, &cache);
+        Ok(cache)
+    }
+
+    fn mark_broken(&self, infra_id: i32, err: &InfraCacheError) {
+        eprintln!("{}", format!("Infra cache marked as broken: {err}").red());
+        self.broken.lock().unwrap().insert(infra_id, err.clone());
+    }
+
+    /// Error that made the cache of an infra fail to load, if it is broken
+    fn broken_error(&self, infra_id: i32) -> Option<InfraCacheError> {
+        self.broken.lock().unwrap().get(&infra_id).cloned()
+    }
+
+    fn snapshot_path(&self, infra_id: i32) -> Option<PathBuf> {
+        self.snapshot_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("infra_{infra_id}.bin")))
+    }
+
+    /// Read the snapshot of an infra cache if it was taken at the given version.
+    /// Unreadable snapshots are ignored.
+    fn read_snapshot(&self, infra_id: i32, version: &str) -> Option<InfraCache> {
+        let path = self.snapshot_path(infra_id)?;
+        match InfraCache::load_snapshot(&path, version) {
+            Ok(cache) => cache,
+            Err(err) => {
+                eprintln!(
+                    "{}",
+                    format!("Ignoring infra cache snapshot {path:?}: {err}").red()
+                );
+                None
+            }
+        }
+    }
+
+    fn write_snapshot(&self, infra_id: i32, version: &str, cache: &InfraCache) {
+        let path = match self.snapshot_path(infra_id) {
+            Some(path) => path,
+            None => return,
+        };
+        if let Err(err) = cache.save_snapshot(&path, version) {
+            eprintln!(
+                "{}",
+                format!("Failed to save infra cache snapshot to {path:?}: {err}").red()
+            );
+        }
+    }
+
+    fn get_or_load<F>(
+        &self,
+        infra_id: i32,
+        load: F,
+    ) -> Result<ReadGuard<'_, i32, InfraCache>, InfraCacheError>
+    where
+        F: Fn() -> Result<InfraCache, InfraCacheError>,
+    {
+        loop {
+            self.ensure_loaded(infra_id, &load)?;
+            if let Some(cache) = self.caches.get(&infra_id) {
+                self.touch(infra_id, cache.estimated_size());
+                return Ok(cache);
+            }
+            // Evicted by another request in the meantime
+        }
+    }
+
+    fn get_mut_or_load<F>(
+        &self,
+        infra_id: i32,
+        load: F,
+    ) -> Result<WriteGuard<'_, i32, InfraCache>, InfraCacheError>
+    where
+        F: Fn() -> Result<InfraCache, InfraCacheError>,
+    {
+        loop {
+            self.ensure_loaded(infra_id, &load)?;
+            if let Some(cache) = self.caches.get_mut(&infra_id) {
+                self.touch(infra_id, cache.estimated_size());
+                return Ok(cache);
+            }
+            // Evicted by another request in the meantime
+        }
+    }
+
+    /// Load the cache of an infra if it isn't, unless it is broken.
+    /// Concurrent first accesses may load it twice, only one of the caches is kept.
+    fn ensure_loaded<F>(&self, infra_id: i32, load: &F) -> Result<(), InfraCacheError>
+    where
+        F: Fn() -> Result<InfraCache, InfraCacheError>,
+    {
+        if self.caches.contains_key(&infra_id) {
+            return Ok(());
+        }
+        if let Some(err) = self.broken_error(infra_id) {
+            return Err(err);
+        }
+        let cache = load().inspect_err(|err| self.mark_broken(infra_id, err))?;
+        let size = cache.estimated_size();
+        self.caches.upsert(infra_id, || cache, |_| ());
+        self.touch(infra_id, size);
+        self.evict(infra_id);
+        Ok(())
+    }
+
+    /// Insert the cache of a new infra
+    pub fn insert_new(&self, infra_id: i32, cache: InfraCache) {
+        let size = cache.estimated_size();
+        self.caches.insert(infra_id, cache);
+        self.touch(infra_id, size);
+        self.evict(infra_id);
+    }
+
+    /// Drop the cache of an infra and its snapshot
+    pub fn remove(&self, infra_id: i32) {
+        self.lru.lock().unwrap().usages.remove(&infra_id);
+        self.broken.lock().unwrap().remove(&infra_id);
+        self.caches.remove(&infra_id);
+        if let Some(path) = self.snapshot_path(infra_id).filter(|path| path.exists()) {
+            if let Err(err) = fs::remove_file(&path) {
+                eprintln!(
+                    "{}",
+                    format!("Failed to remove infra cache snapshot {path:?}: {err}").red()
+                );
+            }
+        }
+    }
+
+    /// Whether the cache of an infra is loaded
+    pub fn is_loaded(&self, infra_id: i32) -> bool {
+        self.caches.contains_key(&infra_id)
+    }
+
+    fn touch(&self, infra_id: i32, size: usize) {
+        let mut lru = self.lru.lock().unwrap();
+        lru.clock += 1;
+        let last_access = lru.clock;
+        lru.usages.insert(infra_id, Usage { last_access, size });
+    }
+
+    /// Evict the least recently used caches but `keep` until the memory budget is respected
+    fn evict(&self, keep: i32) {
+        let budget = match self.memory_budget {
+            Some(budget) => budget,
+            None => return,
+        };
+        let victims = {
+            let mut lru = self.lru.lock().unwrap();
+            let mut total: usize = lru.usages.values().map(|usage| usage.size).sum();
+            let mut candidates: Vec<_> = lru
+                .usages
+                .iter()
+                .filter(|(infra_id, _)| **infra_id != keep)
+                .map(|(infra_id, usage)| (usage.last_access, *infra_id))
+                .collect();
+            candidates.sort_unstable();
+            let mut victims = vec![];
+            for (_, infra_id) in candidates {
+                if total <= budget {
+                    break;
+                }
+                total -= lru.usages.remove(&infra_id).unwrap().size;
+                victims.push(infra_id);
+            }
+            victims
+        };
+        for infra_id in victims {
+            self.caches.remove(&infra_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InfraCaches;
+    use crate::infra_cache::tests::create_track_section_cache;
+    use crate::infra_cache::{InfraCache, InfraCacheError};
+    use crate::objects::{ObjectRef, ObjectType};
+    use std::cell::Cell;
+
+    fn cache(nb_tracks: usize) -> InfraCache {
+        let mut cache = InfraCache::default();
+        for i in 0..nb_tracks {
+            cache.add(create_track_section_cache(format!("track_{}", i), 10.));
+        }
+        cache
+    }
+
+    #[test]
+    fn load_on_first_access() {
+        let caches = InfraCaches::default();
+        let loads = Cell::new(0);
+        let load = || {
+            loads.set(loads.get() + 1);
+            Ok(cache(1))
+        };
+        assert!(!caches.is_loaded(1));
+        assert_eq!(
+            caches.get_or_load(1, load).unwrap().estimated_size(),
+            cache(1).estimated_size()
+        );
+        caches.get_mut_or_load(1, load).unwrap();
+        assert!(caches.is_loaded(1));
+        assert_eq!(loads.get(), 1);
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let budget = cache(10).estimated_size() * 2;
+        let caches = InfraCaches::new(Some(budget), None);
+        caches.insert_new(1, cache(10));
+        caches.insert_new(2, cache(10));
+        // Access the first infra for the second one to be the least recently used
+        caches.get_or_load(1, || panic!("already loaded")).unwrap();
+        caches.insert_new(3, cache(10));
+        assert!(caches.is_loaded(1));
+        assert!(!caches.is_loaded(2));
+        assert!(caches.is_loaded(3));
+
+        // A cache larger than the budget is kept while used
+        caches.get_or_load(4, || Ok(cache(100))).unwrap();
+        assert!(caches.is_loaded(4));
+        assert!(!caches.is_loaded(1));
+        assert!(!caches.is_loaded(3));
+    }
+
+    #[test]
+    fn remove() {
+        let caches = InfraCaches::new(Some(0), None);
+        caches.insert_new(1, cache(1));
+        caches.remove(1);
+        assert!(!caches.is_loaded(1));
+        assert!(caches.get_loaded(1).is_none());
+        assert!(caches.lru.lock().unwrap().usages.is_empty());
+    }
+
+    #[test]
+    fn replace_reports_differences() {
+        let caches = InfraCaches::default();
+        assert!(caches.replace(1, || Ok(cache(2))).unwrap().is_empty());
+        assert!(caches.is_loaded(1));
+
+        let diff = caches.replace(1, || Ok(cache(3))).unwrap();
+        assert_eq!(diff.missing.len(), 1);
+        assert_eq!(diff.missing[0].obj_id, "track_2");
+        assert!(diff.extra.is_empty());
+        assert_eq!(
+            caches
+                .get_or_load(1, || panic!("already loaded"))
+                .unwrap()
+                .estimated_size(),
+            cache(3).estimated_size()
+        );
+    }
+
+    #[test]
+    fn broken_infra_is_not_loaded_again() {
+        let caches = InfraCaches::default();
+        let error = || InfraCacheError::InvalidObject {
+            infra: 1,
+            obj_ref: ObjectRef::new(ObjectType::Route, "R1"),
+            reason: "missing field `path`".into(),
+        };
+        assert!(caches.get_or_load(1, || Err(error())).is_err());
+        let err = caches
+            .get_mut_or_load(1, || panic!("broken"))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("R1"));
+        // Other infras aren't affected
+        assert!(caches.get_or_load(2, || Ok(cache(1))).is_ok());
+
+        // A successful reload fixes the infra
+        assert!(caches.replace(1, || Ok(cache(1))).is_ok());
+        assert!(caches.get_or_load(1, || panic!("already loaded")).is_ok());
+
+        // A failed reload drops the cache
+        assert!(caches.replace(1, || Err(error())).is_err());
+        assert!(!caches.is_loaded(1));
+        assert!(caches.get_or_load(1, || panic!("broken")).is_err());
+    }
+
+    #[test]
+    fn snapshots_are_versioned() {
+        let dir = std::env::temp_dir().join(format!(
+            "editoast_infra_cache_snapshots_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let caches = InfraCaches::new(None, Some(dir.clone()));
+        caches.write_snapshot(1, "3", &cache(5));
+
+        let snapshot = caches.read_snapshot(1, "3").unwrap();
+        assert_eq!(snapshot.estimated_size(), cache(5).estimated_size());
+        assert!(snapshot.track_sections().contains_key("track_4"));
+        assert!(caches.read_snapshot(1, "4").is_none());
+        assert!(caches.read_snapshot(2, "3").is_none());
+
+        caches.remove(1);
+        assert!(caches.read_snapshot(1, "3").is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,8 +1,9 @@
+use enum_map::EnumMap;
 use serde::{Deserialize, Serialize};
 
 use crate::infra_cache::{InfraCache, ObjectCache};
 use crate::objects::operation::{OperationResult, RailjsonObject};
-use crate::objects::{ObjectRef, ObjectType};
+use crate::objects::{OSRDObject, ObjectRef, ObjectType};
 
 // impl Iter trait
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -18,6 +19,11 @@ impl BoundingBox {
     pub fn is_valid(&self) -> bool {
         self.0 .0 <= self.1 .0 && self.0 .1 <= self.1 .1
     }
+
+    /// Check whether two valid bounding boxes share at least a point
+    pub fn intersects(&self, b: &Self) -> bool {
+        self.0 .0 <= b.1 .0 && b.0 .0 <= self.1 .0 && self.0 .1 <= b.1 .1 && b.0 .1 <= self.1 .1
+    }
 }
 impl Default for BoundingBox {
     fn default() -> Self {
@@ -28,13 +34,18 @@ impl Default for BoundingBox {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct InvalidationZone {
     pub geo: BoundingBox,
     pub sch: BoundingBox,
 }
 
 impl InvalidationZone {
+    /// Check whether two zones overlap in either representation
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.geo.intersects(&other.geo) || self.sch.intersects(&other.sch)
+    }
+
     fn merge_bbox(
         geo: &mut BoundingBox,
         sch: &mut BoundingBox,
@@ -47,230 +58,217 @@ impl InvalidationZone {
         }
     }
 
-    pub fn compute(infra_cache: &InfraCache, operations: &Vec<OperationResult>) -> Self {
+    /// Compute the zone affected by an operation, in both representations
+    fn compute_operation(infra_cache: &InfraCache, op: &OperationResult) -> Self {
         let mut geo = BoundingBox::default();
         let mut sch = BoundingBox::default();
-        for op in operations {
-            match op {
-                OperationResult::Create(RailjsonObject::TrackSection { railjson }) => {
-                    geo.union(&railjson.geo.get_bbox());
-                    sch.union(&railjson.sch.get_bbox());
-                }
-                OperationResult::Update(RailjsonObject::TrackSection { railjson }) => {
-                    geo.union(&railjson.geo.get_bbox());
-                    sch.union(&railjson.sch.get_bbox());
-                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, &railjson.id);
-                }
-                OperationResult::Update(RailjsonObject::Signal { railjson })
-                | OperationResult::Create(RailjsonObject::Signal { railjson }) => {
-                    if let Some(ObjectCache::Signal(signal)) =
-                        infra_cache.signals().get(&railjson.id)
-                    {
-                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, &signal.track);
-                    };
-                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, &railjson.track.obj_id);
-                }
-                OperationResult::Update(RailjsonObject::SpeedSection { railjson })
-                | OperationResult::Create(RailjsonObject::SpeedSection { railjson }) => {
-                    if let Some(ObjectCache::SpeedSection(speed_section)) =
-                        infra_cache.speed_sections().get(&railjson.id)
-                    {
-                        for track_id in speed_section.track_ranges.iter().map(|r| &r.track.obj_id) {
-                            Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
-                        }
-                    }
-                    for track_id in railjson.track_ranges.iter().map(|r| &r.track.obj_id) {
+        match op {
+            OperationResult::Create(RailjsonObject::TrackSection { railjson }) => {
+                geo.union(&railjson.geo.get_bbox());
+                sch.union(&railjson.sch.get_bbox());
+            }
+            OperationResult::Update(RailjsonObject::TrackSection { railjson }) => {
+                geo.union(&railjson.geo.get_bbox());
+                sch.union(&railjson.sch.get_bbox());
+                Self::merge_bbox(&mut geo, &mut sch, infra_cache, &railjson.id);
+            }
+            OperationResult::Update(RailjsonObject::Signal { railjson })
+            | OperationResult::Create(RailjsonObject::Signal { railjson }) => {
+                if let Some(ObjectCache::Signal(signal)) = infra_cache.signals().get(&railjson.id) {
+                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, &signal.track);
+                };
+                Self::merge_bbox(&mut geo, &mut sch, infra_cache, &railjson.track.obj_id);
+            }
+            OperationResult::Update(RailjsonObject::SpeedSection { railjson })
+            | OperationResult::Create(RailjsonObject::SpeedSection { railjson }) => {
+                if let Some(ObjectCache::SpeedSection(speed_section)) =
+                    infra_cache.speed_sections().get(&railjson.id)
+                {
+                    for track_id in speed_section.track_ranges.iter().map(|r| &r.track.obj_id) {
                         Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
                     }
                 }
-                OperationResult::Update(RailjsonObject::Route { railjson })
-                | OperationResult::Create(RailjsonObject::Route { railjson }) => {
-                    if let Some(ObjectCache::Route(route)) = infra_cache.routes().get(&railjson.id)
-                    {
-                        for track_id in route.path.iter().map(|r| &r.track.obj_id) {
-                            Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
-                        }
-                    }
-                    for track_id in railjson.path.iter().map(|r| &r.track.obj_id) {
+                for track_id in railjson.track_ranges.iter().map(|r| &r.track.obj_id) {
+                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
+                }
+            }
+            OperationResult::Update(RailjsonObject::Route { railjson })
+            | OperationResult::Create(RailjsonObject::Route { railjson }) => {
+                if let Some(ObjectCache::Route(route)) = infra_cache.routes().get(&railjson.id) {
+                    for track_id in route.path.iter().map(|r| &r.track.obj_id) {
                         Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
                     }
                 }
-                OperationResult::Update(RailjsonObject::OperationalPoint { railjson })
-                | OperationResult::Create(RailjsonObject::OperationalPoint { railjson }) => {
-                    if let Some(ObjectCache::OperationalPoint(op)) =
-                        infra_cache.operational_points().get(&railjson.id)
-                    {
-                        for track_id in op.parts.iter().map(|r| &r.track.obj_id) {
-                            Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
-                        }
-                    }
-                    for track_id in railjson.parts.iter().map(|r| &r.track.obj_id) {
+                for track_id in railjson.path.iter().map(|r| &r.track.obj_id) {
+                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
+                }
+            }
+            OperationResult::Update(RailjsonObject::OperationalPoint { railjson })
+            | OperationResult::Create(RailjsonObject::OperationalPoint { railjson }) => {
+                if let Some(ObjectCache::OperationalPoint(op)) =
+                    infra_cache.operational_points().get(&railjson.id)
+                {
+                    for track_id in op.parts.iter().map(|r| &r.track.obj_id) {
                         Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
                     }
                 }
-                OperationResult::Update(RailjsonObject::TrackSectionLink { railjson })
-                | OperationResult::Create(RailjsonObject::TrackSectionLink { railjson }) => {
-                    if let Some(ObjectCache::TrackSectionLink(link)) =
-                        infra_cache.track_section_links().get(&railjson.id)
-                    {
-                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, &link.src.track.obj_id);
-                    };
-                    let track_id = &railjson.src.track.obj_id;
+                for track_id in railjson.parts.iter().map(|r| &r.track.obj_id) {
                     Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
                 }
-                OperationResult::Update(RailjsonObject::Switch { railjson })
-                | OperationResult::Create(RailjsonObject::Switch { railjson }) => {
-                    if let Some(ObjectCache::Switch(switch)) =
-                        infra_cache.switches().get(&railjson.id)
-                    {
-                        for endpoint in switch.ports.values() {
-                            let track_id = &endpoint.track.obj_id;
-                            Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
-                        }
-                    };
-                    for endpoint in railjson.ports.values() {
+            }
+            OperationResult::Update(RailjsonObject::TrackSectionLink { railjson })
+            | OperationResult::Create(RailjsonObject::TrackSectionLink { railjson }) => {
+                if let Some(ObjectCache::TrackSectionLink(link)) =
+                    infra_cache.track_section_links().get(&railjson.id)
+                {
+                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, &link.src.track.obj_id);
+                };
+                let track_id = &railjson.src.track.obj_id;
+                Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
+            }
+            OperationResult::Update(RailjsonObject::Switch { railjson })
+            | OperationResult::Create(RailjsonObject::Switch { railjson }) => {
+                if let Some(ObjectCache::Switch(switch)) = infra_cache.switches().get(&railjson.id)
+                {
+                    for endpoint in switch.ports.values() {
                         let track_id = &endpoint.track.obj_id;
                         Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
                     }
+                };
+                for endpoint in railjson.ports.values() {
+                    let track_id = &endpoint.track.obj_id;
+                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
                 }
-                OperationResult::Update(RailjsonObject::SwitchType { railjson: _ })
-                | OperationResult::Create(RailjsonObject::SwitchType { railjson: _ }) => {}
-                OperationResult::Update(RailjsonObject::Detector { railjson })
-                | OperationResult::Create(RailjsonObject::Detector { railjson }) => {
-                    if let Some(ObjectCache::Detector(detector)) =
-                        infra_cache.detectors().get(&railjson.id)
-                    {
-                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, &detector.track);
-                    };
-                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, &railjson.track.obj_id);
-                }
-                OperationResult::Update(RailjsonObject::BufferStop { railjson })
-                | OperationResult::Create(RailjsonObject::BufferStop { railjson }) => {
-                    if let Some(ObjectCache::BufferStop(buffer_stop)) =
-                        infra_cache.buffer_stops().get(&railjson.id)
-                    {
-                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, &buffer_stop.track);
-                    };
-                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, &railjson.track.obj_id);
-                }
-                OperationResult::Update(RailjsonObject::Catenary { railjson })
-                | OperationResult::Create(RailjsonObject::Catenary { railjson }) => {
-                    if let Some(ObjectCache::Catenary(catenary)) =
-                        infra_cache.catenaries().get(&railjson.id)
-                    {
-                        for track_id in catenary.track_ranges.iter().map(|r| &r.track.obj_id) {
-                            Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
-                        }
-                    }
-                    for track_id in railjson.track_ranges.iter().map(|r| &r.track.obj_id) {
+            }
+            OperationResult::Update(RailjsonObject::SwitchType { railjson: _ })
+            | OperationResult::Create(RailjsonObject::SwitchType { railjson: _ }) => {}
+            OperationResult::Update(RailjsonObject::Detector { railjson })
+            | OperationResult::Create(RailjsonObject::Detector { railjson }) => {
+                if let Some(ObjectCache::Detector(detector)) =
+                    infra_cache.detectors().get(&railjson.id)
+                {
+                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, &detector.track);
+                };
+                Self::merge_bbox(&mut geo, &mut sch, infra_cache, &railjson.track.obj_id);
+            }
+            OperationResult::Update(RailjsonObject::BufferStop { railjson })
+            | OperationResult::Create(RailjsonObject::BufferStop { railjson }) => {
+                if let Some(ObjectCache::BufferStop(buffer_stop)) =
+                    infra_cache.buffer_stops().get(&railjson.id)
+                {
+                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, &buffer_stop.track);
+                };
+                Self::merge_bbox(&mut geo, &mut sch, infra_cache, &railjson.track.obj_id);
+            }
+            OperationResult::Update(RailjsonObject::Catenary { railjson })
+            | OperationResult::Create(RailjsonObject::Catenary { railjson }) => {
+                if let Some(ObjectCache::Catenary(catenary)) =
+                    infra_cache.catenaries().get(&railjson.id)
+                {
+                    for track_id in catenary.track_ranges.iter().map(|r| &r.track.obj_id) {
                         Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
                     }
                 }
-                OperationResult::Delete(ObjectRef {
-                    obj_type: ObjectType::TrackSection,
-                    obj_id,
-                }) => Self::merge_bbox(&mut geo, &mut sch, infra_cache, obj_id),
-                OperationResult::Delete(ObjectRef {
-                    obj_type: ObjectType::Signal,
-                    obj_id,
-                }) => {
-                    if let Some(ObjectCache::Signal(signal)) = infra_cache.signals().get(obj_id) {
-                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, &signal.track);
-                    }
+                for track_id in railjson.track_ranges.iter().map(|r| &r.track.obj_id) {
+                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
                 }
-                OperationResult::Delete(ObjectRef {
-                    obj_type: ObjectType::SpeedSection,
-                    obj_id,
-                }) => {
-                    if let Some(ObjectCache::SpeedSection(speed)) =
-                        infra_cache.speed_sections().get(obj_id)
-                    {
-                        for track_id in speed.track_ranges.iter().map(|r| &r.track.obj_id) {
-                            Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
-                        }
-                    }
+            }
+            OperationResult::Delete(ObjectRef {
+                obj_type: ObjectType::TrackSection,
+                obj_id,
+            }) => Self::merge_bbox(&mut geo, &mut sch, infra_cache, obj_id),
+            OperationResult::Delete(ObjectRef {
+                obj_type: ObjectType::Signal,
+                obj_id,
+            }) => {
+                if let Some(ObjectCache::Signal(signal)) = infra_cache.signals().get(obj_id) {
+                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, &signal.track);
                 }
-                OperationResult::Delete(ObjectRef {
-                    obj_type: ObjectType::Route,
-                    obj_id,
-                }) => {
-                    if let Some(ObjectCache::Route(route)) = infra_cache.routes().get(obj_id) {
-                        for track_id in route.path.iter().map(|r| &r.track.obj_id) {
-                            Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
-                        }
+            }
+            OperationResult::Delete(ObjectRef {
+                obj_type: ObjectType::SpeedSection,
+                obj_id,
+            }) => {
+                if let Some(ObjectCache::SpeedSection(speed)) =
+                    infra_cache.speed_sections().get(obj_id)
+                {
+                    for track_id in speed.track_ranges.iter().map(|r| &r.track.obj_id) {
+                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
                     }
                 }
-                OperationResult::Delete(ObjectRef {
-                    obj_type: ObjectType::OperationalPoint,
-                    obj_id,
-                }) => {
-                    if let Some(ObjectCache::OperationalPoint(op)) =
-                        infra_cache.operational_points().get(obj_id)
-                    {
-                        for track_id in op.parts.iter().map(|r| &r.track.obj_id) {
-                            Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
-                        }
+            }
+            OperationResult::Delete(ObjectRef {
+                obj_type: ObjectType::Route,
+                obj_id,
+            }) => {
+                if let Some(ObjectCache::Route(route)) = infra_cache.routes().get(obj_id) {
+                    for track_id in route.path.iter().map(|r| &r.track.obj_id) {
+                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
                     }
                 }
-                OperationResult::Delete(ObjectRef {
-                    obj_type: ObjectType::TrackSectionLink,
-                    obj_id,
-                }) => {
-                    if let Some(ObjectCache::TrackSectionLink(link)) =
-                        infra_cache.track_section_links().get(obj_id)
-                    {
-                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, &link.src.track.obj_id);
+            }
+            OperationResult::Delete(ObjectRef {
+                obj_type: ObjectType::OperationalPoint,
+                obj_id,
+            }) => {
+                if let Some(ObjectCache::OperationalPoint(op)) =
+                    infra_cache.operational_points().get(obj_id)
+                {
+                    for track_id in op.parts.iter().map(|r| &r.track.obj_id) {
+                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
                     }
                 }
-                OperationResult::Delete(ObjectRef {
-                    obj_type: ObjectType::Switch,
-                    obj_id,
-                }) => {
-                    if let Some(ObjectCache::Switch(switch)) = infra_cache.switches().get(obj_id) {
-                        for endpoint in switch.ports.values() {
-                            Self::merge_bbox(
-                                &mut geo,
-                                &mut sch,
-                                infra_cache,
-                                &endpoint.track.obj_id,
-                            );
-                        }
-                    }
+            }
+            OperationResult::Delete(ObjectRef {
+                obj_type: ObjectType::TrackSectionLink,
+                obj_id,
+            }) => {
+                if let Some(ObjectCache::TrackSectionLink(link)) =
+                    infra_cache.track_section_links().get(obj_id)
+                {
+                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, &link.src.track.obj_id);
                 }
-                OperationResult::Delete(ObjectRef {
-                    obj_type: ObjectType::Detector,
-                    obj_id,
-                }) => {
-                    if let Some(ObjectCache::Detector(detector)) =
-                        infra_cache.detectors().get(obj_id)
-                    {
-                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, &detector.track);
+            }
+            OperationResult::Delete(ObjectRef {
+                obj_type: ObjectType::Switch,
+                obj_id,
+            }) => {
+                if let Some(ObjectCache::Switch(switch)) = infra_cache.switches().get(obj_id) {
+                    for endpoint in switch.ports.values() {
+                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, &endpoint.track.obj_id);
                     }
                 }
-                OperationResult::Delete(ObjectRef {
-                    obj_type: ObjectType::BufferStop,
-                    obj_id,
-                }) => {
-                    if let Some(ObjectCache::BufferStop(buffer_stop)) =
-                        infra_cache.buffer_stops().get(obj_id)
-                    {
-                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, &buffer_stop.track);
-                    }
+            }
+            OperationResult::Delete(ObjectRef {
+                obj_type: ObjectType::Detector,
+                obj_id,
+            }) => {
+                if let Some(ObjectCache::Detector(detector)) = infra_cache.detectors().get(obj_id) {
+                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, &detector.track);
+                }
+            }
+            OperationResult::Delete(ObjectRef {
+                obj_type: ObjectType::BufferStop,
+                obj_id,
+            }) => {
+                if let Some(ObjectCache::BufferStop(buffer_stop)) =
+                    infra_cache.buffer_stops().get(obj_id)
+                {
+                    Self::merge_bbox(&mut geo, &mut sch, infra_cache, &buffer_stop.track);
                 }
-                OperationResult::Delete(ObjectRef {
-                    obj_type: ObjectType::SwitchType,
-                    obj_id: _,
-                }) => {}
-                OperationResult::Delete(ObjectRef {
-                    obj_type: ObjectType::Catenary,
-                    obj_id,
-                }) => {
-                    if let Some(ObjectCache::Catenary(catenary)) =
-                        infra_cache.catenaries().get(obj_id)
-                    {
-                        for track_id in catenary.track_ranges.iter().map(|r| &r.track.obj_id) {
-                            Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
-                        }
+            }
+            OperationResult::Delete(ObjectRef {
+                obj_type: ObjectType::SwitchType,
+                obj_id: _,
+            }) => {}
+            OperationResult::Delete(ObjectRef {
+                obj_type: ObjectType::Catenary,
+                obj_id,
+            }) => {
+                if let Some(ObjectCache::Catenary(catenary)) = infra_cache.catenaries().get(obj_id)
+                {
+                    for track_id in catenary.track_ranges.iter().map(|r| &r.track.obj_id) {
+                        Self::merge_bbox(&mut geo, &mut sch, infra_cache, track_id);
                     }
                 }
             }
@@ -279,9 +277,91 @@ impl InvalidationZone {
     }
 }
 
+/// Add a zone to a list of non overlapping zones, merging it with the zones it overlaps
+pub fn add_zone(zones: &mut Vec<InvalidationZone>, mut zone: InvalidationZone) {
+    while let Some(index) = zones.iter().position(|other| other.overlaps(&zone)) {
+        let other = zones.swap_remove(index);
+        zone.geo.union(&other.geo);
+        zone.sch.union(&other.sch);
+    }
+    zones.push(zone);
+}
+
+/// Zones to invalidate for each layer, given by the type of its objects.
+/// Each layer gets its own list of non overlapping zones, so that distant edits don't
+/// invalidate everything in between.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayerInvalidationZones(EnumMap<ObjectType, Vec<InvalidationZone>>);
+
+impl LayerInvalidationZones {
+    fn add(&mut self, obj_type: ObjectType, zone: &InvalidationZone) {
+        add_zone(&mut self.0[obj_type], zone.clone());
+    }
+
+    /// Compute the zones to invalidate given a list of operations.
+    /// Editing a track section also invalidates the layers of the objects located on it.
+    pub fn compute(infra_cache: &InfraCache, operations: &Vec<OperationResult>) -> Self {
+        let mut zones = Self::default();
+        for op in operations {
+            let zone = InvalidationZone::compute_operation(infra_cache, op);
+            if !zone.geo.is_valid() {
+                continue;
+            }
+            let obj_type = match op {
+                OperationResult::Create(railjson) | OperationResult::Update(railjson) => {
+                    railjson.get_type()
+                }
+                OperationResult::Delete(obj_ref) => obj_ref.obj_type,
+            };
+            zones.add(obj_type, &zone);
+            if let OperationResult::Create(RailjsonObject::TrackSection { railjson })
+            | OperationResult::Update(RailjsonObject::TrackSection { railjson }) = op
+            {
+                for attached in infra_cache
+                    .track_sections_refs
+                    .get(&railjson.id)
+                    .into_iter()
+                    .flatten()
+                {
+                    zones.add(attached.obj_type, &zone);
+                }
+            }
+        }
+        zones
+    }
+
+    /// Zones to invalidate in the layer of the given object type
+    pub fn get(&self, obj_type: ObjectType) -> &[InvalidationZone] {
+        &self.0[obj_type]
+    }
+
+    /// Whether no layer needs to be invalidated
+    pub fn is_empty(&self) -> bool {
+        self.0.values().all(|zones| zones.is_empty())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::infra_cache::tests::{create_signal_cache, create_track_section_cache};
+    use crate::objects::{LineString, TrackSection};
+
+    fn create_infra_cache() -> InfraCache {
+        let mut infra_cache = InfraCache::default();
+        for (track, bbox) in [
+            ("T1", BoundingBox((0., 0.), (1., 1.))),
+            ("T2", BoundingBox((10., 10.), (11., 11.))),
+        ] {
+            let mut track = create_track_section_cache(track.into(), 100.);
+            track.bbox_geo = bbox.clone();
+            track.bbox_sch = bbox;
+            infra_cache.add(track);
+        }
+        infra_cache.add(create_signal_cache("S1", "T1", 10.));
+        infra_cache.add(create_signal_cache("S2", "T2", 10.));
+        infra_cache
+    }
 
     #[test]
     fn test_bounding_box_union() {
@@ -306,4 +386,57 @@ mod tests {
         assert!(!BoundingBox((0., 1.), (1., 0.)).is_valid());
         assert!(!BoundingBox::default().is_valid());
     }
+
+    #[test]
+    fn distant_edits_have_separate_zones() {
+        let infra_cache = create_infra_cache();
+        let operations = vec![
+            OperationResult::Delete(ObjectRef::new(ObjectType::Signal, "S1")),
+            OperationResult::Delete(ObjectRef::new(ObjectType::Signal, "S2")),
+        ];
+        let zones = LayerInvalidationZones::compute(&infra_cache, &operations);
+        assert_eq!(zones.get(ObjectType::Signal).len(), 2);
+        assert!(zones.get(ObjectType::TrackSection).is_empty());
+    }
+
+    #[test]
+    fn track_edit_invalidates_attached_objects() {
+        let infra_cache = create_infra_cache();
+        let track = TrackSection {
+            id: "T1".into(),
+            length: 100.,
+            geo: LineString::LineString {
+                coordinates: vec![[0., 0.], [2., 2.]],
+            },
+            sch: LineString::LineString {
+                coordinates: vec![[0., 0.], [2., 2.]],
+            },
+            ..Default::default()
+        };
+        let operations = vec![OperationResult::Update(RailjsonObject::TrackSection {
+            railjson: track,
+        })];
+        let zones = LayerInvalidationZones::compute(&infra_cache, &operations);
+        let expected = vec![InvalidationZone {
+            geo: BoundingBox((0., 0.), (2., 2.)),
+            sch: BoundingBox((0., 0.), (2., 2.)),
+        }];
+        assert_eq!(zones.get(ObjectType::TrackSection), expected);
+        assert_eq!(zones.get(ObjectType::Signal), expected);
+        assert!(zones.get(ObjectType::Detector).is_empty());
+    }
+
+    #[test]
+    fn overlapping_zones_are_merged() {
+        let mut zones = vec![];
+        let zone = |min, max| InvalidationZone {
+            geo: BoundingBox((min, min), (max, max)),
+            sch: BoundingBox((min, min), (max, max)),
+        };
+        add_zone(&mut zones, zone(0., 1.));
+        add_zone(&mut zones, zone(5., 6.));
+        assert_eq!(zones.len(), 2);
+        add_zone(&mut zones, zone(0.5, 5.5));
+        assert_eq!(zones, vec![zone(0., 6.)]);
+    }
 }
@@ -0,0 +1,285 @@
+use diesel::result::Error as DieselError;
+use diesel::sql_types::{Bool, Bytea, Double, Integer, Text};
+use diesel::{sql_query, PgConnection, QueryableByName, RunQueryDsl};
+use rocket::http::Status;
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use super::{BoundingBox, Layer};
+use crate::error::ApiError;
+use crate::objects::{
+    BufferStop, Catenary, Detector, OperationalPoint, Route, Signal, SpeedSection, Switch,
+    TrackSection, TrackSectionLink,
+};
+
+#[derive(Debug, Error)]
+pub enum LayerError {
+    #[error("Layer '{0}' does not exist")]
+    UnknownLayer(String),
+    #[error("View '{0}' does not exist, expected 'geo' or 'sch'")]
+    InvalidView(String),
+    #[error("Tile {z}/{x}/{y} is out of bounds")]
+    InvalidTile { z: u32, x: u32, y: u32 },
+    #[error("Bounding box must be given as 'min_lon,min_lat,max_lon,max_lat'")]
+    InvalidBbox,
+    #[error(transparent)]
+    Database(#[from] DieselError),
+}
+
+impl ApiError for LayerError {
+    fn get_status(&self) -> Status {
+        match self {
+            LayerError::UnknownLayer(_) => Status::NotFound,
+            LayerError::InvalidView(_)
+            | LayerError::InvalidTile { .. }
+            | LayerError::InvalidBbox => Status::BadRequest,
+            LayerError::Database(_) => Status::InternalServerError,
+        }
+    }
+
+    fn get_type(&self) -> &'static str {
+        match self {
+            LayerError::UnknownLayer(_) => "editoast:layers:UnknownLayer",
+            LayerError::InvalidView(_) => "editoast:layers:InvalidView",
+            LayerError::InvalidTile { .. } => "editoast:layers:InvalidTile",
+            LayerError::InvalidBbox => "editoast:layers:InvalidBbox",
+            LayerError::Database(_) => "editoast:layers:Database",
+        }
+    }
+
+    fn extra(&self) -> Option<Map<String, Value>> {
+        match self {
+            LayerError::UnknownLayer(layer) => json!({ "layer": layer }),
+            LayerError::InvalidView(view) => json!({ "view": view }),
+            LayerError::InvalidTile { z, x, y } => json!({ "z": z, "x": x, "y": y }),
+            LayerError::InvalidBbox | LayerError::Database(_) => return None,
+        }
+        .as_object()
+        .cloned()
+    }
+}
+
+/// Geometry column of a layer table to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerView {
+    Geo,
+    Sch,
+}
+
+impl LayerView {
+    pub fn parse(view: &str) -> Result<Self, LayerError> {
+        match view {
+            "geo" => Ok(LayerView::Geo),
+            "sch" => Ok(LayerView::Sch),
+            _ => Err(LayerError::InvalidView(view.into())),
+        }
+    }
+
+    pub fn column(&self) -> &'static str {
+        match self {
+            LayerView::Geo => "geographic",
+            LayerView::Sch => "schematic",
+        }
+    }
+}
+
+/// How to query a generated layer table, aliased as `layer` in queries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerDefinition {
+    pub name: &'static str,
+    pub table_name: &'static str,
+    /// SQL expression building the jsonb properties of a feature
+    pub properties: &'static str,
+    /// Join clause needed by the properties expression
+    pub join: Option<String>,
+}
+
+/// Layers of railjson objects take the object railjson as properties
+fn object_layer<T: Layer>() -> LayerDefinition {
+    LayerDefinition {
+        name: T::layer_name(),
+        table_name: T::get_table_name(),
+        properties: "obj.data",
+        join: Some(format!(
+            "INNER JOIN {} AS obj ON obj.obj_id = layer.obj_id AND obj.infra_id = layer.infra_id",
+            T::get_obj_type().get_table()
+        )),
+    }
+}
+
+/// List the layers generated by editoast
+pub fn get_layer_definitions() -> Vec<LayerDefinition> {
+    vec![
+        object_layer::<TrackSection>(),
+        object_layer::<Signal>(),
+        object_layer::<SpeedSection>(),
+        object_layer::<TrackSectionLink>(),
+        object_layer::<Switch>(),
+        object_layer::<Detector>(),
+        object_layer::<BufferStop>(),
+        object_layer::<Route>(),
+        object_layer::<OperationalPoint>(),
+        object_layer::<Catenary>(),
+        LayerDefinition {
+            name: "errors",
+            table_name: "osrd_infra_errorlayer",
+            properties: "jsonb_build_object('id', layer.obj_id, 'type', layer.obj_type, 'information', layer.information)",
+            join: None,
+        },
+        LayerDefinition {
+            name: "tvd_sections",
+            table_name: "osrd_infra_tvdsectionlayer",
+            properties: "layer.data",
+            join: None,
+        },
+    ]
+}
+
+/// Retrieve a layer definition given its name
+pub fn get_layer_definition(name: &str) -> Result<LayerDefinition, LayerError> {
+    get_layer_definitions()
+        .into_iter()
+        .find(|layer| layer.name == name)
+        .ok_or_else(|| LayerError::UnknownLayer(name.into()))
+}
+
+/// Maximum zoom level served, tiles are then smaller than a meter
+const MAX_ZOOM: u32 = 24;
+
+/// Check that a tile exists in the web mercator tiling scheme
+pub fn check_tile(z: u32, x: u32, y: u32) -> Result<(), LayerError> {
+    if z > MAX_ZOOM || x >= 1 << z || y >= 1 << z {
+        return Err(LayerError::InvalidTile { z, x, y });
+    }
+    Ok(())
+}
+
+/// Parse a WGS84 bounding box given as `[min_lon, min_lat, max_lon, max_lat]`
+pub fn parse_bbox(bbox: &[f64]) -> Result<BoundingBox, LayerError> {
+    match *bbox {
+        [min_lon, min_lat, max_lon, max_lat] => {
+            let bbox = BoundingBox((min_lon, min_lat), (max_lon, max_lat));
+            if bbox.is_valid() {
+                Ok(bbox)
+            } else {
+                Err(LayerError::InvalidBbox)
+            }
+        }
+        _ => Err(LayerError::InvalidBbox),
+    }
+}
+
+#[derive(QueryableByName)]
+struct GeoJsonLayer {
+    #[sql_type = "Text"]
+    geojson: String,
+}
+
+#[derive(QueryableByName)]
+struct MvtTile {
+    #[sql_type = "Bytea"]
+    mvt: Vec<u8>,
+}
+
+impl LayerDefinition {
+    /// Build a mapbox vector tile of the layer for a given infra
+    pub fn get_mvt_tile(
+        &self,
+        conn: &PgConnection,
+        infra: i32,
+        view: LayerView,
+        z: u32,
+        x: u32,
+        y: u32,
+    ) -> Result<Vec<u8>, LayerError> {
+        check_tile(z, x, y)?;
+        let query = format!(
+            include_str!("sql/get_mvt_tile.sql"),
+            column = view.column(),
+            properties = self.properties,
+            table = self.table_name,
+            join = self.join.as_deref().unwrap_or_default(),
+            name = self.name,
+        );
+        let tile = sql_query(query)
+            .bind::<Integer, _>(infra)
+            .bind::<Integer, _>(z as i32)
+            .bind::<Integer, _>(x as i32)
+            .bind::<Integer, _>(y as i32)
+            .get_result::<MvtTile>(conn)?;
+        Ok(tile.mvt)
+    }
+
+    /// Export the layer of a given infra as a serialized GeoJSON `FeatureCollection` in WGS84.
+    /// If a bounding box is given, only features intersecting it are exported.
+    pub fn get_geojson(
+        &self,
+        conn: &PgConnection,
+        infra: i32,
+        view: LayerView,
+        bbox: Option<&BoundingBox>,
+    ) -> Result<String, LayerError> {
+        let query = format!(
+            include_str!("sql/get_geojson_layer.sql"),
+            column = view.column(),
+            properties = self.properties,
+            table = self.table_name,
+            join = self.join.as_deref().unwrap_or_default(),
+        );
+        // Placeholder coordinates must be finite to keep the envelope valid
+        let BoundingBox(min, max) = bbox.cloned().unwrap_or(BoundingBox((0., 0.), (0., 0.)));
+        let layer = sql_query(query)
+            .bind::<Integer, _>(infra)
+            .bind::<Bool, _>(bbox.is_some())
+            .bind::<Double, _>(min.0)
+            .bind::<Double, _>(min.1)
+            .bind::<Double, _>(max.0)
+            .bind::<Double, _>(max.1)
+            .get_result::<GeoJsonLayer>(conn)?;
+        Ok(layer.geojson)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_tile, get_layer_definition, get_layer_definitions, parse_bbox, LayerError, LayerView,
+    };
+
+    #[test]
+    fn layer_definitions() {
+        let layer = get_layer_definition("signals").unwrap();
+        assert_eq!(layer.table_name, "osrd_infra_signallayer");
+        assert!(layer.join.unwrap().contains("osrd_infra_signalmodel"));
+        assert!(get_layer_definition("tvd_sections").unwrap().join.is_none());
+        assert!(matches!(
+            get_layer_definition("unknown"),
+            Err(LayerError::UnknownLayer(_))
+        ));
+        assert_eq!(get_layer_definitions().len(), 12);
+    }
+
+    #[test]
+    fn layer_views() {
+        assert_eq!(LayerView::parse("sch").unwrap().column(), "schematic");
+        assert!(LayerView::parse("mercator").is_err());
+    }
+
+    #[test]
+    fn tile_bounds() {
+        assert!(check_tile(0, 0, 0).is_ok());
+        assert!(check_tile(3, 7, 7).is_ok());
+        assert!(check_tile(3, 8, 0).is_err());
+        assert!(check_tile(3, 0, 8).is_err());
+        assert!(check_tile(30, 0, 0).is_err());
+    }
+
+    #[test]
+    fn bbox_parsing() {
+        let bbox = parse_bbox(&[2., 48., 3., 49.]).unwrap();
+        assert_eq!(bbox.0, (2., 48.));
+        assert_eq!(bbox.1, (3., 49.));
+        assert!(parse_bbox(&[2., 48., 3.]).is_err());
+        assert!(parse_bbox(&[3., 48., 2., 49.]).is_err());
+    }
+}
@@ -1,54 +1,95 @@
+use colored::Colorize;
+
 use crate::client::ChartosConfig;
 
 use super::InvalidationZone;
 
 /// Invalidate a whole chartos layer cache.
+/// The invalidation is queued if the background worker is started, sent inline otherwise.
+/// Failures are reported but don't abort the caller, the layer data itself is up to date.
 pub fn invalidate_chartos_layer(infra_id: i32, layer_slug: &str, chartos_config: &ChartosConfig) {
-    let client = reqwest::blocking::Client::new();
-    let resp = client
-        .post(format!(
-            "{}layer/{}/invalidate/?infra={}",
-            chartos_config.url(),
-            layer_slug,
-            infra_id
-        ))
-        .bearer_auth(&chartos_config.chartos_token)
-        .send()
-        .expect("Failed to send invalidate request to chartos");
-    if !resp.status().is_success() {
-        panic!("Failed to invalidate chartos layer: {}", resp.status());
+    if let Some(queue) = chartos_config.invalidation_queue() {
+        queue.push_layer(infra_id, layer_slug);
+    } else if let Err(err) = chartos_config
+        .invalidation_backend()
+        .invalidate_layer(infra_id, layer_slug)
+    {
+        eprintln!(
+            "{}",
+            format!("Failed to invalidate layer {layer_slug} of infra {infra_id}: {err}").red()
+        );
     }
 }
 
 /// Invalidate a part of chartos layer cache.
+/// The invalidation is queued if the background worker is started, sent inline otherwise.
+/// Failures are reported but don't abort the caller, the layer data itself is up to date.
 pub fn invalidate_bbox_chartos_layer(
     infra_id: i32,
     layer_slug: &str,
     invalidation: &InvalidationZone,
     chartos_config: &ChartosConfig,
 ) {
-    let client = reqwest::blocking::Client::new();
-    let resp = client
-        .post(format!(
-            "{}layer/{}/invalidate_bbox/?infra={}",
-            chartos_config.url(),
-            layer_slug,
-            infra_id
-        ))
-        .json(&json!([
-            {
-                "view": "geo",
-                "bbox": invalidation.geo,
-            },
-            {
-                "view": "sch",
-                "bbox": invalidation.sch,
-            }
-        ]))
-        .bearer_auth(&chartos_config.chartos_token)
-        .send()
-        .expect("Failed to send invalidate request to chartos");
-    if !resp.status().is_success() {
-        panic!("Failed to invalidate chartos layer: {}", resp.status());
+    if let Some(queue) = chartos_config.invalidation_queue() {
+        queue.push_bbox(infra_id, layer_slug, invalidation);
+    } else if let Err(err) =
+        chartos_config
+            .invalidation_backend()
+            .invalidate_bbox(infra_id, layer_slug, invalidation)
+    {
+        eprintln!(
+            "{}",
+            format!("Failed to invalidate layer {layer_slug} of infra {infra_id}: {err}").red()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{invalidate_bbox_chartos_layer, invalidate_chartos_layer};
+    use crate::client::ChartosConfig;
+    use crate::layer::{BoundingBox, Invalidation, InvalidationZone};
+
+    #[test]
+    fn default_config_records_invalidations() {
+        let config = ChartosConfig::default();
+        let zone = InvalidationZone {
+            geo: BoundingBox((0., 0.), (1., 1.)),
+            sch: BoundingBox((0., 0.), (2., 2.)),
+        };
+        invalidate_chartos_layer(1, "errors", &config);
+        invalidate_bbox_chartos_layer(1, "signals", &zone, &config);
+        assert_eq!(
+            config.recorded_invalidations(),
+            vec![
+                Invalidation::Layer {
+                    infra_id: 1,
+                    layer: "errors".into()
+                },
+                Invalidation::Bbox {
+                    infra_id: 1,
+                    layer: "signals".into(),
+                    zone
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn deferred_invalidations_are_held_back() {
+        let config = ChartosConfig::default();
+        let deferred = config.deferred();
+        invalidate_chartos_layer(1, "errors", &deferred);
+        invalidate_chartos_layer(1, "errors", &deferred);
+        assert!(config.recorded_invalidations().is_empty());
+
+        config.send_deferred(deferred);
+        assert_eq!(
+            config.recorded_invalidations(),
+            vec![Invalidation::Layer {
+                infra_id: 1,
+                layer: "errors".into()
+            }]
+        );
     }
 }
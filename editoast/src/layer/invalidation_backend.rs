@@ -0,0 +1,200 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use clap::ArgEnum;
+use thiserror::Error;
+
+use super::InvalidationZone;
+
+#[derive(Debug, Error)]
+pub enum InvalidationError {
+    #[error("Failed to send invalidate request to chartos: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Chartos responded to invalidate request with status {0}")]
+    Status(reqwest::StatusCode),
+}
+
+/// A backend in charge of invalidating the layer caches of a tile server
+pub trait InvalidationBackend: Debug + Send + Sync {
+    /// Invalidate a whole layer of an infra
+    fn invalidate_layer(&self, infra_id: i32, layer_slug: &str) -> Result<(), InvalidationError>;
+
+    /// Invalidate the part of a layer of an infra within a zone
+    fn invalidate_bbox(
+        &self,
+        infra_id: i32,
+        layer_slug: &str,
+        zone: &InvalidationZone,
+    ) -> Result<(), InvalidationError>;
+}
+
+/// Which invalidation backend to use
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidationBackendKind {
+    /// Send invalidations to chartos
+    Chartos,
+    /// Drop invalidations, useful when no tile server caches layers
+    Noop,
+    /// Keep invalidations in memory, used by tests. Never released, so not selectable from
+    /// the command line.
+    #[clap(skip)]
+    Recording,
+}
+
+impl Default for InvalidationBackendKind {
+    /// Tests record invalidations instead of reaching chartos
+    #[cfg(test)]
+    fn default() -> Self {
+        Self::Recording
+    }
+
+    #[cfg(not(test))]
+    fn default() -> Self {
+        Self::Chartos
+    }
+}
+
+/// Send invalidations to chartos HTTP API
+#[derive(Debug, Clone)]
+pub struct ChartosBackend {
+    /// Chartos url, ending with a slash
+    pub url: String,
+    pub token: String,
+    pub client: reqwest::blocking::Client,
+}
+
+impl ChartosBackend {
+    fn post(
+        &self,
+        infra_id: i32,
+        layer_slug: &str,
+        action: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<(), InvalidationError> {
+        let mut request = self
+            .client
+            .post(format!(
+                "{}layer/{}/{}/?infra={}",
+                self.url, layer_slug, action, infra_id
+            ))
+            .bearer_auth(&self.token);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let resp = request.send()?;
+        if !resp.status().is_success() {
+            return Err(InvalidationError::Status(resp.status()));
+        }
+        Ok(())
+    }
+}
+
+impl InvalidationBackend for ChartosBackend {
+    fn invalidate_layer(&self, infra_id: i32, layer_slug: &str) -> Result<(), InvalidationError> {
+        self.post(infra_id, layer_slug, "invalidate", None)
+    }
+
+    fn invalidate_bbox(
+        &self,
+        infra_id: i32,
+        layer_slug: &str,
+        zone: &InvalidationZone,
+    ) -> Result<(), InvalidationError> {
+        let body = serde_json::json!([
+            {
+                "view": "geo",
+                "bbox": zone.geo,
+            },
+            {
+                "view": "sch",
+                "bbox": zone.sch,
+            }
+        ]);
+        self.post(infra_id, layer_slug, "invalidate_bbox", Some(body))
+    }
+}
+
+/// Ignore every invalidation
+#[derive(Debug, Default)]
+pub struct NoopBackend;
+
+impl InvalidationBackend for NoopBackend {
+    fn invalidate_layer(&self, _: i32, _: &str) -> Result<(), InvalidationError> {
+        Ok(())
+    }
+
+    fn invalidate_bbox(
+        &self,
+        _: i32,
+        _: &str,
+        _: &InvalidationZone,
+    ) -> Result<(), InvalidationError> {
+        Ok(())
+    }
+}
+
+/// An invalidation kept by the recording backend
+#[derive(Debug, Clone, PartialEq)]
+pub enum Invalidation {
+    Layer {
+        infra_id: i32,
+        layer: String,
+    },
+    Bbox {
+        infra_id: i32,
+        layer: String,
+        zone: InvalidationZone,
+    },
+}
+
+/// Keep every invalidation in memory. Clones share the same records.
+#[derive(Debug, Default, Clone)]
+pub struct RecordingBackend {
+    records: Arc<Mutex<Vec<Invalidation>>>,
+}
+
+impl RecordingBackend {
+    /// Return the recorded invalidations, oldest first
+    pub fn records(&self) -> Vec<Invalidation> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+impl InvalidationBackend for RecordingBackend {
+    fn invalidate_layer(&self, infra_id: i32, layer_slug: &str) -> Result<(), InvalidationError> {
+        self.records.lock().unwrap().push(Invalidation::Layer {
+            infra_id,
+            layer: layer_slug.into(),
+        });
+        Ok(())
+    }
+
+    fn invalidate_bbox(
+        &self,
+        infra_id: i32,
+        layer_slug: &str,
+        zone: &InvalidationZone,
+    ) -> Result<(), InvalidationError> {
+        self.records.lock().unwrap().push(Invalidation::Bbox {
+            infra_id,
+            layer: layer_slug.into(),
+            zone: zone.clone(),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InvalidationBackendKind;
+    use clap::ArgEnum;
+
+    #[test]
+    fn recording_backend_not_selectable() {
+        assert_eq!(
+            InvalidationBackendKind::from_str("chartos", true),
+            Ok(InvalidationBackendKind::Chartos)
+        );
+        assert!(InvalidationBackendKind::from_str("recording", true).is_err());
+    }
+}
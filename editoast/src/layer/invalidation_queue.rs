@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use super::bounding_box::add_zone;
+use super::{InvalidationBackend, InvalidationError, InvalidationZone};
+
+/// Delay before retrying a failed invalidation, doubled on each failure
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Pending invalidation of a layer of an infra
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PendingInvalidation {
+    /// The whole layer must be invalidated
+    Layer,
+    /// Only these zones must be invalidated, they don't overlap each other
+    Zones(Vec<InvalidationZone>),
+}
+
+impl PendingInvalidation {
+    /// Merge two pending invalidations, overlapping zones are merged into one
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Zones(mut zones), Self::Zones(others)) => {
+                for zone in others {
+                    add_zone(&mut zones, zone);
+                }
+                Self::Zones(zones)
+            }
+            _ => Self::Layer,
+        }
+    }
+}
+
+type QueueKey = (i32, String);
+
+#[derive(Debug, Default)]
+struct QueueState {
+    pending: BTreeMap<QueueKey, PendingInvalidation>,
+    /// Invalidations being sent, kept to be persisted until they succeed
+    in_flight: BTreeMap<QueueKey, PendingInvalidation>,
+}
+
+impl QueueState {
+    fn push(&mut self, key: QueueKey, invalidation: PendingInvalidation) {
+        let invalidation = match self.pending.remove(&key) {
+            Some(pending) => pending.merge(invalidation),
+            None => invalidation,
+        };
+        self.pending.insert(key, invalidation);
+    }
+}
+
+/// Queue of layer invalidations, sent by a background worker.
+/// Invalidations of the same layer are coalesced, failures are retried with an exponential
+/// backoff and the invalidations being sent are persisted to a file to survive a restart.
+#[derive(Debug, Clone)]
+pub struct InvalidationQueue {
+    state: Arc<(Mutex<QueueState>, Condvar)>,
+    persist_path: Option<PathBuf>,
+}
+
+impl InvalidationQueue {
+    /// Create a queue, reloading the invalidations persisted in the given file if any
+    pub fn new(persist_path: Option<PathBuf>) -> Self {
+        let mut state = QueueState::default();
+        if let Some(path) = persist_path.as_ref().filter(|path| path.exists()) {
+            let persisted = fs::read(path)
+                .map_err(|err| err.to_string())
+                .and_then(|data| {
+                    serde_json::from_slice::<Vec<(i32, String, PendingInvalidation)>>(&data)
+                        .map_err(|err| err.to_string())
+                });
+            match persisted {
+                Ok(persisted) => {
+                    for (infra_id, layer, invalidation) in persisted {
+                        state.push((infra_id, layer), invalidation);
+                    }
+                }
+                Err(err) => eprintln!(
+                    "{}",
+                    format!("Ignoring pending invalidations in {path:?}: {err}").red()
+                ),
+            }
+        }
+        Self {
+            state: Arc::new((Mutex::new(state), Condvar::new())),
+            persist_path,
+        }
+    }
+
+    /// Queue the invalidation of a whole layer
+    pub fn push_layer(&self, infra_id: i32, layer_slug: &str) {
+        self.push(infra_id, layer_slug, PendingInvalidation::Layer);
+    }
+
+    /// Queue the invalidation of a zone of a layer
+    pub fn push_bbox(&self, infra_id: i32, layer_slug: &str, zone: &InvalidationZone) {
+        self.push(
+            infra_id,
+            layer_slug,
+            PendingInvalidation::Zones(vec![zone.clone()]),
+        );
+    }
+
+    fn push(&self, infra_id: i32, layer_slug: &str, invalidation: PendingInvalidation) {
+        let (lock, condvar) = &*self.state;
+        lock.lock()
+            .unwrap()
+            .push((infra_id, layer_slug.into()), invalidation);
+        condvar.notify_all();
+    }
+
+    /// Move the pending invalidations of another queue to this one
+    pub fn push_all(&self, other: &InvalidationQueue) {
+        let pending = std::mem::take(&mut other.state.0.lock().unwrap().pending);
+        if pending.is_empty() {
+            return;
+        }
+        let (lock, condvar) = &*self.state;
+        let mut state = lock.lock().unwrap();
+        for (key, invalidation) in pending {
+            state.push(key, invalidation);
+        }
+        condvar.notify_all();
+    }
+
+    /// Write pending and in flight invalidations to the persistence file.
+    /// Only called by the sending side, not to slow down the pushes.
+    fn persist(&self) {
+        let path = match &self.persist_path {
+            Some(path) => path,
+            None => return,
+        };
+        let mut all = QueueState::default();
+        {
+            let state = self.state.0.lock().unwrap();
+            for (key, invalidation) in state.in_flight.iter().chain(state.pending.iter()) {
+                all.push(key.clone(), invalidation.clone());
+            }
+        }
+        let all: Vec<_> = all
+            .pending
+            .into_iter()
+            .map(|((infra_id, layer), invalidation)| (infra_id, layer, invalidation))
+            .collect();
+        // Write then rename to never leave a truncated file
+        let tmp_path = path.with_extension("tmp");
+        let result = fs::write(&tmp_path, serde_json::to_vec(&all).unwrap())
+            .and_then(|_| fs::rename(&tmp_path, path));
+        if let Err(err) = result {
+            eprintln!(
+                "{}",
+                format!("Failed to persist pending invalidations to {path:?}: {err}").red()
+            );
+        }
+    }
+
+    /// Send all pending invalidations.
+    /// On failure, the invalidations that weren't sent are queued again.
+    pub fn flush(&self, backend: &dyn InvalidationBackend) -> Result<(), InvalidationError> {
+        let (lock, _) = &*self.state;
+        let batch = {
+            let mut state = lock.lock().unwrap();
+            state.in_flight = std::mem::take(&mut state.pending);
+            state.in_flight.clone()
+        };
+        // Persist the batch before sending it to survive a restart
+        self.persist();
+
+        let mut result = Ok(());
+        let mut remaining = batch.into_iter();
+        for ((infra_id, layer), invalidation) in remaining.by_ref() {
+            let sent = match &invalidation {
+                PendingInvalidation::Layer => backend.invalidate_layer(infra_id, &layer),
+                PendingInvalidation::Zones(zones) => zones
+                    .iter()
+                    .try_for_each(|zone| backend.invalidate_bbox(infra_id, &layer, zone)),
+            };
+            if let Err(err) = sent {
+                // Zones already sent are invalidated again, which is harmless
+                lock.lock().unwrap().push((infra_id, layer), invalidation);
+                result = Err(err);
+                break;
+            }
+        }
+
+        {
+            let mut state = lock.lock().unwrap();
+            for (key, invalidation) in remaining {
+                state.push(key, invalidation);
+            }
+            state.in_flight.clear();
+        }
+        self.persist();
+        result
+    }
+
+    /// Spawn a thread sending queued invalidations with the given backend
+    pub fn spawn_worker(&self, backend: Box<dyn InvalidationBackend>) {
+        let queue = self.clone();
+        thread::spawn(move || {
+            let mut retry_delay = MIN_RETRY_DELAY;
+            loop {
+                {
+                    let (lock, condvar) = &*queue.state;
+                    let state = lock.lock().unwrap();
+                    let _state = condvar
+                        .wait_while(state, |state| state.pending.is_empty())
+                        .unwrap();
+                }
+                match queue.flush(backend.as_ref()) {
+                    Ok(()) => retry_delay = MIN_RETRY_DELAY,
+                    Err(err) => {
+                        eprintln!(
+                            "{}",
+                            format!(
+                                "Failed to invalidate layers, retrying in {}s: {err}",
+                                retry_delay.as_secs()
+                            )
+                            .red()
+                        );
+                        thread::sleep(retry_delay);
+                        retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InvalidationQueue, PendingInvalidation};
+    use crate::layer::{
+        BoundingBox, Invalidation, InvalidationBackend, InvalidationError, InvalidationZone,
+        RecordingBackend,
+    };
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn zone(min: f64, max: f64) -> InvalidationZone {
+        InvalidationZone {
+            geo: BoundingBox((min, min), (max, max)),
+            sch: BoundingBox((min, min), (max, max)),
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct FailingBackend {
+        failed: AtomicBool,
+    }
+
+    impl InvalidationBackend for FailingBackend {
+        fn invalidate_layer(&self, _: i32, _: &str) -> Result<(), InvalidationError> {
+            self.failed.store(true, Ordering::Relaxed);
+            Err(InvalidationError::Status(
+                reqwest::StatusCode::SERVICE_UNAVAILABLE,
+            ))
+        }
+
+        fn invalidate_bbox(
+            &self,
+            infra_id: i32,
+            layer_slug: &str,
+            _: &InvalidationZone,
+        ) -> Result<(), InvalidationError> {
+            self.invalidate_layer(infra_id, layer_slug)
+        }
+    }
+
+    #[test]
+    fn coalesce_zones() {
+        let merged = PendingInvalidation::Zones(vec![zone(0., 1.), zone(5., 6.)])
+            .merge(PendingInvalidation::Zones(vec![zone(0.5, 5.5)]));
+        assert_eq!(merged, PendingInvalidation::Zones(vec![zone(0., 6.)]));
+
+        let disjoint = PendingInvalidation::Zones(vec![zone(0., 1.)])
+            .merge(PendingInvalidation::Zones(vec![zone(2., 3.)]));
+        assert_eq!(
+            disjoint,
+            PendingInvalidation::Zones(vec![zone(0., 1.), zone(2., 3.)])
+        );
+
+        let layer = disjoint.merge(PendingInvalidation::Layer);
+        assert_eq!(layer, PendingInvalidation::Layer);
+    }
+
+    #[test]
+    fn flush_coalesced() {
+        let queue = InvalidationQueue::new(None);
+        queue.push_bbox(1, "signals", &zone(0., 1.));
+        queue.push_bbox(1, "signals", &zone(0.5, 2.));
+        queue.push_layer(1, "errors");
+        queue.push_bbox(1, "errors", &zone(0., 1.));
+
+        let backend = RecordingBackend::default();
+        queue.flush(&backend).unwrap();
+        assert_eq!(
+            backend.records(),
+            vec![
+                Invalidation::Layer {
+                    infra_id: 1,
+                    layer: "errors".into()
+                },
+                Invalidation::Bbox {
+                    infra_id: 1,
+                    layer: "signals".into(),
+                    zone: zone(0., 2.)
+                }
+            ]
+        );
+
+        // Nothing left to send
+        queue.flush(&backend).unwrap();
+        assert_eq!(backend.records().len(), 2);
+    }
+
+    #[test]
+    fn push_all_moves_pending() {
+        let held = InvalidationQueue::new(None);
+        held.push_bbox(1, "signals", &zone(0., 1.));
+        let queue = InvalidationQueue::new(None);
+        queue.push_bbox(1, "signals", &zone(0.5, 2.));
+        queue.push_all(&held);
+
+        let backend = RecordingBackend::default();
+        held.flush(&backend).unwrap();
+        assert!(backend.records().is_empty());
+        queue.flush(&backend).unwrap();
+        assert_eq!(
+            backend.records(),
+            vec![Invalidation::Bbox {
+                infra_id: 1,
+                layer: "signals".into(),
+                zone: zone(0., 2.)
+            }]
+        );
+    }
+
+    #[test]
+    fn failures_are_persisted_and_retried() {
+        let path = std::env::temp_dir().join(format!(
+            "editoast_pending_invalidations_{}.json",
+            std::process::id()
+        ));
+        let queue = InvalidationQueue::new(Some(path.clone()));
+        queue.push_layer(1, "signals");
+        queue.push_layer(2, "signals");
+
+        let failing = FailingBackend::default();
+        assert!(queue.flush(&failing).is_err());
+        assert!(failing.failed.load(Ordering::Relaxed));
+
+        // A restarted queue reloads the pending invalidations
+        let reloaded = InvalidationQueue::new(Some(path.clone()));
+        let backend = RecordingBackend::default();
+        reloaded.flush(&backend).unwrap();
+        assert_eq!(backend.records().len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "[]",
+            "sent invalidations are removed from the file"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
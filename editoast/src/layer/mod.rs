@@ -1,8 +1,17 @@
 mod bounding_box;
+mod definitions;
 mod invalidate_chartos;
+mod invalidation_backend;
+mod invalidation_queue;
 
-pub use bounding_box::{BoundingBox, InvalidationZone};
+pub use bounding_box::{BoundingBox, InvalidationZone, LayerInvalidationZones};
+pub use definitions::{get_layer_definition, parse_bbox, LayerView};
 pub use invalidate_chartos::{invalidate_bbox_chartos_layer, invalidate_chartos_layer};
+pub use invalidation_backend::{
+    ChartosBackend, Invalidation, InvalidationBackend, InvalidationBackendKind, InvalidationError,
+    NoopBackend, RecordingBackend,
+};
+pub use invalidation_queue::InvalidationQueue;
 
 use crate::client::ChartosConfig;
 use crate::infra_cache::InfraCache;
@@ -89,7 +98,7 @@ pub trait Layer {
         infra: i32,
         operations: &Vec<OperationResult>,
         infra_cache: &InfraCache,
-        invalid_zone: &InvalidationZone,
+        invalid_zones: &LayerInvalidationZones,
         chartos_config: &ChartosConfig,
     ) -> Result<(), Error> {
         let mut update_obj_ids = HashSet::new();
@@ -125,7 +134,9 @@ pub trait Layer {
         Self::delete_list(conn, infra, delete_obj_ids)?;
         Self::insert_update_list(conn, infra, update_obj_ids)?;
 
-        invalidate_bbox_chartos_layer(infra, Self::layer_name(), invalid_zone, chartos_config);
+        for zone in invalid_zones.get(Self::get_obj_type()) {
+            invalidate_bbox_chartos_layer(infra, Self::layer_name(), zone, chartos_config);
+        }
 
         Ok(())
     }
@@ -0,0 +1,25 @@
+SELECT jsonb_build_object(
+        'type',
+        'FeatureCollection',
+        'features',
+        COALESCE(
+            jsonb_agg(
+                jsonb_build_object(
+                    'type',
+                    'Feature',
+                    'geometry',
+                    ST_AsGeoJSON(ST_Transform(layer.{column}, 4326))::jsonb,
+                    'properties',
+                    {properties}
+                )
+            ),
+            '[]'::jsonb
+        )
+    )::text AS geojson
+FROM {table} AS layer
+    {join}
+WHERE layer.infra_id = $1
+    AND (
+        NOT $2
+        OR layer.{column} && ST_Transform(ST_MakeEnvelope($3, $4, $5, $6, 4326), 3857)
+    )
@@ -0,0 +1,14 @@
+WITH bounds AS (
+    SELECT ST_TileEnvelope($2, $3, $4) AS geom
+),
+tile AS (
+    SELECT ST_AsMVTGeom(layer.{column}, bounds.geom) AS geom,
+        {properties} AS properties
+    FROM {table} AS layer
+        {join}
+        CROSS JOIN bounds
+    WHERE layer.infra_id = $1
+        AND layer.{column} && bounds.geom
+)
+SELECT COALESCE(ST_AsMVT(tile, '{name}', 4096, 'geom'), '') AS mvt
+FROM tile
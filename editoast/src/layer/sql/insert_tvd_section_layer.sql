@@ -0,0 +1,68 @@
+WITH sections AS (
+    SELECT unnest($2::text []) AS section_id,
+        unnest($3::json []) AS data
+),
+ranges AS (
+    SELECT unnest($4::text []) AS section_id,
+        unnest($5::text []) AS track_id,
+        unnest($6::float []) AS slice_begin,
+        unnest($7::float []) AS slice_end
+),
+sliced_tracks AS (
+    SELECT ranges.section_id,
+        ST_Transform(
+            ST_LineSubstring(
+                ST_GeomFromGeoJSON(tracks.data->'geo'),
+                GREATEST(
+                    LEAST(
+                        ranges.slice_begin / (tracks.data->'length')::float,
+                        1.
+                    ),
+                    0.
+                ),
+                LEAST(
+                    GREATEST(
+                        ranges.slice_end / (tracks.data->'length')::float,
+                        0.
+                    ),
+                    1.
+                )
+            ),
+            3857
+        ) AS geo,
+        ST_Transform(
+            ST_LineSubstring(
+                ST_GeomFromGeoJSON(tracks.data->'sch'),
+                GREATEST(
+                    LEAST(
+                        ranges.slice_begin / (tracks.data->'length')::float,
+                        1.
+                    ),
+                    0.
+                ),
+                LEAST(
+                    GREATEST(
+                        ranges.slice_end / (tracks.data->'length')::float,
+                        0.
+                    ),
+                    1.
+                )
+            ),
+            3857
+        ) AS sch
+    FROM ranges
+        INNER JOIN osrd_infra_tracksectionmodel AS tracks ON tracks.obj_id = ranges.track_id
+        AND tracks.infra_id = $1
+)
+INSERT INTO osrd_infra_tvdsectionlayer (obj_id, infra_id, geographic, schematic, data)
+SELECT sections.section_id,
+    $1,
+    ST_Collect(sliced_tracks.geo),
+    ST_Collect(sliced_tracks.sch),
+    sections.data::jsonb
+FROM sections
+    INNER JOIN sliced_tracks ON sliced_tracks.section_id = sections.section_id
+WHERE GeometryType(sliced_tracks.geo) = 'LINESTRING'
+    AND GeometryType(sliced_tracks.sch) = 'LINESTRING'
+GROUP BY sections.section_id,
+    sections.data::jsonb
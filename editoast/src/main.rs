@@ -6,28 +6,36 @@ extern crate diesel;
 #[macro_use]
 extern crate rocket_contrib;
 
+mod auth;
 mod client;
 mod error;
+mod events;
 mod generate;
 mod infra_cache;
+mod infra_caches;
 mod layer;
 mod models;
 mod objects;
 mod schema;
 mod views;
 
-use chashmap::CHashMap;
 use clap::Parser;
-use client::{ChartosConfig, Client, Commands, GenerateArgs, PostgresConfig, RunserverArgs};
+use client::{
+    AuthConfig, ChartosConfig, Client, Commands, GenerateArgs, PostgresConfig, RunserverArgs,
+};
 use colored::*;
 use diesel::{Connection, PgConnection};
+use events::InfraEventBus;
 use infra_cache::InfraCache;
+use infra_caches::InfraCaches;
+use models::webhooks::WebhookNotifier;
 use models::{DBConnection, Infra};
 use rocket::config::{Limits, Value};
 use rocket::Rocket;
 use rocket_cors::CorsOptions;
 use std::collections::HashMap;
 use std::error::Error;
+use std::fs;
 use std::process::exit;
 
 fn main() {
@@ -44,17 +52,19 @@ fn run() -> Result<(), Box<dyn Error + Send + Sync>> {
     let client = Client::parse();
     let pg_config = client.postgres_config;
     let chartos_config = client.chartos_config;
+    let auth_config = client.auth_config;
 
     match client.command {
-        Commands::Runserver(args) => runserver(args, pg_config, chartos_config),
+        Commands::Runserver(args) => runserver(args, pg_config, chartos_config, auth_config),
         Commands::Generate(args) => generate(args, pg_config, chartos_config),
     }
 }
 pub fn create_server(
-    infra_caches: CHashMap<i32, InfraCache>,
+    infra_caches: InfraCaches,
     port: u16,
     pg_config: &PostgresConfig,
     chartos_config: ChartosConfig,
+    auth_config: AuthConfig,
 ) -> Rocket {
     // Config server
     let databases = HashMap::from([(
@@ -85,11 +95,18 @@ pub fn create_server(
     // Setup CORS
     let cors = CorsOptions::default().to_cors().unwrap();
 
+    // Leave half of the workers to the requests other than event streams
+    let event_bus = InfraEventBus::new((config.workers as usize / 2).max(1));
+
     let mut rocket = rocket::custom(config)
         .attach(DBConnection::fairing())
         .attach(cors)
         .manage(infra_caches)
-        .manage(chartos_config);
+        .manage(chartos_config)
+        .manage(auth_config)
+        .manage(event_bus)
+        .manage(WebhookNotifier::default())
+        .register(catchers![auth::unauthorized]);
 
     // Mount routes
     for (base, routes) in views::routes() {
@@ -101,25 +118,39 @@ pub fn create_server(
 fn runserver(
     args: RunserverArgs,
     pg_config: PostgresConfig,
-    chartos_config: ChartosConfig,
+    mut chartos_config: ChartosConfig,
+    auth_config: AuthConfig,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let conn = PgConnection::establish(&pg_config.url()).expect("Error while connecting DB");
-    let infras = Infra::list(&conn);
+    // Infra caches are loaded on first access, or at startup from their up to date snapshots
+    let memory_budget = args.infra_cache_budget.map(|budget| budget * 1024 * 1024);
+    let infra_caches = InfraCaches::new(memory_budget, args.infra_cache_snapshots.clone());
+    if let Some(snapshot_dir) = args.infra_cache_snapshots.as_ref() {
+        fs::create_dir_all(snapshot_dir)?;
+        let conn = PgConnection::establish(&pg_config.url())?;
+        let loaded = infra_caches.load_snapshots(&conn);
+        println!("✅ Loaded {loaded} infra caches from snapshots");
+    }
 
-    // Initialize infra caches
-    let infra_caches = CHashMap::new();
-    for infra in infras.iter() {
-        println!(
-            "🍞 Loading cache for infra {}[{}]...",
-            infra.name.bold(),
-            infra.id
+    // Layer invalidations are sent in the background not to fail or slow down edits
+    chartos_config.start_invalidation_worker();
+
+    if auth_config.disable_auth {
+        eprintln!(
+            "{}",
+            "Warning: Authentication is disabled, every request is accepted!".yellow()
         );
-        let infra_cache = InfraCache::load(&conn, infra.id);
-        infra_caches.insert_new(infra.id, infra_cache);
     }
-    println!("✅ Done loading infra caches!");
 
-    let rocket = create_server(infra_caches, args.port, &pg_config, chartos_config);
+    let mut rocket = create_server(
+        infra_caches,
+        args.port,
+        &pg_config,
+        chartos_config,
+        auth_config,
+    );
+    if args.tile_server {
+        rocket = rocket.mount("/layers", views::layers::routes());
+    }
 
     // Run server
     rocket.launch();
@@ -155,7 +186,16 @@ fn generate(
             infra.name.bold(),
             infra.id
         );
-        let infra_cache = InfraCache::load(&conn, infra.id);
+        let infra_cache = match InfraCache::load(&conn, infra.id) {
+            Ok(infra_cache) => infra_cache,
+            Err(err) => {
+                eprintln!(
+                    "{}",
+                    format!("❌ Infra {}[{}] is broken: {err}", infra.name, infra.id).red()
+                );
+                continue;
+            }
+        };
         generate::refresh(&conn, &infra, args.force, &chartos_config, &infra_cache)?;
         println!("✅ Infra {}[{}] generated!", infra.name.bold(), infra.id);
     }
@@ -0,0 +1,251 @@
+use std::fmt;
+
+use diesel::result::Error as DieselError;
+use diesel::sql_types::{BigInt, Integer, Jsonb, Nullable, Text, Uuid as SqlUuid};
+use diesel::{
+    insert_into, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::infra::unix_now;
+use crate::error::ApiError;
+use crate::objects::operation::OperationResult;
+use crate::objects::OSRDObject;
+use crate::schema::osrd_infra_infraauditentry;
+use crate::views::pagination::{paginate, PaginationError};
+
+/// Mutation of an infra kept in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Create,
+    Delete,
+    Edit,
+    Lock,
+    Unlock,
+    Refresh,
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self {
+            AuditAction::Create => "create",
+            AuditAction::Delete => "delete",
+            AuditAction::Edit => "edit",
+            AuditAction::Lock => "lock",
+            AuditAction::Unlock => "unlock",
+            AuditAction::Refresh => "refresh",
+        };
+        write!(f, "{}", action)
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "osrd_infra_infraauditentry"]
+struct NewAuditEntry<'a> {
+    infra_id: i32,
+    user: &'a Uuid,
+    timestamp: i64,
+    action: String,
+    version_before: Option<&'a str>,
+    version_after: Option<&'a str>,
+    summary: Value,
+}
+
+/// Record a change of an infra in the audit log.
+/// Must be called in the transaction of the change not to log changes that were rolled back.
+pub fn record_audit(
+    conn: &PgConnection,
+    infra_id: i32,
+    user: &Uuid,
+    action: AuditAction,
+    version_before: Option<&str>,
+    version_after: Option<&str>,
+    summary: Value,
+) -> Result<(), DieselError> {
+    insert_into(osrd_infra_infraauditentry::table)
+        .values(NewAuditEntry {
+            infra_id,
+            user,
+            timestamp: unix_now(),
+            action: action.to_string(),
+            version_before,
+            version_after,
+            summary,
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Return the user who created an infra, as recorded in its audit log.
+/// It remains known once the infra is deleted.
+pub fn audit_creator(conn: &PgConnection, infra_id: i32) -> Result<Option<Uuid>, DieselError> {
+    use crate::schema::osrd_infra_infraauditentry::dsl;
+    dsl::osrd_infra_infraauditentry
+        .filter(dsl::infra_id.eq(infra_id))
+        .filter(dsl::action.eq(AuditAction::Create.to_string()))
+        .select(dsl::user)
+        .first(conn)
+        .optional()
+}
+
+/// Summarize operations as the list of the objects they changed
+pub fn operations_summary(operations: &[OperationResult]) -> Value {
+    let operations: Vec<_> = operations
+        .iter()
+        .map(|op| {
+            let (operation, obj_ref) = match op {
+                OperationResult::Create(railjson) => ("create", railjson.get_ref()),
+                OperationResult::Update(railjson) => ("update", railjson.get_ref()),
+                OperationResult::Delete(obj_ref) => ("delete", obj_ref.clone()),
+            };
+            json!({
+                "operation": operation,
+                "type": obj_ref.obj_type,
+                "id": obj_ref.obj_id,
+            })
+        })
+        .collect();
+    json!({ "operations": operations })
+}
+
+#[derive(QueryableByName, Debug)]
+struct AuditEntryQueryable {
+    #[sql_type = "BigInt"]
+    count: i64,
+    #[sql_type = "Integer"]
+    id: i32,
+    #[sql_type = "SqlUuid"]
+    user: Uuid,
+    #[sql_type = "BigInt"]
+    timestamp: i64,
+    #[sql_type = "Text"]
+    action: String,
+    #[sql_type = "Nullable<Text>"]
+    version_before: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    version_after: Option<String>,
+    #[sql_type = "Jsonb"]
+    summary: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub user: Uuid,
+    /// Unix timestamp of the change
+    pub timestamp: i64,
+    pub action: String,
+    pub version_before: Option<String>,
+    pub version_after: Option<String>,
+    pub summary: Value,
+}
+
+impl From<AuditEntryQueryable> for AuditEntry {
+    fn from(entry: AuditEntryQueryable) -> Self {
+        Self {
+            id: entry.id,
+            user: entry.user,
+            timestamp: entry.timestamp,
+            action: entry.action,
+            version_before: entry.version_before,
+            version_after: entry.version_after,
+            summary: entry.summary,
+        }
+    }
+}
+
+/// Return a page of the audit log of an infra, latest changes first,
+/// optionally only the changes of a user or of an action
+pub fn get_paginated_audit(
+    conn: &PgConnection,
+    infra: i32,
+    page: i64,
+    per_page: i64,
+    user: Option<Uuid>,
+    action: Option<String>,
+) -> Result<(Vec<AuditEntry>, i64), Box<dyn ApiError>> {
+    let query = String::from(
+        "SELECT id, \"user\", timestamp, action, version_before, version_after, summary
+         FROM osrd_infra_infraauditentry
+         WHERE infra_id = $1 AND ($2 IS NULL OR \"user\" = $2) AND ($3 IS NULL OR action = $3)
+         ORDER BY id DESC",
+    );
+    let entries = paginate(query, page, per_page)
+        .bind::<Integer, _>(infra)
+        .bind::<Nullable<SqlUuid>, _>(user)
+        .bind::<Nullable<Text>, _>(action)
+        .load::<AuditEntryQueryable>(conn)?;
+    let count = entries.first().map(|e| e.count).unwrap_or_default();
+    let entries: Vec<AuditEntry> = entries.into_iter().map(|e| e.into()).collect();
+    if entries.is_empty() && page > 1 {
+        return Err(Box::new(PaginationError));
+    }
+    Ok((entries, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_paginated_audit, operations_summary, record_audit, AuditAction};
+    use crate::models::infra::tests::test_transaction;
+    use crate::objects::operation::OperationResult;
+    use crate::objects::{ObjectRef, ObjectType};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[test]
+    fn summary_of_operations() {
+        let operations = vec![OperationResult::Delete(ObjectRef::new(
+            ObjectType::Signal,
+            "S1",
+        ))];
+        assert_eq!(
+            operations_summary(&operations),
+            json!({
+                "operations": [{"operation": "delete", "type": "Signal", "id": "S1"}]
+            })
+        );
+    }
+
+    #[test]
+    fn record_and_filter() {
+        test_transaction(|conn, infra| {
+            let alice = Uuid::new_v4();
+            let bob = Uuid::new_v4();
+            record_audit(
+                conn,
+                infra.id,
+                &alice,
+                AuditAction::Lock,
+                Some("0"),
+                Some("0"),
+                json!({}),
+            )
+            .unwrap();
+            record_audit(
+                conn,
+                infra.id,
+                &bob,
+                AuditAction::Edit,
+                Some("0"),
+                Some("1"),
+                json!({}),
+            )
+            .unwrap();
+
+            let (entries, count) = get_paginated_audit(conn, infra.id, 1, 10, None, None).unwrap();
+            assert_eq!(count, 2);
+            assert_eq!(entries[0].action, "edit", "latest changes come first");
+
+            let (entries, count) =
+                get_paginated_audit(conn, infra.id, 1, 10, Some(alice), None).unwrap();
+            assert_eq!(count, 1);
+            assert_eq!(entries[0].user, alice);
+
+            let (_, count) =
+                get_paginated_audit(conn, infra.id, 1, 10, None, Some("unlock".into())).unwrap();
+            assert_eq!(count, 0);
+        });
+    }
+}
@@ -61,7 +61,8 @@ pub fn generate_errors(infra_cache: &InfraCache) -> Vec<InfraError> {
                 "position",
                 buffer_stop.position,
                 [0.0, track_cache.length],
-            );
+            )
+            .with_clamp_fix(ObjectType::BufferStop);
             errors.push(infra_error);
         }
     }
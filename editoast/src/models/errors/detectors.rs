@@ -58,7 +58,8 @@ pub fn generate_errors(infra_cache: &InfraCache) -> Vec<InfraError> {
                 "position",
                 detector.position,
                 [0.0, track_cache.length],
-            );
+            )
+            .with_clamp_fix(ObjectType::Detector);
             errors.push(infra_error);
         }
     }
@@ -0,0 +1,302 @@
+use std::collections::BTreeSet;
+
+use super::graph::Graph;
+use super::{generate_all_errors, InfraError, InfraErrorType};
+use crate::error::ApiError;
+use crate::infra_cache::InfraCache;
+use crate::objects::operation::{Operation, RailjsonObject, UpdateOperation};
+use crate::objects::{BufferStop, ObjectRef, ObjectType};
+use rocket::http::Status;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+/// Select an infra error of which the suggested fix must be applied
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ErrorFixSelector {
+    pub obj_id: String,
+    pub obj_type: ObjectType,
+    pub error_type: String,
+    #[serde(default)]
+    pub field: String,
+}
+
+#[derive(Debug, Error)]
+pub enum FixError {
+    #[error("No '{error_type}' error found on the field '{field}' of the object '{obj_id}'")]
+    ErrorNotFound {
+        obj_id: String,
+        error_type: String,
+        field: String,
+    },
+    #[error("The '{error_type}' error on the object '{obj_id}' can't be fixed automatically")]
+    NoFixAvailable { obj_id: String, error_type: String },
+}
+
+impl ApiError for FixError {
+    fn get_status(&self) -> Status {
+        match self {
+            FixError::ErrorNotFound { .. } => Status::NotFound,
+            FixError::NoFixAvailable { .. } => Status::BadRequest,
+        }
+    }
+
+    fn get_type(&self) -> &'static str {
+        match self {
+            FixError::ErrorNotFound { .. } => "editoast:errors:fix:ErrorNotFound",
+            FixError::NoFixAvailable { .. } => "editoast:errors:fix:NoFixAvailable",
+        }
+    }
+
+    fn extra(&self) -> Option<Map<String, Value>> {
+        match self {
+            FixError::ErrorNotFound {
+                obj_id,
+                error_type,
+                field,
+            } => Some(json!({ "obj_id": obj_id, "error_type": error_type, "field": field })),
+            FixError::NoFixAvailable { obj_id, error_type } => {
+                Some(json!({ "obj_id": obj_id, "error_type": error_type }))
+            }
+        }
+        .map(|extra| extra.as_object().unwrap().clone())
+    }
+}
+
+impl InfraError {
+    /// Attach operations that would fix the error
+    pub(super) fn with_fix(mut self, fix: Vec<Operation>) -> Self {
+        self.fix = fix;
+        self
+    }
+
+    /// Attach to an out of range error the operation moving the position to the closest bound
+    pub(super) fn with_clamp_fix(self, obj_type: ObjectType) -> Self {
+        let (position, [min, max]) = match self.sub_type {
+            InfraErrorType::OutOfRange {
+                position,
+                expected_range,
+            } => (position, expected_range),
+            _ => return self,
+        };
+        let obj_ref = ObjectRef::new(obj_type, &self.obj_id);
+        let path = field_to_json_pointer(&self.field);
+        let fix = patch_operation(
+            obj_ref,
+            json!([{ "op": "replace", "path": path, "value": position.clamp(min, max) }]),
+        );
+        self.with_fix(vec![fix])
+    }
+
+    /// Name of the error type as serialized in the `error_type` field
+    fn error_type_name(&self) -> String {
+        let value = serde_json::to_value(&self.sub_type).unwrap();
+        value["error_type"].as_str().unwrap().to_string()
+    }
+}
+
+/// Convert an error field such as `path.0.begin` to a json pointer such as `/path/0/begin`
+fn field_to_json_pointer(field: &str) -> String {
+    field
+        .split('.')
+        .map(|token| format!("/{}", token.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+fn patch_operation(obj_ref: ObjectRef, patch: Value) -> Operation {
+    Operation::Update(UpdateOperation {
+        obj_id: obj_ref.obj_id,
+        obj_type: obj_ref.obj_type,
+        railjson_patch: serde_json::from_value(patch).unwrap(),
+    })
+}
+
+/// Create a buffer stop at the given position of a track section
+pub(super) fn create_buffer_stop(track_id: &str, position: f64) -> Operation {
+    Operation::Create(Box::new(RailjsonObject::BufferStop {
+        railjson: BufferStop {
+            track: ObjectRef::new(ObjectType::TrackSection, track_id),
+            position,
+            ..Default::default()
+        },
+    }))
+}
+
+/// Remove a port of a switch type, checking first that the port still has the expected index
+pub(super) fn remove_switch_type_port(switch_type_id: &str, pos: usize, port: &str) -> Operation {
+    let path = format!("/ports/{}", pos);
+    patch_operation(
+        ObjectRef::new(ObjectType::SwitchType, switch_type_id),
+        json!([
+            { "op": "test", "path": path, "value": port },
+            { "op": "remove", "path": path },
+        ]),
+    )
+}
+
+/// Remove a group of a switch type
+pub(super) fn remove_switch_type_group(switch_type_id: &str, group_name: &str) -> Operation {
+    patch_operation(
+        ObjectRef::new(ObjectType::SwitchType, switch_type_id),
+        json!([{ "op": "remove", "path": field_to_json_pointer(&format!("groups.{}", group_name)) }]),
+    )
+}
+
+/// Return the operations fixing the selected errors.
+/// Errors are regenerated from the given infra cache to make sure fixes are up to date.
+pub fn get_selected_fixes(
+    infra_cache: &InfraCache,
+    selectors: &[ErrorFixSelector],
+) -> Result<Vec<Operation>, FixError> {
+    let graph = Graph::load(infra_cache);
+    let errors = generate_all_errors(infra_cache, &graph);
+
+    let mut selected = BTreeSet::new();
+    for selector in selectors {
+        let (index, (_, error)) = errors
+            .iter()
+            .enumerate()
+            .find(|(_, (obj_type, error))| {
+                *obj_type == selector.obj_type
+                    && error.obj_id == selector.obj_id
+                    && error.field == selector.field
+                    && error.error_type_name() == selector.error_type
+            })
+            .ok_or_else(|| FixError::ErrorNotFound {
+                obj_id: selector.obj_id.clone(),
+                error_type: selector.error_type.clone(),
+                field: selector.field.clone(),
+            })?;
+        if error.fix.is_empty() {
+            return Err(FixError::NoFixAvailable {
+                obj_id: selector.obj_id.clone(),
+                error_type: selector.error_type.clone(),
+            });
+        }
+        selected.insert(index);
+    }
+
+    // Errors are generated by increasing port index, applying fixes in reverse order
+    // keeps the index of the ports removed later valid.
+    Ok(selected
+        .into_iter()
+        .rev()
+        .flat_map(|index| errors[index].1.fix.clone())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{field_to_json_pointer, get_selected_fixes, ErrorFixSelector, FixError};
+    use crate::infra_cache::tests::{
+        create_signal_cache, create_small_infra_cache, create_switch_type_cache,
+    };
+    use crate::objects::operation::Operation;
+    use crate::objects::{ObjectRef, ObjectType};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn selector(
+        obj_id: &str,
+        obj_type: ObjectType,
+        error_type: &str,
+        field: &str,
+    ) -> ErrorFixSelector {
+        ErrorFixSelector {
+            obj_id: obj_id.into(),
+            obj_type,
+            error_type: error_type.into(),
+            field: field.into(),
+        }
+    }
+
+    #[test]
+    fn json_pointer() {
+        assert_eq!(field_to_json_pointer("path.0.begin"), "/path/0/begin");
+        assert_eq!(field_to_json_pointer("groups.a/b"), "/groups/a~1b");
+    }
+
+    #[test]
+    fn fix_out_of_range() {
+        let mut infra_cache = create_small_infra_cache();
+        infra_cache.add(create_signal_cache("S_error", "A", 530.));
+        let selectors = [selector(
+            "S_error",
+            ObjectType::Signal,
+            "out_of_range",
+            "position",
+        )];
+        let fix = get_selected_fixes(&infra_cache, &selectors).unwrap();
+        assert_eq!(fix.len(), 1);
+        let Operation::Update(update) = &fix[0] else {
+            panic!("Expected an update operation")
+        };
+        assert_eq!(update.obj_id, "S_error");
+        assert_eq!(
+            serde_json::to_value(&update.railjson_patch).unwrap(),
+            json!([{ "op": "replace", "path": "/position", "value": 500. }])
+        );
+    }
+
+    #[test]
+    fn fix_missing_buffer_stop() {
+        let mut infra_cache = create_small_infra_cache();
+        infra_cache.apply_delete(&ObjectRef::new(ObjectType::BufferStop, "BF1"));
+        let selectors = [selector(
+            "A",
+            ObjectType::TrackSection,
+            "no_buffer_stop",
+            "buffer_stop",
+        )];
+        let fix = get_selected_fixes(&infra_cache, &selectors).unwrap();
+        assert_eq!(fix.len(), 1);
+        let Operation::Create(create) = &fix[0] else {
+            panic!("Expected a create operation")
+        };
+        let railjson = serde_json::to_value(create).unwrap();
+        assert_eq!(railjson["obj_type"], "BufferStop");
+        assert_eq!(railjson["railjson"]["track"]["id"], "A");
+        assert_eq!(railjson["railjson"]["position"], 0.);
+    }
+
+    #[test]
+    fn fix_unused_ports_in_reverse_order() {
+        let mut infra_cache = create_small_infra_cache();
+        infra_cache.add(create_switch_type_cache(
+            "ST_error",
+            vec!["A".into(), "B".into(), "C".into()],
+            HashMap::new(),
+        ));
+        let selectors = [
+            selector("ST_error", ObjectType::SwitchType, "unused_port", "ports.0"),
+            selector("ST_error", ObjectType::SwitchType, "unused_port", "ports.2"),
+        ];
+        let fix = get_selected_fixes(&infra_cache, &selectors).unwrap();
+        let paths: Vec<_> = fix
+            .iter()
+            .map(|op| match op {
+                Operation::Update(update) => {
+                    serde_json::to_value(&update.railjson_patch).unwrap()[1]["path"].clone()
+                }
+                _ => panic!("Expected an update operation"),
+            })
+            .collect();
+        assert_eq!(paths, vec![json!("/ports/2"), json!("/ports/0")]);
+    }
+
+    #[test]
+    fn fix_unknown_error() {
+        let infra_cache = create_small_infra_cache();
+        let selectors = [selector(
+            "S_error",
+            ObjectType::Signal,
+            "out_of_range",
+            "position",
+        )];
+        assert!(matches!(
+            get_selected_fixes(&infra_cache, &selectors),
+            Err(FixError::ErrorNotFound { .. })
+        ));
+    }
+}
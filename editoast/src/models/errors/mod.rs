@@ -1,5 +1,6 @@
 pub mod buffer_stops;
 pub mod detectors;
+pub mod fix;
 pub mod graph;
 pub mod operational_points;
 pub mod routes;
@@ -10,19 +11,23 @@ pub mod switches;
 pub mod track_section_links;
 pub mod track_sections;
 
+use derivative::Derivative;
 use diesel::result::Error as DieselError;
 use diesel::{sql_query, sql_types::Integer, PgConnection, RunQueryDsl};
 use serde::{Deserialize, Serialize};
 
 use crate::client::ChartosConfig;
 use crate::layer::invalidate_chartos_layer;
+use crate::objects::operation::Operation;
+use crate::objects::ObjectType;
 use crate::{infra_cache::InfraCache, objects::ObjectRef};
 
 use self::routes::PathEndpointField;
 
 use graph::Graph;
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, Derivative, Debug)]
+#[derivative(PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct InfraError {
     field: String,
@@ -31,6 +36,10 @@ pub struct InfraError {
     sub_type: InfraErrorType,
     #[serde(skip)]
     obj_id: String,
+    /// Operations suggested to automatically fix the error
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[derivative(PartialEq = "ignore")]
+    fix: Vec<Operation>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -73,6 +82,8 @@ enum InfraErrorType {
     OverlappingSwitches { reference: ObjectRef },
     #[serde(rename = "overlapping_track_links")]
     OverlappingTrackLinks { reference: ObjectRef },
+    #[serde(rename = "uncovered_switch_group")]
+    UncoveredSwitchGroup { group: String },
 }
 
 impl InfraError {
@@ -86,6 +97,7 @@ impl InfraError {
             is_warning: false,
             sub_type: InfraErrorType::InvalidReference { reference },
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -103,6 +115,7 @@ impl InfraError {
                 expected_range,
             },
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -112,6 +125,7 @@ impl InfraError {
             is_warning: false,
             sub_type: InfraErrorType::EmptyPath,
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -131,6 +145,7 @@ impl InfraError {
                 endpoint_field,
             },
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -140,6 +155,7 @@ impl InfraError {
             is_warning: true,
             sub_type: InfraErrorType::EmptyObject,
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -154,6 +170,7 @@ impl InfraError {
             is_warning: false,
             sub_type: InfraErrorType::ObjectOutOfPath { position, track },
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -163,6 +180,17 @@ impl InfraError {
             is_warning: true,
             sub_type: InfraErrorType::MissingRoute,
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
+        }
+    }
+
+    fn new_uncovered_switch_group<U: AsRef<str>>(obj_id: U, group: String) -> Self {
+        Self {
+            field: Default::default(),
+            is_warning: true,
+            sub_type: InfraErrorType::UncoveredSwitchGroup { group },
+            obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -176,6 +204,7 @@ impl InfraError {
             is_warning: false,
             sub_type: InfraErrorType::UnknownPortName { port_name },
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -185,6 +214,7 @@ impl InfraError {
             is_warning: false,
             sub_type: InfraErrorType::InvalidSwitchPorts,
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -198,6 +228,7 @@ impl InfraError {
             is_warning: true,
             sub_type: InfraErrorType::UnusedPort { port_name },
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -213,6 +244,7 @@ impl InfraError {
                 original_group_path,
             },
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -222,6 +254,7 @@ impl InfraError {
             is_warning: true,
             sub_type: InfraErrorType::NoBufferStop,
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -231,6 +264,7 @@ impl InfraError {
             is_warning: false,
             sub_type: InfraErrorType::PathIsNotContinuous,
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -240,6 +274,7 @@ impl InfraError {
             is_warning: false,
             sub_type: InfraErrorType::OverlappingSwitches { reference },
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 
@@ -249,10 +284,59 @@ impl InfraError {
             is_warning: true,
             sub_type: InfraErrorType::OverlappingTrackLinks { reference },
             obj_id: obj_id.as_ref().into(),
+            fix: vec![],
         }
     }
 }
 
+/// Generate in memory all the errors and warnings of the infra along with the type of their object
+pub fn generate_all_errors(
+    infra_cache: &InfraCache,
+    graph: &Graph,
+) -> Vec<(ObjectType, InfraError)> {
+    let mut errors = vec![];
+    let generated = [
+        (
+            ObjectType::TrackSection,
+            track_sections::generate_errors(infra_cache, graph),
+        ),
+        (ObjectType::Signal, signals::generate_errors(infra_cache)),
+        (
+            ObjectType::SpeedSection,
+            speed_sections::generate_errors(infra_cache),
+        ),
+        (
+            ObjectType::TrackSectionLink,
+            track_section_links::generate_errors(infra_cache),
+        ),
+        (
+            ObjectType::SwitchType,
+            switch_types::generate_errors(infra_cache),
+        ),
+        (ObjectType::Switch, switches::generate_errors(infra_cache)),
+        (
+            ObjectType::Detector,
+            detectors::generate_errors(infra_cache),
+        ),
+        (
+            ObjectType::BufferStop,
+            buffer_stops::generate_errors(infra_cache),
+        ),
+        (
+            ObjectType::Route,
+            routes::generate_errors(infra_cache, graph),
+        ),
+        (
+            ObjectType::OperationalPoint,
+            operational_points::generate_errors(infra_cache),
+        ),
+    ];
+    for (obj_type, infra_errors) in generated {
+        errors.extend(infra_errors.into_iter().map(|e| (obj_type, e)));
+    }
+    errors
+}
+
 /// This function regenerate the errors and warnings of the infra
 pub fn generate_errors(
     conn: &PgConnection,
@@ -68,7 +68,8 @@ pub fn generate_errors(infra_cache: &InfraCache) -> Vec<InfraError> {
                     format!("parts.{}.position", index),
                     part.position,
                     [0.0, track_cache.length],
-                );
+                )
+                .with_clamp_fix(ObjectType::OperationalPoint);
                 errors.push(infra_error);
             }
         }
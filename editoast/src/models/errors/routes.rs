@@ -166,7 +166,8 @@ pub fn generate_errors(infra_cache: &InfraCache, graph: &Graph) -> Vec<InfraErro
                         format!("path.{}.{}", index, field),
                         pos,
                         [0.0, track_cache.length],
-                    );
+                    )
+                    .with_clamp_fix(ObjectType::Route);
                     errors.push(infra_error);
                     skip_continuous_path = true;
                 }
@@ -58,7 +58,8 @@ pub fn generate_errors(infra_cache: &InfraCache) -> Vec<InfraError> {
                 "position",
                 signal.position,
                 [0.0, track_cache.length],
-            );
+            )
+            .with_clamp_fix(ObjectType::Signal);
             errors.push(infra_error);
         }
     }
@@ -68,7 +68,8 @@ pub fn generate_errors(infra_cache: &InfraCache) -> Vec<InfraError> {
                         format!("track_ranges.{}.{}", index, field),
                         pos,
                         [0.0, track_cache.length],
-                    );
+                    )
+                    .with_clamp_fix(ObjectType::SpeedSection);
                     errors.push(infra_error);
                 }
             }
@@ -3,7 +3,7 @@ use std::collections::{HashMap, HashSet};
 use diesel::sql_types::{Array, Integer, Json, Text};
 use diesel::{sql_query, PgConnection, RunQueryDsl};
 
-use super::InfraError;
+use super::{fix, InfraError};
 use crate::infra_cache::InfraCache;
 use diesel::result::Error as DieselError;
 use serde_json::to_value;
@@ -61,7 +61,11 @@ pub fn generate_errors(infra_cache: &InfraCache) -> Vec<InfraError> {
                     switch_type_id.clone(),
                     format!("groups.{}", group_name),
                     format!("groups.{}", duplicate_group),
-                );
+                )
+                .with_fix(vec![fix::remove_switch_type_group(
+                    switch_type_id,
+                    group_name,
+                )]);
                 errors.push(infra_error);
             } else {
                 duplicate_port_connection.insert(group, group_name);
@@ -78,7 +82,12 @@ pub fn generate_errors(infra_cache: &InfraCache) -> Vec<InfraError> {
                 switch_type_id.clone(),
                 format!("ports.{}", pos),
                 port.into(),
-            );
+            )
+            .with_fix(vec![fix::remove_switch_type_port(
+                switch_type_id,
+                pos,
+                port,
+            )]);
             errors.push(infra_error);
         }
     }
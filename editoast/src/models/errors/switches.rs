@@ -34,10 +34,25 @@ pub fn insert_errors(
     Ok(())
 }
 
+/// Retrieve the pairs of track endpoints crossed by the routes (the end of a range and the begin of the next one)
+fn get_route_crossings(infra_cache: &InfraCache) -> HashSet<(TrackEndpoint, TrackEndpoint)> {
+    let mut crossings = HashSet::new();
+    for route in infra_cache.routes().values() {
+        let route = route.unwrap_route();
+        for (prev, next) in route.path.iter().zip(route.path.iter().skip(1)) {
+            if prev.track != next.track {
+                crossings.insert((prev.get_end(), next.get_begin()));
+            }
+        }
+    }
+    crossings
+}
+
 pub fn generate_errors(infra_cache: &InfraCache) -> Vec<InfraError> {
     let mut errors = vec![];
 
     let mut switch_cache = HashMap::<&TrackEndpoint, ObjectRef>::new();
+    let route_crossings = get_route_crossings(infra_cache);
 
     for (switch_id, switch) in infra_cache.switches().iter() {
         let switch = switch.unwrap_switch();
@@ -106,6 +121,30 @@ pub fn generate_errors(infra_cache: &InfraCache) -> Vec<InfraError> {
         for port in switch.ports.values() {
             switch_cache.insert(port, switch_ref.clone());
         }
+
+        // Check that each group of the switch is crossed by at least one route
+        let mut groups: Vec<_> = switch_type.groups.iter().collect();
+        groups.sort_by_key(|(name, _)| *name);
+        for (group_name, connections) in groups {
+            let is_covered = connections.iter().any(|connection| {
+                match (
+                    switch.ports.get(&connection.src),
+                    switch.ports.get(&connection.dst),
+                ) {
+                    (Some(src), Some(dst)) => {
+                        route_crossings.contains(&(src.clone(), dst.clone()))
+                            || (connection.bidirectional
+                                && route_crossings.contains(&(dst.clone(), src.clone())))
+                    }
+                    _ => false,
+                }
+            });
+            if !is_covered {
+                let infra_error =
+                    InfraError::new_uncovered_switch_group(switch_id.clone(), group_name.clone());
+                errors.push(infra_error);
+            }
+        }
     }
 
     errors
@@ -187,4 +226,14 @@ mod tests {
         let errors = generate_errors(&infra_cache);
         assert_eq!(1, errors.len());
     }
+
+    #[test]
+    fn uncovered_switch_group() {
+        let mut infra_cache = create_small_infra_cache();
+        infra_cache.apply_delete(&ObjectRef::new(ObjectType::Route, "R3"));
+        let errors = generate_errors(&infra_cache);
+        assert_eq!(1, errors.len());
+        let infra_error = InfraError::new_uncovered_switch_group("switch", "RIGHT".into());
+        assert_eq!(infra_error, errors[0]);
+    }
 }
@@ -1,5 +1,5 @@
 use super::graph::Graph;
-use super::InfraError;
+use super::{fix, InfraError};
 use crate::infra_cache::InfraCache;
 use crate::objects::ObjectType;
 use diesel::result::Error as DieselError;
@@ -50,9 +50,9 @@ pub fn generate_errors(infra_cache: &InfraCache, graph: &Graph) -> Vec<InfraErro
     // topological error : no buffer stop on graph leaves
     for (track_id, track_cache) in infra_cache.track_sections().iter() {
         let track_cache = track_cache.unwrap_track_section();
-        if graph.get_neighbours(&track_cache.get_begin()).is_none()
-            || graph.get_neighbours(&track_cache.get_end()).is_none()
-        {
+        let begin_is_leaf = graph.get_neighbours(&track_cache.get_begin()).is_none();
+        let end_is_leaf = graph.get_neighbours(&track_cache.get_end()).is_none();
+        if begin_is_leaf || end_is_leaf {
             let track_refs = infra_cache.track_sections_refs.get(track_id);
 
             if track_refs.is_none()
@@ -61,7 +61,15 @@ pub fn generate_errors(infra_cache: &InfraCache, graph: &Graph) -> Vec<InfraErro
                     .iter()
                     .any(|x| x.obj_type == ObjectType::BufferStop)
             {
-                let infra_error = InfraError::new_no_buffer_stop(track_id.clone(), "buffer_stop");
+                let mut fix = vec![];
+                if begin_is_leaf {
+                    fix.push(fix::create_buffer_stop(track_id, 0.));
+                }
+                if end_is_leaf {
+                    fix.push(fix::create_buffer_stop(track_id, track_cache.length));
+                }
+                let infra_error =
+                    InfraError::new_no_buffer_stop(track_id.clone(), "buffer_stop").with_fix(fix);
                 errors.push(infra_error);
             }
         }
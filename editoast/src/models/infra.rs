@@ -1,14 +1,17 @@
 use crate::error::ApiError;
 use crate::schema::osrd_infra_infra;
 use crate::schema::osrd_infra_infra::dsl::*;
+use diesel::dsl::exists;
 use diesel::result::Error as DieselError;
-use diesel::sql_types::Text;
+use diesel::sql_types::{Text, Uuid as SqlUuid};
 use diesel::ExpressionMethods;
-use diesel::{delete, sql_query, update, PgConnection, QueryDsl, RunQueryDsl};
+use diesel::{delete, select, sql_query, update, PgConnection, QueryDsl, RunQueryDsl};
 use rocket::http::Status;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
+use uuid::Uuid;
 
 static RAILJSON_VERSION: &str = "2.3.1";
 
@@ -17,9 +20,15 @@ static RAILJSON_VERSION: &str = "2.3.1";
 pub struct Infra {
     pub id: i32,
     pub name: String,
+    /// The user who created the infra, an administrator of the infra
+    pub owner: Uuid,
     pub version: String,
     pub generated_version: Option<String>,
     pub locked: bool,
+    pub locked_by: Option<String>,
+    pub lock_reason: Option<String>,
+    /// Lock expiry as a unix timestamp, the lock never expires if not set
+    pub locked_until: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,11 +37,49 @@ pub struct CreateInfra {
     pub name: String,
 }
 
+/// Who locks an infra, why and for how long
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockInfra {
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Lock duration in seconds
+    #[serde(default)]
+    pub duration: Option<u64>,
+}
+
+/// Who unlocks an infra. `force` allows to release a lock owned by someone else.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnlockInfra {
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Longest lock duration accepted, in seconds (one year)
+pub const MAX_LOCK_DURATION: u64 = 365 * 24 * 3600;
+
+/// Current unix timestamp, in seconds
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
 #[derive(Debug, Error)]
 pub enum InfraError {
     /// Couldn't found the infra with the given id
     #[error("Infra '{0}', could not be found")]
     NotFound(i32),
+    #[error("Infra '{infra}' is locked by '{lock_owner}'")]
+    LockedByOther { infra: i32, lock_owner: String },
+    #[error("Lock duration '{0}' exceeds the maximum of {MAX_LOCK_DURATION} seconds")]
+    InvalidLockDuration(u64),
     #[error("An internal diesel error occurred: '{}'", .0.to_string())]
     DieselError(DieselError),
 }
@@ -41,6 +88,8 @@ impl ApiError for InfraError {
     fn get_status(&self) -> Status {
         match self {
             InfraError::NotFound(_) => Status::NotFound,
+            InfraError::LockedByOther { .. } => Status::Conflict,
+            InfraError::InvalidLockDuration(_) => Status::BadRequest,
             InfraError::DieselError(_) => Status::InternalServerError,
         }
     }
@@ -48,6 +97,8 @@ impl ApiError for InfraError {
     fn get_type(&self) -> &'static str {
         match self {
             InfraError::NotFound(_) => "editoast:infra:NotFound",
+            InfraError::LockedByOther { .. } => "editoast:infra:LockedByOther",
+            InfraError::InvalidLockDuration(_) => "editoast:infra:InvalidLockDuration",
             InfraError::DieselError(_) => "editoast:infra:DieselError",
         }
     }
@@ -59,6 +110,18 @@ impl ApiError for InfraError {
             })
             .as_object()
             .cloned(),
+            InfraError::LockedByOther { infra, lock_owner } => json!({
+                "infra_id": infra,
+                "locked_by": lock_owner,
+            })
+            .as_object()
+            .cloned(),
+            InfraError::InvalidLockDuration(duration) => json!({
+                "duration": duration,
+                "max_duration": MAX_LOCK_DURATION,
+            })
+            .as_object()
+            .cloned(),
             _ => None,
         }
     }
@@ -66,19 +129,30 @@ impl ApiError for InfraError {
 
 impl Infra {
     pub fn retrieve(conn: &PgConnection, infra_id: i32) -> Result<Infra, Box<dyn ApiError>> {
-        match osrd_infra_infra.find(infra_id).first(conn) {
-            Ok(infra) => Ok(infra),
+        match osrd_infra_infra.find(infra_id).first::<Infra>(conn) {
+            Ok(infra) => Ok(infra.release_expired_lock()),
             Err(DieselError::NotFound) => Err(Box::new(InfraError::NotFound(infra_id))),
             Err(e) => Err(Box::new(InfraError::DieselError(e))),
         }
     }
 
+    /// Whether the infra exists, deleted infras may still have an audit log
+    pub fn exists(conn: &PgConnection, infra_id: i32) -> Result<bool, Box<dyn ApiError>> {
+        select(exists(osrd_infra_infra.find(infra_id)))
+            .get_result(conn)
+            .map_err(|e| Box::new(InfraError::DieselError(e)) as Box<dyn ApiError>)
+    }
+
     pub fn retrieve_for_update(
         conn: &PgConnection,
         infra_id: i32,
     ) -> Result<Infra, Box<dyn ApiError>> {
-        match osrd_infra_infra.for_update().find(infra_id).first(conn) {
-            Ok(infra) => Ok(infra),
+        match osrd_infra_infra
+            .for_update()
+            .find(infra_id)
+            .first::<Infra>(conn)
+        {
+            Ok(infra) => Ok(infra.release_expired_lock()),
             Err(DieselError::NotFound) => Err(Box::new(InfraError::NotFound(infra_id))),
             Err(e) => Err(Box::new(InfraError::DieselError(e))),
         }
@@ -88,6 +162,9 @@ impl Infra {
         osrd_infra_infra
             .load::<Self>(conn)
             .expect("List infra query failed")
+            .into_iter()
+            .map(Self::release_expired_lock)
+            .collect()
     }
 
     pub fn list_for_update(conn: &PgConnection) -> Vec<Infra> {
@@ -95,6 +172,9 @@ impl Infra {
             .for_update()
             .load::<Self>(conn)
             .expect("List infra query failed")
+            .into_iter()
+            .map(Self::release_expired_lock)
+            .collect()
     }
 
     pub fn bump_version(&self, conn: &PgConnection) -> Result<Self, Box<dyn ApiError>> {
@@ -125,14 +205,19 @@ impl Infra {
         }
     }
 
-    pub fn create(infra_name: &String, conn: &PgConnection) -> Result<Infra, Box<dyn ApiError>> {
+    pub fn create(
+        infra_name: &String,
+        infra_owner: &Uuid,
+        conn: &PgConnection,
+    ) -> Result<Infra, Box<dyn ApiError>> {
         match sql_query(
             "INSERT INTO osrd_infra_infra (name, railjson_version, owner, version, generated_version, locked)
-             VALUES ($1, $2, '00000000-0000-0000-0000-000000000000', '0', '0', false)
+             VALUES ($1, $2, $3, '0', '0', false)
              RETURNING *",
         )
         .bind::<Text, _>(infra_name)
         .bind::<Text, _>(RAILJSON_VERSION)
+        .bind::<SqlUuid, _>(infra_owner)
         .get_result::<Infra>(conn)
         {
             Ok(infra) => Ok(infra),
@@ -148,10 +233,82 @@ impl Infra {
         }
     }
 
-    /// Lock or unlock the infra whether `lock` is true or false
-    pub fn set_locked(&self, lock: bool, conn: &PgConnection) -> Result<Self, Box<dyn ApiError>> {
+    /// Whether the infra is locked by a lock that isn't expired
+    pub fn is_locked(&self) -> bool {
+        self.locked && self.locked_until.is_none_or(|until| until > unix_now())
+    }
+
+    /// Present an expired lock as released, it is cleared in the database by the next lock change
+    fn release_expired_lock(self) -> Self {
+        if self.locked && !self.is_locked() {
+            Self {
+                locked: false,
+                locked_by: None,
+                lock_reason: None,
+                locked_until: None,
+                ..self
+            }
+        } else {
+            self
+        }
+    }
+
+    /// Return an error if the infra is locked by someone else than `lock_owner`.
+    /// Anyone may take over a lock without owner.
+    fn check_lock_owner(&self, lock_owner: &Option<String>) -> Result<(), Box<dyn ApiError>> {
+        match &self.locked_by {
+            Some(current_owner)
+                if self.is_locked() && Some(current_owner) != lock_owner.as_ref() =>
+            {
+                Err(Box::new(InfraError::LockedByOther {
+                    infra: self.id,
+                    lock_owner: current_owner.clone(),
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Lock the infra, or renew a lock owned by the same owner
+    pub fn lock(&self, lock: &LockInfra, conn: &PgConnection) -> Result<Self, Box<dyn ApiError>> {
+        self.check_lock_owner(&lock.owner)?;
+        let until = match lock.duration {
+            Some(duration) if duration > MAX_LOCK_DURATION => {
+                return Err(Box::new(InfraError::InvalidLockDuration(duration)))
+            }
+            Some(duration) => Some(unix_now().saturating_add(duration as i64)),
+            None => None,
+        };
+        self.set_lock(true, &lock.owner, &lock.reason, until, conn)
+    }
+
+    /// Unlock the infra, a lock owned by someone else is only released if `force` is set
+    pub fn unlock(
+        &self,
+        unlock: &UnlockInfra,
+        conn: &PgConnection,
+    ) -> Result<Self, Box<dyn ApiError>> {
+        if !unlock.force {
+            self.check_lock_owner(&unlock.owner)?;
+        }
+        self.set_lock(false, &None, &None, None, conn)
+    }
+
+    fn set_lock(
+        &self,
+        lock: bool,
+        lock_owner: &Option<String>,
+        reason: &Option<String>,
+        until: Option<i64>,
+        conn: &PgConnection,
+    ) -> Result<Self, Box<dyn ApiError>> {
         match update(osrd_infra_infra.filter(id.eq(self.id)))
-            .set(locked.eq(lock))
+            .set((
+                locked.eq(lock),
+                locked_by.eq(lock_owner),
+                lock_reason.eq(reason),
+                locked_until.eq(until),
+            ))
             .get_result::<Infra>(conn)
         {
             Ok(infra) => Ok(infra),
@@ -163,16 +320,17 @@ impl Infra {
 
 #[cfg(test)]
 pub mod tests {
-    use super::Infra;
+    use super::{unix_now, Infra, LockInfra, UnlockInfra};
     use crate::client::PostgresConfig;
     use diesel::result::Error;
     use diesel::{Connection, PgConnection};
     use rocket::http::Status;
+    use uuid::Uuid;
 
     pub fn test_transaction(fn_test: fn(&PgConnection, Infra)) {
         let conn = PgConnection::establish(&PostgresConfig::default().url()).unwrap();
         conn.test_transaction::<_, Error, _>(|| {
-            let infra = Infra::create(&"test".to_string(), &conn).unwrap();
+            let infra = Infra::create(&"test".to_string(), &Uuid::nil(), &conn).unwrap();
 
             fn_test(&conn, infra);
             Ok(())
@@ -194,4 +352,63 @@ pub mod tests {
             assert_eq!(err.get_status(), Status::NotFound);
         });
     }
+
+    #[test]
+    fn lock_expiry() {
+        test_transaction(|conn, infra| {
+            let lock = LockInfra {
+                owner: Some("alice".into()),
+                reason: Some("study".into()),
+                duration: Some(3600),
+            };
+            let infra = infra.lock(&lock, conn).unwrap();
+            assert!(infra.is_locked());
+            assert!(infra.locked_until.unwrap() > unix_now());
+
+            let expired = Infra {
+                locked_until: Some(unix_now() - 1),
+                ..infra
+            };
+            assert!(!expired.is_locked());
+            assert!(!expired.release_expired_lock().locked);
+        });
+    }
+
+    #[test]
+    fn lock_duration_too_long() {
+        test_transaction(|conn, infra| {
+            let lock = LockInfra {
+                duration: Some(u64::MAX),
+                ..Default::default()
+            };
+            let err = infra.lock(&lock, conn).unwrap_err();
+            assert_eq!(err.get_status(), Status::BadRequest);
+        });
+    }
+
+    #[test]
+    fn unlock_by_other_owner() {
+        test_transaction(|conn, infra| {
+            let lock = LockInfra {
+                owner: Some("alice".into()),
+                ..Default::default()
+            };
+            let infra = infra.lock(&lock, conn).unwrap();
+
+            let unlock = UnlockInfra {
+                owner: Some("bob".into()),
+                force: false,
+            };
+            let err = infra.unlock(&unlock, conn).unwrap_err();
+            assert_eq!(err.get_status(), Status::Conflict);
+            let err = infra.lock(&LockInfra::default(), conn).unwrap_err();
+            assert_eq!(err.get_status(), Status::Conflict);
+
+            let unlock = UnlockInfra {
+                force: true,
+                ..unlock
+            };
+            assert!(!infra.unlock(&unlock, conn).unwrap().locked);
+        });
+    }
 }
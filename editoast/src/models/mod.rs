@@ -1,8 +1,20 @@
+pub mod audit;
 pub mod errors;
 pub mod infra;
 pub mod infra_errors;
+pub mod object_search;
+pub mod operational_point_search;
+pub mod pathfinding;
+pub mod permissions;
+pub mod route_conflicts;
+pub mod routes_generation;
+pub mod schematic_generation;
+pub mod track_geometry;
+pub mod track_projection;
+pub mod tvd_sections;
+pub mod webhooks;
 
-pub use infra::{CreateInfra, Infra, InfraError};
+pub use infra::{CreateInfra, Infra, LockInfra, UnlockInfra};
 
 use rocket_contrib::databases::diesel;
 
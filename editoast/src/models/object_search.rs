@@ -0,0 +1,241 @@
+use diesel::result::Error as DieselError;
+use diesel::sql_types::{BigInt, Double, Integer, Nullable, Text};
+use diesel::{sql_query, PgConnection, QueryableByName, RunQueryDsl};
+use rocket::http::Status;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::error::ApiError;
+use crate::objects::{ObjectRef, ObjectType};
+
+/// Object types searched when no type is requested
+const SEARCHED_TYPES: [ObjectType; 11] = [
+    ObjectType::TrackSection,
+    ObjectType::Signal,
+    ObjectType::SpeedSection,
+    ObjectType::Detector,
+    ObjectType::TrackSectionLink,
+    ObjectType::Switch,
+    ObjectType::SwitchType,
+    ObjectType::BufferStop,
+    ObjectType::Route,
+    ObjectType::OperationalPoint,
+    ObjectType::Catenary,
+];
+
+#[derive(Debug, Error)]
+pub enum ObjectSearchError {
+    #[error("Object type '{0}' is not valid")]
+    InvalidObjectType(String),
+}
+
+impl ApiError for ObjectSearchError {
+    fn get_status(&self) -> Status {
+        Status::BadRequest
+    }
+
+    fn get_type(&self) -> &'static str {
+        match self {
+            ObjectSearchError::InvalidObjectType(_) => "editoast:objects:InvalidObjectType",
+        }
+    }
+
+    fn extra(&self) -> Option<Map<String, Value>> {
+        match self {
+            ObjectSearchError::InvalidObjectType(obj_type) => {
+                json!({ "obj_type": obj_type }).as_object().cloned()
+            }
+        }
+    }
+}
+
+/// Parse an object type given as a query parameter
+pub fn parse_object_type(obj_type: &str) -> Result<ObjectType, ObjectSearchError> {
+    serde_json::from_value(Value::String(obj_type.into()))
+        .map_err(|_| ObjectSearchError::InvalidObjectType(obj_type.into()))
+}
+
+/// An object matching a search, with its bounding box (`[xmin, ymin, xmax, ymax]` in WGS84)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectSearchResult {
+    pub obj_ref: ObjectRef,
+    pub bbox: Option<[f64; 4]>,
+}
+
+#[derive(QueryableByName, Debug, Clone)]
+struct ObjectSearchRow {
+    #[sql_type = "Text"]
+    obj_type: String,
+    #[sql_type = "Text"]
+    obj_id: String,
+    #[sql_type = "Nullable<Double>"]
+    xmin: Option<f64>,
+    #[sql_type = "Nullable<Double>"]
+    ymin: Option<f64>,
+    #[sql_type = "Nullable<Double>"]
+    xmax: Option<f64>,
+    #[sql_type = "Nullable<Double>"]
+    ymax: Option<f64>,
+}
+
+impl From<ObjectSearchRow> for ObjectSearchResult {
+    fn from(row: ObjectSearchRow) -> Self {
+        let obj_type = parse_object_type(&row.obj_type).unwrap();
+        let bbox = match (row.xmin, row.ymin, row.xmax, row.ymax) {
+            (Some(xmin), Some(ymin), Some(xmax), Some(ymax)) => Some([xmin, ymin, xmax, ymax]),
+            _ => None,
+        };
+        Self {
+            obj_ref: ObjectRef::new(obj_type, row.obj_id),
+            bbox,
+        }
+    }
+}
+
+/// Attributes of the railjson objects matched by the search, besides their id
+fn searched_attributes(obj_type: ObjectType) -> &'static [&'static str] {
+    match obj_type {
+        ObjectType::TrackSection => &["line_name", "track_name", "line_code"],
+        ObjectType::Signal | ObjectType::Switch => &["label"],
+        _ => &[],
+    }
+}
+
+/// Escape the wildcards of a `LIKE` pattern
+fn escape_like_pattern(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Build the search subquery of an object type.
+/// `$1` is the infra id, `$2` the pattern matching the attributes and `$3` the exact id pattern.
+fn build_type_query(obj_type: ObjectType) -> String {
+    let type_name = serde_json::to_value(obj_type).unwrap();
+    let type_name = type_name.as_str().unwrap();
+    let mut conditions = vec!["obj.obj_id ILIKE $2".to_string()];
+    conditions.extend(
+        searched_attributes(obj_type)
+            .iter()
+            .map(|attribute| format!("(obj.data->>'{}') ILIKE $2", attribute)),
+    );
+    let (bbox, join) = match obj_type.get_layer_table() {
+        Some(layer_table) => (
+            "ST_Extent(ST_Transform(layer.geographic, 4326))".to_string(),
+            format!(
+                "LEFT JOIN {} AS layer ON layer.infra_id = obj.infra_id AND layer.obj_id = obj.obj_id",
+                layer_table
+            ),
+        ),
+        None => ("NULL::box2d".to_string(), String::new()),
+    };
+    format!(
+        "SELECT '{type_name}' AS obj_type, obj.obj_id, (obj.obj_id NOT ILIKE $3) AS inexact, {bbox} AS bbox
+        FROM {table} AS obj {join}
+        WHERE obj.infra_id = $1 AND ({conditions})
+        GROUP BY obj.obj_id",
+        type_name = type_name,
+        bbox = bbox,
+        table = obj_type.get_table(),
+        join = join,
+        conditions = conditions.join(" OR "),
+    )
+}
+
+/// Search the objects of an infra by id and by their main attributes (labels, line and track names).
+/// Objects whose id exactly matches the query come first.
+pub fn search_objects(
+    conn: &PgConnection,
+    infra_id: i32,
+    query: &str,
+    obj_type: Option<ObjectType>,
+    limit: i64,
+) -> Result<Vec<ObjectSearchResult>, DieselError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let subqueries: Vec<_> = match obj_type {
+        Some(obj_type) => vec![build_type_query(obj_type)],
+        None => SEARCHED_TYPES.into_iter().map(build_type_query).collect(),
+    };
+    let sql = format!(
+        "SELECT obj_type, obj_id, ST_XMin(bbox) AS xmin, ST_YMin(bbox) AS ymin, ST_XMax(bbox) AS xmax, ST_YMax(bbox) AS ymax
+        FROM ({}) AS results
+        ORDER BY inexact, obj_type, obj_id
+        LIMIT $4",
+        subqueries.join(" UNION ALL ")
+    );
+
+    let exact_pattern = escape_like_pattern(query);
+    let pattern = format!("%{}%", exact_pattern);
+    Ok(sql_query(sql)
+        .bind::<Integer, _>(infra_id)
+        .bind::<Text, _>(pattern)
+        .bind::<Text, _>(exact_pattern)
+        .bind::<BigInt, _>(limit)
+        .load::<ObjectSearchRow>(conn)?
+        .into_iter()
+        .map(ObjectSearchResult::from)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_like_pattern, parse_object_type, search_objects};
+    use crate::models::infra::tests::test_transaction;
+    use crate::objects::operation::create::tests::{create_signal, create_track};
+    use crate::objects::{ObjectType, Signal, TrackSection};
+
+    #[test]
+    fn like_pattern_escaping() {
+        assert_eq!(escape_like_pattern("a_b%c\\d"), "a\\_b\\%c\\\\d");
+    }
+
+    #[test]
+    fn object_type_parsing() {
+        assert_eq!(parse_object_type("Signal").unwrap(), ObjectType::Signal);
+        assert!(parse_object_type("signal").is_err());
+    }
+
+    #[test]
+    fn search_by_attributes() {
+        test_transaction(|conn, infra| {
+            create_track(
+                conn,
+                infra.id,
+                TrackSection {
+                    id: "track_search".into(),
+                    line_name: "Ligne de Bretagne".into(),
+                    ..Default::default()
+                },
+            );
+            create_signal(
+                conn,
+                infra.id,
+                Signal {
+                    id: "signal_search".into(),
+                    label: Some("CARRE 12".into()),
+                    ..Default::default()
+                },
+            );
+
+            let results = search_objects(conn, infra.id, "bretagne", None, 10).unwrap();
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].obj_ref.obj_id, "track_search");
+            assert_eq!(results[0].obj_ref.obj_type, ObjectType::TrackSection);
+
+            let results = search_objects(conn, infra.id, "carre", None, 10).unwrap();
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].obj_ref.obj_id, "signal_search");
+
+            let results =
+                search_objects(conn, infra.id, "search", Some(ObjectType::Signal), 10).unwrap();
+            assert_eq!(results.len(), 1);
+            assert!(results[0].bbox.is_none());
+        });
+    }
+}
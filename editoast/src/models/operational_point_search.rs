@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+
+use crate::infra_cache::InfraCache;
+use crate::objects::{OperationalPointCache, OperationalPointPart};
+
+/// An operational point matching a search query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationalPointMatch {
+    pub id: String,
+    pub uic: i64,
+    pub trigram: String,
+    pub name: String,
+    pub ch: String,
+    pub parts: Vec<OperationalPointPart>,
+}
+
+impl From<&OperationalPointCache> for OperationalPointMatch {
+    fn from(op: &OperationalPointCache) -> Self {
+        Self {
+            id: op.obj_id.clone(),
+            uic: op.uic,
+            trigram: op.trigram.clone(),
+            name: op.name.clone(),
+            ch: op.ch.clone(),
+            parts: op.parts.clone(),
+        }
+    }
+}
+
+/// Lowercase a string and strip the most common diacritics
+fn normalize(value: &str) -> String {
+    value
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'â' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'î' | 'ï' => 'i',
+            'ô' | 'ö' => 'o',
+            'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            '-' | '\'' => ' ',
+            c => c,
+        })
+        .collect()
+}
+
+/// Number of edits needed to go from a string to another
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Score how well a field matches the (normalized) query, the lower the better.
+/// Exact matches come first, then prefix matches, word prefix matches and finally typos.
+fn score_field(query: &str, field: &str) -> Option<usize> {
+    let field = normalize(field);
+    if field.is_empty() {
+        return None;
+    }
+    if field == query {
+        return Some(0);
+    }
+    if field.starts_with(query) {
+        return Some(1);
+    }
+    if field.split_whitespace().any(|word| word.starts_with(query)) {
+        return Some(2);
+    }
+
+    // Allow a few typos, compared to the beginning of the field to keep prefix matching
+    let max_typos = match query.chars().count() {
+        0..=2 => return None,
+        3..=7 => 1,
+        _ => 2,
+    };
+    let query: Vec<char> = query.chars().collect();
+    let field: Vec<char> = field.chars().collect();
+    let field_prefix = &field[..field.len().min(query.len())];
+    let distance = levenshtein(&query, field_prefix).min(levenshtein(&query, &field));
+    (distance <= max_typos).then_some(2 + distance)
+}
+
+/// Score an operational point against the query, matching its uic, trigram, name and ch
+fn score_operational_point(query: &str, op: &OperationalPointCache) -> Option<usize> {
+    let uic_score = if !query.is_empty() && query.chars().all(|c| c.is_ascii_digit()) {
+        let uic = op.uic.to_string();
+        if uic == query {
+            Some(0)
+        } else if uic.starts_with(query) {
+            Some(1)
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+    [&op.trigram, &op.name, &op.ch]
+        .into_iter()
+        .filter_map(|field| score_field(query, field))
+        .chain(uic_score)
+        .min()
+}
+
+/// Search the operational points of an infra, best matches first
+pub fn search_operational_points(
+    infra_cache: &InfraCache,
+    query: &str,
+    limit: usize,
+) -> Vec<OperationalPointMatch> {
+    let query = normalize(query);
+    if query.is_empty() {
+        return vec![];
+    }
+
+    let mut matches: Vec<_> = infra_cache
+        .operational_points()
+        .values()
+        .map(|op| op.unwrap_operational_point())
+        .filter_map(|op| score_operational_point(&query, op).map(|score| (score, op)))
+        .collect();
+    matches.sort_by(|(score_a, a), (score_b, b)| {
+        score_a
+            .cmp(score_b)
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.obj_id.cmp(&b.obj_id))
+    });
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, op)| op.into())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::search_operational_points;
+    use crate::infra_cache::tests::{create_operational_point_cache, create_small_infra_cache};
+    use crate::infra_cache::InfraCache;
+
+    fn create_infra_cache() -> InfraCache {
+        let mut infra_cache = create_small_infra_cache();
+        for (id, uic, trigram, name, ch) in [
+            ("OP_PNO", 87686006, "PNO", "Paris Nord", "BV"),
+            ("OP_PLY", 87686030, "PLY", "Paris Gare de Lyon", "BV"),
+            ("OP_LYD", 87723197, "LYD", "Lyon Part-Dieu", "BV"),
+            (
+                "OP_SME",
+                87313874,
+                "SME",
+                "Saint-Étienne Châteaucreux",
+                "00",
+            ),
+        ] {
+            let mut op = create_operational_point_cache(id, "A", 100.);
+            op.uic = uic;
+            op.trigram = trigram.into();
+            op.name = name.into();
+            op.ch = ch.into();
+            infra_cache.add(op);
+        }
+        infra_cache
+    }
+
+    fn search(infra_cache: &InfraCache, query: &str) -> Vec<String> {
+        search_operational_points(infra_cache, query, 10)
+            .into_iter()
+            .map(|op| op.id)
+            .collect()
+    }
+
+    #[test]
+    fn search_by_trigram() {
+        let infra_cache = create_infra_cache();
+        assert_eq!(search(&infra_cache, "lyd"), vec!["OP_LYD"]);
+        let results = search_operational_points(&infra_cache, "PNO", 10);
+        assert_eq!(results[0].parts[0].track.obj_id, "A");
+        assert_eq!(results[0].parts[0].position, 100.);
+    }
+
+    #[test]
+    fn search_by_uic() {
+        let infra_cache = create_infra_cache();
+        assert_eq!(search(&infra_cache, "87723197"), vec!["OP_LYD"]);
+        assert_eq!(search(&infra_cache, "876860"), vec!["OP_PLY", "OP_PNO"]);
+    }
+
+    #[test]
+    fn search_by_name_prefix() {
+        let infra_cache = create_infra_cache();
+        assert_eq!(search(&infra_cache, "paris"), vec!["OP_PLY", "OP_PNO"]);
+        // Word prefixes come after full prefixes
+        assert_eq!(search(&infra_cache, "lyon"), vec!["OP_LYD", "OP_PLY"]);
+        assert_eq!(search(&infra_cache, "saint etienne"), vec!["OP_SME"]);
+    }
+
+    #[test]
+    fn search_with_typos() {
+        let infra_cache = create_infra_cache();
+        assert_eq!(search(&infra_cache, "pari nord"), vec!["OP_PNO"]);
+        assert_eq!(search(&infra_cache, "chateaucreu"), vec!["OP_SME"]);
+        assert!(search(&infra_cache, "marseille").is_empty());
+    }
+
+    #[test]
+    fn search_limit() {
+        let infra_cache = create_infra_cache();
+        assert_eq!(search_operational_points(&infra_cache, "bv", 2).len(), 2);
+        assert!(search(&infra_cache, " ").is_empty());
+    }
+}
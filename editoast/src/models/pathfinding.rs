@@ -0,0 +1,452 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use rocket::http::Status;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use super::errors::graph::Graph;
+use crate::error::ApiError;
+use crate::infra_cache::InfraCache;
+use crate::objects::{
+    Direction, DirectionalTrackRange, Endpoint, ObjectRef, ObjectType, TrackEndpoint,
+};
+
+/// A location on the infra the path must go through
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathWaypoint {
+    pub track: String,
+    pub offset: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Path {
+    pub track_ranges: Vec<DirectionalTrackRange>,
+    /// Routes traversed by the path, in the order they are encountered
+    pub routes: Vec<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum PathfindingError {
+    #[error("At least two waypoints are required, {0} given")]
+    NotEnoughWaypoints(usize),
+    #[error("Waypoint {index} references an unknown track '{track}'")]
+    UnknownTrack { index: usize, track: String },
+    #[error("Waypoint {index} offset {offset} is not in the range [0, {length}]")]
+    OffsetOutOfRange {
+        index: usize,
+        offset: f64,
+        length: f64,
+    },
+    #[error("No path found between waypoints {0} and {}", .0 + 1)]
+    NoPath(usize),
+}
+
+impl ApiError for PathfindingError {
+    fn get_status(&self) -> Status {
+        match self {
+            PathfindingError::NoPath(_) => Status::NotFound,
+            _ => Status::BadRequest,
+        }
+    }
+
+    fn get_type(&self) -> &'static str {
+        match self {
+            PathfindingError::NotEnoughWaypoints(_) => "editoast:pathfinding:NotEnoughWaypoints",
+            PathfindingError::UnknownTrack { .. } => "editoast:pathfinding:UnknownTrack",
+            PathfindingError::OffsetOutOfRange { .. } => "editoast:pathfinding:OffsetOutOfRange",
+            PathfindingError::NoPath(_) => "editoast:pathfinding:NoPath",
+        }
+    }
+
+    fn extra(&self) -> Option<Map<String, Value>> {
+        match self {
+            PathfindingError::UnknownTrack { index, .. }
+            | PathfindingError::OffsetOutOfRange { index, .. }
+            | PathfindingError::NoPath(index) => json!({
+                "waypoint": index,
+            })
+            .as_object()
+            .cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// An element of the Dijkstra queue: a track endpoint reached at a given cost
+#[derive(Debug, PartialEq)]
+struct QueueItem {
+    cost: f64,
+    exit: TrackEndpoint,
+}
+
+impl Eq for QueueItem {}
+
+impl Ord for QueueItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed to make the binary heap a min heap
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for QueueItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Shortest way found to leave a track through one of its endpoints
+struct Visit {
+    cost: f64,
+    /// Endpoint through which the previous track was left, `None` for the start track
+    previous: Option<TrackEndpoint>,
+}
+
+/// Compute the shortest path going through all the given waypoints.
+/// Tracks are traversed following navigable links and switch groups.
+pub fn compute_path(
+    infra_cache: &InfraCache,
+    graph: &Graph,
+    waypoints: &[PathWaypoint],
+) -> Result<Path, PathfindingError> {
+    if waypoints.len() < 2 {
+        return Err(PathfindingError::NotEnoughWaypoints(waypoints.len()));
+    }
+    for (index, waypoint) in waypoints.iter().enumerate() {
+        let length = track_length(infra_cache, &waypoint.track).ok_or_else(|| {
+            PathfindingError::UnknownTrack {
+                index,
+                track: waypoint.track.clone(),
+            }
+        })?;
+        if !(0.0..=length).contains(&waypoint.offset) {
+            return Err(PathfindingError::OffsetOutOfRange {
+                index,
+                offset: waypoint.offset,
+                length,
+            });
+        }
+    }
+
+    let mut track_ranges: Vec<DirectionalTrackRange> = vec![];
+    for (index, (from, to)) in waypoints.iter().zip(waypoints.iter().skip(1)).enumerate() {
+        let leg =
+            compute_leg(infra_cache, graph, from, to).ok_or(PathfindingError::NoPath(index))?;
+        for range in leg {
+            match track_ranges.last_mut() {
+                // Merge ranges split by an intermediate waypoint
+                Some(last) if last.track == range.track && last.direction == range.direction => {
+                    last.begin = last.begin.min(range.begin);
+                    last.end = last.end.max(range.end);
+                }
+                _ => track_ranges.push(range),
+            }
+        }
+    }
+
+    let routes = find_traversed_routes(infra_cache, &track_ranges);
+    Ok(Path {
+        track_ranges,
+        routes,
+    })
+}
+
+fn track_length(infra_cache: &InfraCache, track: &str) -> Option<f64> {
+    infra_cache
+        .track_sections()
+        .get(track)
+        .map(|track| track.unwrap_track_section().length)
+}
+
+fn make_range(track: &str, from: f64, to: f64, direction: Direction) -> DirectionalTrackRange {
+    DirectionalTrackRange {
+        track: ObjectRef::new(ObjectType::TrackSection, track),
+        begin: from.min(to),
+        end: from.max(to),
+        direction,
+    }
+}
+
+/// Direction a track is traversed in given the endpoint it's left through
+fn exit_direction(exit: &TrackEndpoint) -> Direction {
+    match exit.endpoint {
+        Endpoint::Begin => Direction::StopToStart,
+        Endpoint::End => Direction::StartToStop,
+    }
+}
+
+/// Run a Dijkstra between two waypoints.
+/// Nodes are the track endpoints through which tracks are left.
+fn compute_leg(
+    infra_cache: &InfraCache,
+    graph: &Graph,
+    from: &PathWaypoint,
+    to: &PathWaypoint,
+) -> Option<Vec<DirectionalTrackRange>> {
+    let from_cache = infra_cache
+        .track_sections()
+        .get(&from.track)?
+        .unwrap_track_section();
+    let to_length = track_length(infra_cache, &to.track)?;
+    let target_cost = |entry: &TrackEndpoint| match entry.endpoint {
+        Endpoint::Begin => to.offset,
+        Endpoint::End => to_length - to.offset,
+    };
+
+    // Best way to reach the target: total cost, endpoint through which the last track was left
+    // and endpoint through which the target track is entered
+    let mut best: Option<(f64, Option<(TrackEndpoint, TrackEndpoint)>)> = None;
+    if from.track == to.track {
+        best = Some(((to.offset - from.offset).abs(), None));
+    }
+
+    let mut visits: HashMap<TrackEndpoint, Visit> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    for (exit, cost) in [
+        (from_cache.get_begin(), from.offset),
+        (from_cache.get_end(), from_cache.length - from.offset),
+    ] {
+        visits.insert(
+            exit.clone(),
+            Visit {
+                cost,
+                previous: None,
+            },
+        );
+        queue.push(QueueItem { cost, exit });
+    }
+
+    while let Some(QueueItem { cost, exit }) = queue.pop() {
+        if visits.get(&exit).is_some_and(|visit| visit.cost < cost) {
+            continue;
+        }
+        if best
+            .as_ref()
+            .is_some_and(|(best_cost, _)| *best_cost <= cost)
+        {
+            break;
+        }
+        for entry in graph.get_neighbours(&exit).into_iter().flatten() {
+            let length = match track_length(infra_cache, &entry.track.obj_id) {
+                Some(length) => length,
+                None => continue,
+            };
+
+            // Reaching the target track
+            if entry.track.obj_id == to.track {
+                let total_cost = cost + target_cost(entry);
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_cost, _)| total_cost < *best_cost)
+                {
+                    best = Some((total_cost, Some((exit.clone(), (*entry).clone()))));
+                }
+            }
+
+            // Traversing the whole track
+            let next_exit = TrackEndpoint {
+                endpoint: match entry.endpoint {
+                    Endpoint::Begin => Endpoint::End,
+                    Endpoint::End => Endpoint::Begin,
+                },
+                track: entry.track.clone(),
+            };
+            let next_cost = cost + length;
+            if visits
+                .get(&next_exit)
+                .is_none_or(|visit| next_cost < visit.cost)
+            {
+                visits.insert(
+                    next_exit.clone(),
+                    Visit {
+                        cost: next_cost,
+                        previous: Some(exit.clone()),
+                    },
+                );
+                queue.push(QueueItem {
+                    cost: next_cost,
+                    exit: next_exit,
+                });
+            }
+        }
+    }
+
+    // Build the path backward
+    let (mut exit, entry) = match best? {
+        (_, Some(endpoints)) => endpoints,
+        (_, None) => {
+            let direction = if from.offset <= to.offset {
+                Direction::StartToStop
+            } else {
+                Direction::StopToStart
+            };
+            return Some(vec![make_range(
+                &from.track,
+                from.offset,
+                to.offset,
+                direction,
+            )]);
+        }
+    };
+
+    let mut path = vec![match entry.endpoint {
+        Endpoint::Begin => make_range(&to.track, 0., to.offset, Direction::StartToStop),
+        Endpoint::End => make_range(&to.track, to.offset, to_length, Direction::StopToStart),
+    }];
+    loop {
+        let visit = visits.get(&exit)?;
+        let track = &exit.track.obj_id;
+        let length = track_length(infra_cache, track)?;
+        let direction = exit_direction(&exit);
+        match &visit.previous {
+            Some(previous) => {
+                path.push(make_range(track, 0., length, direction));
+                exit = previous.clone();
+            }
+            None => {
+                let bound = match direction {
+                    Direction::StartToStop => length,
+                    Direction::StopToStart => 0.,
+                };
+                path.push(make_range(track, from.offset, bound, direction));
+                break;
+            }
+        }
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Return the routes of which every range is on the path, in the same direction
+fn find_traversed_routes(infra_cache: &InfraCache, path: &[DirectionalTrackRange]) -> Vec<String> {
+    let mut routes: Vec<(usize, &String)> = infra_cache
+        .routes()
+        .iter()
+        .filter_map(|(route_id, route)| {
+            let route = route.unwrap_route();
+            let mut first_index = None;
+            for range in route.path.iter() {
+                let index = path.iter().position(|path_range| {
+                    path_range.track == range.track
+                        && path_range.direction == range.direction
+                        && range.begin <= path_range.end
+                        && path_range.begin <= range.end
+                })?;
+                first_index = first_index.or(Some(index));
+            }
+            first_index.map(|index| (index, route_id))
+        })
+        .collect();
+    routes.sort();
+    routes
+        .into_iter()
+        .map(|(_, route_id)| route_id.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compute_path, PathWaypoint, PathfindingError};
+    use crate::infra_cache::tests::create_small_infra_cache;
+    use crate::models::errors::graph::Graph;
+    use crate::objects::Direction;
+
+    fn waypoint(track: &str, offset: f64) -> PathWaypoint {
+        PathWaypoint {
+            track: track.into(),
+            offset,
+        }
+    }
+
+    fn simplify(path: &super::Path) -> Vec<(&str, f64, f64, Direction)> {
+        path.track_ranges
+            .iter()
+            .map(|r| (r.track.obj_id.as_str(), r.begin, r.end, r.direction.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn path_through_switch() {
+        let infra_cache = create_small_infra_cache();
+        let graph = Graph::load(&infra_cache);
+        let path = compute_path(
+            &infra_cache,
+            &graph,
+            &[waypoint("A", 100.), waypoint("C", 100.)],
+        )
+        .unwrap();
+        assert_eq!(
+            simplify(&path),
+            vec![
+                ("A", 100., 500., Direction::StartToStop),
+                ("B", 0., 500., Direction::StartToStop),
+                ("C", 0., 100., Direction::StartToStop),
+            ]
+        );
+        assert_eq!(path.routes, vec!["R1", "R2"]);
+    }
+
+    #[test]
+    fn path_backward_with_intermediate_waypoint() {
+        let infra_cache = create_small_infra_cache();
+        let graph = Graph::load(&infra_cache);
+        let path = compute_path(
+            &infra_cache,
+            &graph,
+            &[
+                waypoint("D", 100.),
+                waypoint("B", 300.),
+                waypoint("B", 100.),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            simplify(&path),
+            vec![
+                ("D", 0., 100., Direction::StopToStart),
+                ("B", 100., 500., Direction::StopToStart),
+            ]
+        );
+        assert!(path.routes.is_empty());
+    }
+
+    #[test]
+    fn no_path_between_switch_legs() {
+        let infra_cache = create_small_infra_cache();
+        let graph = Graph::load(&infra_cache);
+        let path = compute_path(
+            &infra_cache,
+            &graph,
+            &[waypoint("C", 100.), waypoint("D", 100.)],
+        );
+        assert!(matches!(path, Err(PathfindingError::NoPath(0))));
+    }
+
+    #[test]
+    fn invalid_waypoints() {
+        let infra_cache = create_small_infra_cache();
+        let graph = Graph::load(&infra_cache);
+        let path = compute_path(&infra_cache, &graph, &[waypoint("A", 100.)]);
+        assert!(matches!(path, Err(PathfindingError::NotEnoughWaypoints(1))));
+        let path = compute_path(
+            &infra_cache,
+            &graph,
+            &[waypoint("A", 100.), waypoint("E", 100.)],
+        );
+        assert!(matches!(
+            path,
+            Err(PathfindingError::UnknownTrack { index: 1, .. })
+        ));
+        let path = compute_path(
+            &infra_cache,
+            &graph,
+            &[waypoint("A", 100.), waypoint("B", 600.)],
+        );
+        assert!(matches!(
+            path,
+            Err(PathfindingError::OffsetOutOfRange { index: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt;
+use std::str::FromStr;
+
+use diesel::result::Error as DieselError;
+use diesel::{delete, insert_into, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
+use rocket::http::Status;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::schema::osrd_infra_infrapermission;
+use crate::schema::osrd_infra_infrapermission::dsl;
+
+/// Role of a user on an infra, each role grants the rights of the previous ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Read the infra and its generated data
+    Read,
+    /// Edit, lock and refresh the infra
+    Edit,
+    /// Delete the infra, force unlock it and manage its permissions and webhooks
+    Admin,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let role = match self {
+            Role::Read => "read",
+            Role::Edit => "edit",
+            Role::Admin => "admin",
+        };
+        write!(f, "{}", role)
+    }
+}
+
+impl FromStr for Role {
+    type Err = PermissionError;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "read" => Ok(Role::Read),
+            "edit" => Ok(Role::Edit),
+            "admin" => Ok(Role::Admin),
+            _ => Err(PermissionError::InvalidRole(role.into())),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PermissionError {
+    #[error("User '{user}' has no permission on infra '{infra}'")]
+    NotFound { infra: i32, user: Uuid },
+    #[error("Invalid role '{0}'")]
+    InvalidRole(String),
+    #[error(transparent)]
+    Database(#[from] DieselError),
+}
+
+impl ApiError for PermissionError {
+    fn get_status(&self) -> Status {
+        match self {
+            PermissionError::NotFound { .. } => Status::NotFound,
+            PermissionError::InvalidRole(_) => Status::InternalServerError,
+            PermissionError::Database(_) => Status::InternalServerError,
+        }
+    }
+
+    fn get_type(&self) -> &'static str {
+        match self {
+            PermissionError::NotFound { .. } => "editoast:permissions:NotFound",
+            PermissionError::InvalidRole(_) => "editoast:permissions:InvalidRole",
+            PermissionError::Database(_) => "editoast:permissions:Database",
+        }
+    }
+
+    fn extra(&self) -> Option<Map<String, Value>> {
+        match self {
+            PermissionError::NotFound { infra, user } => json!({ "infra": infra, "user": user }),
+            PermissionError::InvalidRole(role) => json!({ "role": role }),
+            PermissionError::Database(_) => return None,
+        }
+        .as_object()
+        .cloned()
+    }
+}
+
+/// Role granted to a user on an infra
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InfraPermission {
+    pub user: Uuid,
+    pub role: Role,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetPermission {
+    pub role: Role,
+}
+
+#[derive(Insertable)]
+#[table_name = "osrd_infra_infrapermission"]
+struct NewPermission<'a> {
+    infra_id: i32,
+    user: &'a Uuid,
+    role: &'a str,
+}
+
+impl InfraPermission {
+    /// List the permissions granted on an infra
+    pub fn list(conn: &PgConnection, infra_id: i32) -> Result<Vec<Self>, PermissionError> {
+        dsl::osrd_infra_infrapermission
+            .filter(dsl::infra_id.eq(infra_id))
+            .order(dsl::id)
+            .select((dsl::user, dsl::role))
+            .load::<(Uuid, String)>(conn)?
+            .into_iter()
+            .map(|(user, role)| {
+                Ok(Self {
+                    user,
+                    role: role.parse()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Role of a user on an infra, if any was granted
+    pub fn get_role(
+        conn: &PgConnection,
+        infra_id: i32,
+        user: &Uuid,
+    ) -> Result<Option<Role>, PermissionError> {
+        let role = dsl::osrd_infra_infrapermission
+            .filter(dsl::infra_id.eq(infra_id))
+            .filter(dsl::user.eq(user))
+            .select(dsl::role)
+            .first::<String>(conn);
+        match role {
+            Ok(role) => Ok(Some(role.parse()?)),
+            Err(DieselError::NotFound) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Roles of a user on all the infras a permission was granted on
+    pub fn user_roles(
+        conn: &PgConnection,
+        user: &Uuid,
+    ) -> Result<Vec<(i32, Role)>, PermissionError> {
+        dsl::osrd_infra_infrapermission
+            .filter(dsl::user.eq(user))
+            .select((dsl::infra_id, dsl::role))
+            .load::<(i32, String)>(conn)?
+            .into_iter()
+            .map(|(infra_id, role)| Ok((infra_id, role.parse()?)))
+            .collect()
+    }
+
+    /// Grant a role to a user, replacing the previous one
+    pub fn set(
+        conn: &PgConnection,
+        infra_id: i32,
+        user: &Uuid,
+        role: Role,
+    ) -> Result<Self, PermissionError> {
+        let role_name = role.to_string();
+        insert_into(dsl::osrd_infra_infrapermission)
+            .values(NewPermission {
+                infra_id,
+                user,
+                role: &role_name,
+            })
+            .on_conflict((dsl::infra_id, dsl::user))
+            .do_update()
+            .set(dsl::role.eq(&role_name))
+            .execute(conn)?;
+        Ok(Self { user: *user, role })
+    }
+
+    /// Revoke the role of a user on an infra
+    pub fn delete(conn: &PgConnection, infra_id: i32, user: &Uuid) -> Result<(), PermissionError> {
+        let deleted = delete(
+            dsl::osrd_infra_infrapermission
+                .filter(dsl::infra_id.eq(infra_id))
+                .filter(dsl::user.eq(user)),
+        )
+        .execute(conn)?;
+        match deleted {
+            0 => Err(PermissionError::NotFound {
+                infra: infra_id,
+                user: *user,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Role;
+
+    #[test]
+    fn roles_are_ordered() {
+        assert!(Role::Read < Role::Edit);
+        assert!(Role::Edit < Role::Admin);
+    }
+
+    #[test]
+    fn role_names() {
+        for role in [Role::Read, Role::Edit, Role::Admin] {
+            assert_eq!(role.to_string().parse::<Role>().unwrap(), role);
+        }
+        assert!("owner".parse::<Role>().is_err());
+    }
+}
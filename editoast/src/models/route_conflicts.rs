@@ -0,0 +1,162 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::infra_cache::InfraCache;
+use crate::objects::{Route, TrackEndpoint};
+
+/// For each route, the set of routes it conflicts with
+pub type ConflictMatrix = BTreeMap<String, BTreeSet<String>>;
+
+/// A switch crossed through one of its groups
+type SwitchGroup<'a> = (&'a String, &'a String);
+
+/// Index the switch groups by the pair of track endpoints their connections link
+fn index_switch_groups(
+    infra_cache: &InfraCache,
+) -> HashMap<(&TrackEndpoint, &TrackEndpoint), Vec<SwitchGroup<'_>>> {
+    let mut index: HashMap<_, Vec<_>> = HashMap::new();
+    for switch in infra_cache.switches().values() {
+        let switch = switch.unwrap_switch();
+        let switch_type = match infra_cache.switch_types().get(&switch.switch_type) {
+            Some(switch_type) => switch_type.unwrap_switch_type(),
+            None => continue,
+        };
+        for (group_name, connections) in switch_type.groups.iter() {
+            for connection in connections {
+                let (src, dst) = match (
+                    switch.ports.get(&connection.src),
+                    switch.ports.get(&connection.dst),
+                ) {
+                    (Some(src), Some(dst)) => (src, dst),
+                    _ => continue,
+                };
+                index
+                    .entry((src, dst))
+                    .or_default()
+                    .push((&switch.obj_id, group_name));
+                if connection.bidirectional {
+                    index
+                        .entry((dst, src))
+                        .or_default()
+                        .push((&switch.obj_id, group_name));
+                }
+            }
+        }
+    }
+    index
+}
+
+/// Retrieve the switch groups crossed by a route
+fn get_crossed_switch_groups<'a>(
+    route: &Route,
+    index: &HashMap<(&TrackEndpoint, &TrackEndpoint), Vec<SwitchGroup<'a>>>,
+) -> Vec<SwitchGroup<'a>> {
+    let mut crossed = vec![];
+    for (prev, next) in route.path.iter().zip(route.path.iter().skip(1)) {
+        if prev.track == next.track {
+            continue;
+        }
+        if let Some(groups) = index.get(&(&prev.get_end(), &next.get_begin())) {
+            crossed.extend(groups.iter().cloned());
+        }
+    }
+    crossed
+}
+
+/// Whether two routes have a common part of track
+fn share_track_range(route: &Route, other: &Route) -> bool {
+    route.path.iter().any(|range| {
+        other.path.iter().any(|other_range| {
+            range.track == other_range.track
+                && range.begin < other_range.end
+                && other_range.begin < range.end
+        })
+    })
+}
+
+/// Whether two routes cross the same switch in different groups
+fn cross_switch_differently(route: &[SwitchGroup], other: &[SwitchGroup]) -> bool {
+    route.iter().any(|(switch, group)| {
+        other
+            .iter()
+            .any(|(other_switch, other_group)| switch == other_switch && group != other_group)
+    })
+}
+
+/// Compute the conflicts between all the routes of an infra.
+/// Two routes conflict when they share a track range or cross the same switch in different groups.
+pub fn compute_conflict_matrix(infra_cache: &InfraCache) -> ConflictMatrix {
+    let index = index_switch_groups(infra_cache);
+    let routes: Vec<(&String, &Route, Vec<SwitchGroup>)> = infra_cache
+        .routes()
+        .iter()
+        .map(|(route_id, route)| {
+            let route = route.unwrap_route();
+            (route_id, route, get_crossed_switch_groups(route, &index))
+        })
+        .collect();
+
+    let mut matrix: ConflictMatrix = routes
+        .iter()
+        .map(|(route_id, _, _)| ((*route_id).clone(), BTreeSet::new()))
+        .collect();
+    for (i, (route_id, route, groups)) in routes.iter().enumerate() {
+        for (other_id, other, other_groups) in routes.iter().skip(i + 1) {
+            if share_track_range(route, other) || cross_switch_differently(groups, other_groups) {
+                matrix
+                    .get_mut(*route_id)
+                    .unwrap()
+                    .insert((*other_id).clone());
+                matrix
+                    .get_mut(*other_id)
+                    .unwrap()
+                    .insert((*route_id).clone());
+            }
+        }
+    }
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::compute_conflict_matrix;
+    use crate::infra_cache::tests::{create_route_cache, create_small_infra_cache};
+    use crate::objects::{Direction, ObjectRef, ObjectType};
+
+    fn set(ids: &[&str]) -> BTreeSet<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn small_infra_conflicts() {
+        let infra_cache = create_small_infra_cache();
+        let matrix = compute_conflict_matrix(&infra_cache);
+        assert_eq!(matrix.len(), 3);
+        assert_eq!(matrix["R1"], set(&[]));
+        assert_eq!(matrix["R2"], set(&["R3"]));
+        assert_eq!(matrix["R3"], set(&["R2"]));
+    }
+
+    #[test]
+    fn switch_crossed_in_different_groups() {
+        let mut infra_cache = create_small_infra_cache();
+        // Routes crossing the switch without sharing any track range
+        for (id, track) in [("R_left", "C"), ("R_right", "D")] {
+            infra_cache.add(create_route_cache(
+                id,
+                ObjectRef::new(ObjectType::Detector, "D1"),
+                ObjectRef::new(ObjectType::BufferStop, "BF2"),
+                vec![],
+                vec![
+                    ("B", 500., 500., Direction::StartToStop),
+                    (track, 0., 10., Direction::StartToStop),
+                ],
+            ));
+        }
+        let matrix = compute_conflict_matrix(&infra_cache);
+        assert_eq!(matrix["R_left"], set(&["R2", "R3", "R_right"]));
+        assert_eq!(matrix["R_right"], set(&["R2", "R3", "R_left"]));
+        assert_eq!(matrix["R1"], set(&[]));
+    }
+}
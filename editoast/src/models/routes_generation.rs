@@ -0,0 +1,294 @@
+use std::collections::{HashMap, HashSet};
+
+use super::errors::graph::Graph;
+use crate::infra_cache::InfraCache;
+use crate::objects::{
+    Direction, DirectionalTrackRange, Endpoint, ObjectRef, ObjectType, Route, TrackEndpoint,
+};
+
+/// Detectors and buffer stops of each track section, sorted by position
+type Waypoints = HashMap<String, Vec<(f64, ObjectRef)>>;
+
+/// Routes are identified by their entry point, exit point and the tracks they go through
+type RouteKey = (ObjectRef, ObjectRef, Vec<String>);
+
+/// Generate the routes going from each detector or buffer stop to the next ones.
+/// Every switch group combination is explored, routes already present in the infra are skipped.
+pub fn generate_routes(infra_cache: &InfraCache) -> Vec<Route> {
+    let graph = Graph::load(infra_cache);
+    let waypoints = load_waypoints(infra_cache);
+    let explorer = RouteExplorer {
+        infra_cache,
+        graph: &graph,
+        waypoints: &waypoints,
+    };
+
+    let mut known_routes: HashSet<RouteKey> = infra_cache
+        .routes()
+        .values()
+        .map(|route| route_key(route.unwrap_route()))
+        .collect();
+    let mut used_ids: HashSet<String> = infra_cache.routes().keys().cloned().collect();
+
+    // Sort entry points to generate routes in a deterministic order
+    let mut entry_points: Vec<_> = waypoints
+        .iter()
+        .flat_map(|(track, track_waypoints)| {
+            track_waypoints
+                .iter()
+                .map(move |(position, waypoint)| (waypoint, track, *position))
+        })
+        .collect();
+    entry_points.sort_by(|(a, _, _), (b, _, _)| a.obj_id.cmp(&b.obj_id));
+
+    let mut routes = vec![];
+    for (entry_point, track, position) in entry_points {
+        for direction in [Direction::StartToStop, Direction::StopToStart] {
+            let mut found = vec![];
+            explorer.walk(
+                track,
+                position,
+                direction,
+                true,
+                &mut vec![],
+                &mut HashSet::new(),
+                &mut found,
+            );
+
+            for (exit_point, path) in found {
+                let mut route = Route {
+                    id: String::new(),
+                    entry_point: entry_point.clone(),
+                    exit_point,
+                    release_detectors: vec![],
+                    path,
+                };
+                if !known_routes.insert(route_key(&route)) {
+                    continue;
+                }
+                route.id = generate_route_id(&route.entry_point, &route.exit_point, &mut used_ids);
+                routes.push(route);
+            }
+        }
+    }
+    routes
+}
+
+fn load_waypoints(infra_cache: &InfraCache) -> Waypoints {
+    let mut waypoints = Waypoints::new();
+    for detector in infra_cache.detectors().values() {
+        let detector = detector.unwrap_detector();
+        waypoints.entry(detector.track.clone()).or_default().push((
+            detector.position,
+            ObjectRef::new(ObjectType::Detector, &detector.obj_id),
+        ));
+    }
+    for buffer_stop in infra_cache.buffer_stops().values() {
+        let buffer_stop = buffer_stop.unwrap_buffer_stop();
+        waypoints
+            .entry(buffer_stop.track.clone())
+            .or_default()
+            .push((
+                buffer_stop.position,
+                ObjectRef::new(ObjectType::BufferStop, &buffer_stop.obj_id),
+            ));
+    }
+    for track_waypoints in waypoints.values_mut() {
+        track_waypoints.sort_by(|(pos_a, a), (pos_b, b)| {
+            pos_a.total_cmp(pos_b).then_with(|| a.obj_id.cmp(&b.obj_id))
+        });
+    }
+    waypoints
+}
+
+fn route_key(route: &Route) -> RouteKey {
+    (
+        route.entry_point.clone(),
+        route.exit_point.clone(),
+        route
+            .path
+            .iter()
+            .map(|range| range.track.obj_id.clone())
+            .collect(),
+    )
+}
+
+/// Build a route id from its entry and exit points, adding a suffix if it's already used
+fn generate_route_id(
+    entry_point: &ObjectRef,
+    exit_point: &ObjectRef,
+    used_ids: &mut HashSet<String>,
+) -> String {
+    let base_id = format!("rt.{}->{}", entry_point.obj_id, exit_point.obj_id);
+    let mut id = base_id.clone();
+    let mut index = 1;
+    while used_ids.contains(&id) {
+        id = format!("{}.{}", base_id, index);
+        index += 1;
+    }
+    used_ids.insert(id.clone());
+    id
+}
+
+fn make_range(track: &str, from: f64, to: f64, direction: Direction) -> DirectionalTrackRange {
+    DirectionalTrackRange {
+        track: ObjectRef::new(ObjectType::TrackSection, track),
+        begin: from.min(to),
+        end: from.max(to),
+        direction,
+    }
+}
+
+struct RouteExplorer<'a> {
+    infra_cache: &'a InfraCache,
+    graph: &'a Graph<'a>,
+    waypoints: &'a Waypoints,
+}
+
+impl<'a> RouteExplorer<'a> {
+    /// Walk along the track from the given position until the next waypoint.
+    /// When the end of the track is reached, every neighbour is explored.
+    /// Each reached waypoint is pushed to `found` along with the path leading to it.
+    #[allow(clippy::too_many_arguments)]
+    fn walk(
+        &self,
+        track: &str,
+        from: f64,
+        direction: Direction,
+        is_entry_track: bool,
+        path: &mut Vec<DirectionalTrackRange>,
+        visited: &mut HashSet<String>,
+        found: &mut Vec<(ObjectRef, Vec<DirectionalTrackRange>)>,
+    ) {
+        let track_cache = match self.infra_cache.track_sections().get(track) {
+            Some(track_cache) => track_cache.unwrap_track_section(),
+            None => return,
+        };
+
+        // The waypoint the route starts from must not be found again
+        let is_ahead = |position: f64| match (&direction, is_entry_track) {
+            (Direction::StartToStop, true) => position > from,
+            (Direction::StartToStop, false) => position >= from,
+            (Direction::StopToStart, true) => position < from,
+            (Direction::StopToStart, false) => position <= from,
+        };
+        let track_waypoints = self.waypoints.get(track).map(Vec::as_slice).unwrap_or(&[]);
+        let next_waypoint = match direction {
+            Direction::StartToStop => track_waypoints.iter().find(|(pos, _)| is_ahead(*pos)),
+            Direction::StopToStart => track_waypoints.iter().rev().find(|(pos, _)| is_ahead(*pos)),
+        };
+
+        if let Some((position, exit_point)) = next_waypoint {
+            path.push(make_range(track, from, *position, direction));
+            found.push((exit_point.clone(), path.clone()));
+            path.pop();
+            return;
+        }
+
+        let (bound, endpoint) = match direction {
+            Direction::StartToStop => (track_cache.length, Endpoint::End),
+            Direction::StopToStart => (0., Endpoint::Begin),
+        };
+        path.push(make_range(track, from, bound, direction));
+        visited.insert(track.to_string());
+
+        let track_endpoint = TrackEndpoint {
+            endpoint,
+            track: ObjectRef::new(ObjectType::TrackSection, track),
+        };
+        if let Some(neighbours) = self.graph.get_neighbours(&track_endpoint) {
+            let mut neighbours: Vec<_> = neighbours.iter().collect();
+            neighbours.sort_by(|a, b| a.track.obj_id.cmp(&b.track.obj_id));
+            for neighbour in neighbours {
+                let next_track = &neighbour.track.obj_id;
+                if visited.contains(next_track) {
+                    continue;
+                }
+                let (start, next_direction) = match neighbour.endpoint {
+                    Endpoint::Begin => (0., Direction::StartToStop),
+                    Endpoint::End => match self.infra_cache.track_sections().get(next_track) {
+                        Some(next_cache) => (
+                            next_cache.unwrap_track_section().length,
+                            Direction::StopToStart,
+                        ),
+                        None => continue,
+                    },
+                };
+                self.walk(
+                    next_track,
+                    start,
+                    next_direction,
+                    false,
+                    path,
+                    visited,
+                    found,
+                );
+            }
+        }
+
+        visited.remove(track);
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate_routes;
+    use crate::infra_cache::tests::create_small_infra_cache;
+    use crate::models::errors::graph::Graph;
+    use crate::models::errors::routes;
+    use crate::objects::{Direction, ObjectRef, ObjectType};
+
+    #[test]
+    fn generate_small_infra_routes() {
+        let mut infra_cache = create_small_infra_cache();
+        for route_id in ["R1", "R2", "R3"] {
+            infra_cache.apply_delete(&ObjectRef::new(ObjectType::Route, route_id));
+        }
+
+        let routes = generate_routes(&infra_cache);
+        let mut ids: Vec<_> = routes.iter().map(|route| route.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                "rt.BF1->D1",
+                "rt.BF2->D1",
+                "rt.BF3->D1",
+                "rt.D1->BF1",
+                "rt.D1->BF2",
+                "rt.D1->BF3",
+            ]
+        );
+
+        let route = routes.iter().find(|r| r.id == "rt.BF2->D1").unwrap();
+        let path: Vec<_> = route
+            .path
+            .iter()
+            .map(|r| (r.track.obj_id.as_str(), r.begin, r.end, r.direction.clone()))
+            .collect();
+        assert_eq!(
+            path,
+            vec![
+                ("C", 0., 480., Direction::StopToStart),
+                ("B", 250., 500., Direction::StopToStart),
+            ]
+        );
+
+        // Generated routes must be valid
+        for route in routes {
+            infra_cache.add(route);
+        }
+        let graph = Graph::load(&infra_cache);
+        assert!(routes::generate_errors(&infra_cache, &graph).is_empty());
+    }
+
+    #[test]
+    fn skip_existing_routes() {
+        let infra_cache = create_small_infra_cache();
+        let routes = generate_routes(&infra_cache);
+        let mut ids: Vec<_> = routes.iter().map(|route| route.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["rt.BF2->D1", "rt.BF3->D1", "rt.D1->BF1"]);
+    }
+}
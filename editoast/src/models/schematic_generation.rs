@@ -0,0 +1,318 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use serde_json::json;
+
+use super::errors::graph::Graph;
+use crate::infra_cache::InfraCache;
+use crate::objects::operation::{Operation, UpdateOperation};
+use crate::objects::{Endpoint, ObjectRef, ObjectType, TrackEndpoint};
+
+/// Approximate number of meters in a degree, used to lay out the schematic in WGS84 coordinates
+const METERS_PER_DEGREE: f64 = 111_195.;
+/// Vertical distance between two lanes of the schematic, in degrees
+const LANE_SPACING: f64 = 0.0005;
+/// Horizontal length of the diagonal leading a diverging track to its lane, in meters
+const DIAGONAL_LENGTH: f64 = 50.;
+
+/// Position of a track section in the schematic layout
+#[derive(Debug, Clone, PartialEq)]
+struct PlacedTrack {
+    /// Abscissa of the beginning of the track, in meters from the component origin
+    x_begin: f64,
+    /// Whether the track goes towards increasing abscissas from its beginning
+    forward: bool,
+    /// Lane of the track and lane of the track it diverges from, with the diverging side
+    lane: i64,
+    from_lane: i64,
+    from_endpoint: Endpoint,
+    /// First track of the connected component, used as the origin of the layout
+    root: String,
+}
+
+impl PlacedTrack {
+    /// Schematic vertices of the track as (position on the track, lane) pairs, sorted by position
+    fn vertices(&self, length: f64, extra_positions: &[f64]) -> Vec<(f64, f64)> {
+        let lane = self.lane as f64;
+        let from_lane = self.from_lane as f64;
+        let diagonal = DIAGONAL_LENGTH.min(length / 2.);
+        let mut vertices = if self.lane == self.from_lane {
+            vec![(0., lane), (length, lane)]
+        } else {
+            match self.from_endpoint {
+                Endpoint::Begin => vec![(0., from_lane), (diagonal, lane), (length, lane)],
+                Endpoint::End => vec![(0., lane), (length - diagonal, lane), (length, from_lane)],
+            }
+        };
+
+        // Add a vertex where operational points are located
+        for &position in extra_positions {
+            let position = position.clamp(0., length);
+            if vertices
+                .iter()
+                .any(|(pos, _)| (pos - position).abs() < 1e-6)
+            {
+                continue;
+            }
+            let index = vertices
+                .iter()
+                .position(|(pos, _)| *pos > position)
+                .unwrap();
+            let (pos_a, lane_a) = vertices[index - 1];
+            let (pos_b, lane_b) = vertices[index];
+            let ratio = (position - pos_a) / (pos_b - pos_a);
+            vertices.insert(index, (position, lane_a + (lane_b - lane_a) * ratio));
+        }
+        vertices
+    }
+}
+
+/// Build an undirected adjacency between track endpoints from the directed graph
+fn undirected_links<'a>(
+    infra_cache: &'a InfraCache,
+    graph: &'a Graph,
+) -> HashMap<TrackEndpoint, BTreeSet<(String, Endpoint)>> {
+    let mut links: HashMap<TrackEndpoint, BTreeSet<(String, Endpoint)>> = HashMap::new();
+    for track in infra_cache.track_sections().values() {
+        let track = track.unwrap_track_section();
+        for endpoint in [track.get_begin(), track.get_end()] {
+            for neighbour in graph.get_neighbours(&endpoint).into_iter().flatten() {
+                links
+                    .entry(endpoint.clone())
+                    .or_default()
+                    .insert((neighbour.track.obj_id.clone(), neighbour.endpoint.clone()));
+                links
+                    .entry((*neighbour).clone())
+                    .or_default()
+                    .insert((track.obj_id.clone(), endpoint.endpoint.clone()));
+            }
+        }
+    }
+    links
+}
+
+fn track_endpoint(track: &str, endpoint: Endpoint) -> TrackEndpoint {
+    TrackEndpoint {
+        endpoint,
+        track: ObjectRef::new(ObjectType::TrackSection, track),
+    }
+}
+
+/// Lay out the tracks of an infra, one connected component after the other.
+/// Each component is walked from its first track (by id): connected tracks continue on the
+/// same lane, while each additional branch of a switch gets a new lane.
+fn layout_tracks(infra_cache: &InfraCache, graph: &Graph) -> HashMap<String, PlacedTrack> {
+    let links = undirected_links(infra_cache, graph);
+    let mut track_ids: Vec<_> = infra_cache.track_sections().keys().collect();
+    track_ids.sort();
+
+    let mut placed: HashMap<String, PlacedTrack> = HashMap::new();
+    let mut next_lane = 0;
+    for root in track_ids {
+        if placed.contains_key(root) {
+            continue;
+        }
+        placed.insert(
+            root.clone(),
+            PlacedTrack {
+                x_begin: 0.,
+                forward: true,
+                lane: next_lane,
+                from_lane: next_lane,
+                from_endpoint: Endpoint::Begin,
+                root: root.clone(),
+            },
+        );
+        next_lane += 1;
+
+        let mut queue = VecDeque::from([root.clone()]);
+        while let Some(track_id) = queue.pop_front() {
+            let track = placed[&track_id].clone();
+            let length = infra_cache
+                .track_sections()
+                .get(&track_id)
+                .unwrap()
+                .unwrap_track_section()
+                .length;
+            for endpoint in [Endpoint::Begin, Endpoint::End] {
+                // Location of the endpoint and direction going away from the track
+                let (x, outward) = match (&endpoint, track.forward) {
+                    (Endpoint::Begin, forward) => (track.x_begin, !forward),
+                    (Endpoint::End, true) => (track.x_begin + length, true),
+                    (Endpoint::End, false) => (track.x_begin - length, false),
+                };
+                let neighbours = match links.get(&track_endpoint(&track_id, endpoint.clone())) {
+                    Some(neighbours) => neighbours,
+                    None => continue,
+                };
+                let mut same_lane_used = false;
+                for (neighbour_id, neighbour_endpoint) in neighbours {
+                    if placed.contains_key(neighbour_id) {
+                        same_lane_used = true;
+                        continue;
+                    }
+                    let neighbour_length = match infra_cache.track_sections().get(neighbour_id) {
+                        Some(neighbour) => neighbour.unwrap_track_section().length,
+                        None => continue,
+                    };
+                    let lane = if same_lane_used {
+                        next_lane += 1;
+                        next_lane - 1
+                    } else {
+                        same_lane_used = true;
+                        track.lane
+                    };
+                    let (x_begin, forward) = match neighbour_endpoint {
+                        Endpoint::Begin => (x, outward),
+                        Endpoint::End if outward => (x + neighbour_length, false),
+                        Endpoint::End => (x - neighbour_length, true),
+                    };
+                    placed.insert(
+                        neighbour_id.clone(),
+                        PlacedTrack {
+                            x_begin,
+                            forward,
+                            lane,
+                            from_lane: track.lane,
+                            from_endpoint: neighbour_endpoint.clone(),
+                            root: track.root.clone(),
+                        },
+                    );
+                    queue.push_back(neighbour_id.clone());
+                }
+            }
+        }
+    }
+    placed
+}
+
+/// Generate a simplified schematic layout of the infra and return the update operations
+/// replacing the `sch` line string of each track section.
+/// Operational points are kept as vertices of the schematic line strings.
+/// If `only_duplicated` is set, only tracks whose `sch` has the same extent as `geo` are updated.
+pub fn generate_schematic(infra_cache: &InfraCache, only_duplicated: bool) -> Vec<Operation> {
+    let graph = Graph::load(infra_cache);
+    let placed = layout_tracks(infra_cache, &graph);
+
+    let mut op_positions: HashMap<&String, Vec<f64>> = HashMap::new();
+    for op in infra_cache.operational_points().values() {
+        for part in op.unwrap_operational_point().parts.iter() {
+            op_positions
+                .entry(&part.track.obj_id)
+                .or_default()
+                .push(part.position);
+        }
+    }
+
+    let mut track_ids: Vec<_> = placed.keys().collect();
+    track_ids.sort();
+    let mut operations = vec![];
+    for track_id in track_ids {
+        let track = placed.get(track_id).unwrap();
+        let track_cache = infra_cache
+            .track_sections()
+            .get(track_id)
+            .unwrap()
+            .unwrap_track_section();
+        if only_duplicated && track_cache.bbox_geo != track_cache.bbox_sch {
+            continue;
+        }
+
+        // Components are laid out from the geographic location of their root track
+        let root_lane = placed[&track.root].lane as f64;
+        let (x, y) = infra_cache
+            .track_sections()
+            .get(&track.root)
+            .unwrap()
+            .unwrap_track_section()
+            .bbox_geo
+            .0;
+        let origin = if x.is_finite() && y.is_finite() {
+            (x, y)
+        } else {
+            (0., 0.)
+        };
+
+        let positions = op_positions.get(track_id).cloned().unwrap_or_default();
+        let coordinates: Vec<_> = track
+            .vertices(track_cache.length, &positions)
+            .into_iter()
+            .map(|(position, lane)| {
+                let x = if track.forward {
+                    track.x_begin + position
+                } else {
+                    track.x_begin - position
+                };
+                [
+                    origin.0 + x / METERS_PER_DEGREE,
+                    origin.1 - (lane - root_lane) * LANE_SPACING,
+                ]
+            })
+            .collect();
+
+        let patch = json!([{
+            "op": "replace",
+            "path": "/sch",
+            "value": { "type": "LineString", "coordinates": coordinates },
+        }]);
+        operations.push(Operation::Update(UpdateOperation {
+            obj_id: track_id.clone(),
+            obj_type: ObjectType::TrackSection,
+            railjson_patch: serde_json::from_value(patch).unwrap(),
+        }));
+    }
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_schematic, layout_tracks};
+    use crate::infra_cache::tests::{create_operational_point_cache, create_small_infra_cache};
+    use crate::models::errors::graph::Graph;
+    use crate::objects::operation::Operation;
+    use serde_json::Value;
+
+    fn get_coordinates(operations: &[Operation], track_id: &str) -> Value {
+        let update = operations
+            .iter()
+            .find_map(|op| match op {
+                Operation::Update(update) if update.obj_id == track_id => Some(update),
+                _ => None,
+            })
+            .unwrap();
+        serde_json::to_value(&update.railjson_patch).unwrap()[0]["value"]["coordinates"].clone()
+    }
+
+    #[test]
+    fn small_infra_layout() {
+        let infra_cache = create_small_infra_cache();
+        let graph = Graph::load(&infra_cache);
+        let placed = layout_tracks(&infra_cache, &graph);
+        assert_eq!(placed.len(), 4);
+        assert_eq!((placed["A"].x_begin, placed["A"].lane), (0., 0));
+        assert_eq!((placed["B"].x_begin, placed["B"].lane), (500., 0));
+        // The first branch of the switch continues on the same lane, the other one gets a new lane
+        assert_eq!((placed["C"].x_begin, placed["C"].lane), (1000., 0));
+        assert_eq!((placed["D"].x_begin, placed["D"].lane), (1000., 1));
+        assert!(placed
+            .values()
+            .all(|track| track.forward && track.root == "A"));
+    }
+
+    #[test]
+    fn small_infra_schematic() {
+        let mut infra_cache = create_small_infra_cache();
+        infra_cache.add(create_operational_point_cache("OP", "D", 250.));
+        let operations = generate_schematic(&infra_cache, false);
+        assert_eq!(operations.len(), 4);
+
+        // Track sections of the small infra have no geometry, the origin is (0, 0)
+        let coordinates = get_coordinates(&operations, "D");
+        let coordinates: Vec<[f64; 2]> = serde_json::from_value(coordinates).unwrap();
+        assert_eq!(coordinates.len(), 4);
+        assert_eq!(coordinates[0][1], 0.);
+        assert!(coordinates[1][1] < 0.);
+        assert_eq!(coordinates[1][1], coordinates[3][1]);
+        // The operational point is a vertex of the line string
+        assert!((coordinates[2][0] - 1250. / 111_195.).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,26 @@
+WITH collect AS (
+    SELECT (tracks.data->>'length')::float AS track_length,
+        ST_Transform(layer.geographic, 4326) AS track_geo,
+        ST_Transform(layer.schematic, 4326) AS track_sch
+    FROM osrd_infra_tracksectionlayer AS layer
+        INNER JOIN osrd_infra_tracksectionmodel AS tracks ON tracks.obj_id = layer.obj_id
+        AND tracks.infra_id = layer.infra_id
+    WHERE layer.infra_id = $1
+        AND layer.obj_id = $2
+),
+points AS (
+    SELECT ST_LineInterpolatePoint(
+            track_geo,
+            LEAST(GREATEST($3 / track_length, 0.), 1.)
+        ) AS geo,
+        ST_LineInterpolatePoint(
+            track_sch,
+            LEAST(GREATEST($3 / track_length, 0.), 1.)
+        ) AS sch
+    FROM collect
+)
+SELECT ST_X(geo) AS geo_x,
+    ST_Y(geo) AS geo_y,
+    ST_X(sch) AS sch_x,
+    ST_Y(sch) AS sch_y
+FROM points
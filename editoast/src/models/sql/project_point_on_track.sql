@@ -0,0 +1,23 @@
+WITH point AS (
+    SELECT ST_SetSRID(ST_MakePoint($2, $3), 4326) AS geom
+),
+candidates AS (
+    -- Closest tracks in Web Mercator, using the spatial index of the layer.
+    -- The projection being conformal, the nearest track in meters is among them.
+    SELECT obj_id,
+        ST_Transform(geographic, 4326) AS track_geo
+    FROM osrd_infra_tracksectionlayer
+    WHERE infra_id = $1
+    ORDER BY geographic <-> ST_Transform(ST_SetSRID(ST_MakePoint($2, $3), 4326), 3857)
+    LIMIT 16
+)
+SELECT candidates.obj_id AS track,
+    ST_LineLocatePoint(candidates.track_geo, point.geom) * (tracks.data->>'length')::float AS "offset",
+    ST_Distance(candidates.track_geo::geography, point.geom::geography) AS distance
+FROM candidates
+    INNER JOIN osrd_infra_tracksectionmodel AS tracks ON tracks.obj_id = candidates.obj_id
+    AND tracks.infra_id = $1,
+    point
+ORDER BY distance,
+    candidates.obj_id
+LIMIT 1
@@ -0,0 +1,458 @@
+use diesel::result::Error as DieselError;
+use diesel::sql_types::{Integer, Jsonb, Text};
+use diesel::{sql_query, PgConnection, QueryableByName, RunQueryDsl};
+use rocket::http::Status;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::error::ApiError;
+use crate::infra_cache::{InfraCache, ObjectCache};
+use crate::objects::operation::{Operation, UpdateOperation};
+use crate::objects::{LineString, ObjectRef, ObjectType, TrackSection};
+
+/// Mean earth radius in meters, used to compute the length of geographic line strings
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+#[derive(Debug, Error)]
+pub enum TrackGeometryError {
+    #[error("Track section '{0}' could not be found")]
+    TrackNotFound(String),
+    #[error("Vertex index {index} is out of bounds, the line string has {vertex_count} vertices")]
+    InvalidVertexIndex { index: usize, vertex_count: usize },
+    #[error("A line string must keep at least two vertices")]
+    NotEnoughVertices,
+    #[error(transparent)]
+    Database(#[from] DieselError),
+}
+
+impl ApiError for TrackGeometryError {
+    fn get_status(&self) -> Status {
+        match self {
+            TrackGeometryError::TrackNotFound(_) => Status::NotFound,
+            TrackGeometryError::InvalidVertexIndex { .. }
+            | TrackGeometryError::NotEnoughVertices => Status::BadRequest,
+            TrackGeometryError::Database(_) => Status::InternalServerError,
+        }
+    }
+
+    fn get_type(&self) -> &'static str {
+        match self {
+            TrackGeometryError::TrackNotFound(_) => "editoast:track_geometry:TrackNotFound",
+            TrackGeometryError::InvalidVertexIndex { .. } => {
+                "editoast:track_geometry:InvalidVertexIndex"
+            }
+            TrackGeometryError::NotEnoughVertices => "editoast:track_geometry:NotEnoughVertices",
+            TrackGeometryError::Database(_) => "editoast:track_geometry:Database",
+        }
+    }
+
+    fn extra(&self) -> Option<Map<String, Value>> {
+        match self {
+            TrackGeometryError::TrackNotFound(track) => {
+                json!({ "track": track }).as_object().cloned()
+            }
+            TrackGeometryError::InvalidVertexIndex {
+                index,
+                vertex_count,
+            } => json!({ "index": index, "vertex_count": vertex_count })
+                .as_object()
+                .cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// Which line string of the track section is edited
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GeometryRepresentation {
+    Geo,
+    Sch,
+}
+
+/// An edition of a single vertex of a line string
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum VertexEdit {
+    /// Move the vertex at the given index
+    #[serde(rename = "MOVE")]
+    Move { index: usize, coordinates: [f64; 2] },
+    /// Insert a vertex before the given index (or at the end if the index is the vertex count)
+    #[serde(rename = "INSERT")]
+    Insert { index: usize, coordinates: [f64; 2] },
+    /// Delete the vertex at the given index
+    #[serde(rename = "DELETE")]
+    Delete { index: usize },
+}
+
+/// A batch of vertex editions on one of the line strings of a track section
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrackGeometryEdit {
+    pub representation: GeometryRepresentation,
+    pub edits: Vec<VertexEdit>,
+    /// Recompute the track length from its geographic line string
+    /// and rescale the positions of the objects located on the track
+    #[serde(default)]
+    pub update_length: bool,
+}
+
+#[derive(QueryableByName)]
+struct TrackData {
+    #[sql_type = "Jsonb"]
+    data: Value,
+}
+
+/// Load the railjson of a track section
+pub fn load_track_section(
+    conn: &PgConnection,
+    infra_id: i32,
+    track_id: &str,
+) -> Result<TrackSection, TrackGeometryError> {
+    let mut tracks = sql_query(
+        "SELECT data FROM osrd_infra_tracksectionmodel WHERE infra_id = $1 AND obj_id = $2",
+    )
+    .bind::<Integer, _>(infra_id)
+    .bind::<Text, _>(track_id)
+    .load::<TrackData>(conn)?;
+    match tracks.pop() {
+        Some(track) => Ok(serde_json::from_value(track.data).unwrap()),
+        None => Err(TrackGeometryError::TrackNotFound(track_id.into())),
+    }
+}
+
+/// Apply vertex editions in order to a list of coordinates
+fn apply_vertex_edits(
+    coordinates: &mut Vec<[f64; 2]>,
+    edits: &[VertexEdit],
+) -> Result<(), TrackGeometryError> {
+    for edit in edits {
+        let (index, max_index) = match edit {
+            VertexEdit::Move { index, .. } | VertexEdit::Delete { index } => {
+                (*index, coordinates.len())
+            }
+            VertexEdit::Insert { index, .. } => (*index, coordinates.len() + 1),
+        };
+        if index >= max_index {
+            return Err(TrackGeometryError::InvalidVertexIndex {
+                index,
+                vertex_count: coordinates.len(),
+            });
+        }
+        match edit {
+            VertexEdit::Move { coordinates: c, .. } => coordinates[index] = *c,
+            VertexEdit::Insert { coordinates: c, .. } => coordinates.insert(index, *c),
+            VertexEdit::Delete { .. } => {
+                coordinates.remove(index);
+            }
+        }
+    }
+    if coordinates.len() < 2 {
+        return Err(TrackGeometryError::NotEnoughVertices);
+    }
+    Ok(())
+}
+
+/// Compute the length in meters of a line string of WGS84 coordinates
+fn geographic_length(coordinates: &[[f64; 2]]) -> f64 {
+    coordinates
+        .windows(2)
+        .map(|segment| {
+            let [lon_a, lat_a] = segment[0].map(f64::to_radians);
+            let [lon_b, lat_b] = segment[1].map(f64::to_radians);
+            let h = ((lat_b - lat_a) / 2.).sin().powi(2)
+                + lat_a.cos() * lat_b.cos() * ((lon_b - lon_a) / 2.).sin().powi(2);
+            2. * EARTH_RADIUS * h.sqrt().asin()
+        })
+        .sum()
+}
+
+/// Build the `replace` patch operations moving positions given as json pointers
+fn rescale_patch(positions: &[(String, f64)], ratio: f64) -> Vec<Value> {
+    positions
+        .iter()
+        .map(|(path, position)| json!({ "op": "replace", "path": path, "value": position * ratio }))
+        .collect()
+}
+
+fn update_operation(obj_ref: ObjectRef, patch: Vec<Value>) -> Operation {
+    Operation::Update(UpdateOperation {
+        obj_id: obj_ref.obj_id,
+        obj_type: obj_ref.obj_type,
+        railjson_patch: serde_json::from_value(Value::Array(patch)).unwrap(),
+    })
+}
+
+/// Positions (as json pointers) located on the given track of an object referencing it
+fn get_attached_positions(object: &ObjectCache, track_id: &str) -> Vec<(String, f64)> {
+    let ranges = |prefix: &str, ranges: Vec<(&String, f64, f64)>| {
+        ranges
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (track, _, _))| *track == track_id)
+            .flat_map(|(index, (_, begin, end))| {
+                [
+                    (format!("/{}/{}/begin", prefix, index), begin),
+                    (format!("/{}/{}/end", prefix, index), end),
+                ]
+            })
+            .collect()
+    };
+    match object {
+        ObjectCache::Signal(signal) => vec![("/position".into(), signal.position)],
+        ObjectCache::Detector(detector) => vec![("/position".into(), detector.position)],
+        ObjectCache::BufferStop(buffer_stop) => vec![("/position".into(), buffer_stop.position)],
+        ObjectCache::OperationalPoint(op) => op
+            .parts
+            .iter()
+            .enumerate()
+            .filter(|(_, part)| part.track.obj_id == track_id)
+            .map(|(index, part)| (format!("/parts/{}/position", index), part.position))
+            .collect(),
+        ObjectCache::SpeedSection(speed_section) => ranges(
+            "track_ranges",
+            speed_section
+                .track_ranges
+                .iter()
+                .map(|r| (&r.track.obj_id, r.begin, r.end))
+                .collect(),
+        ),
+        ObjectCache::Catenary(catenary) => ranges(
+            "track_ranges",
+            catenary
+                .track_ranges
+                .iter()
+                .map(|r| (&r.track.obj_id, r.begin, r.end))
+                .collect(),
+        ),
+        ObjectCache::Route(route) => ranges(
+            "path",
+            route
+                .path
+                .iter()
+                .map(|r| (&r.track.obj_id, r.begin, r.end))
+                .collect(),
+        ),
+        _ => vec![],
+    }
+}
+
+/// Build the update operations applying vertex editions to a track section.
+/// When the length is updated, the ranges of the track (slopes, curves, loading gauge limits)
+/// and the positions of the objects located on it are rescaled to the new length.
+pub fn get_track_geometry_operations(
+    track: &TrackSection,
+    edit: &TrackGeometryEdit,
+    infra_cache: &InfraCache,
+) -> Result<Vec<Operation>, TrackGeometryError> {
+    let LineString::LineString {
+        coordinates: geo_coordinates,
+    } = &track.geo;
+    let LineString::LineString {
+        coordinates: sch_coordinates,
+    } = &track.sch;
+    let (path, mut coordinates) = match edit.representation {
+        GeometryRepresentation::Geo => ("/geo/coordinates", geo_coordinates.clone()),
+        GeometryRepresentation::Sch => ("/sch/coordinates", sch_coordinates.clone()),
+    };
+    apply_vertex_edits(&mut coordinates, &edit.edits)?;
+
+    let mut track_patch = vec![json!({ "op": "replace", "path": path, "value": coordinates })];
+    let track_ref = ObjectRef::new(ObjectType::TrackSection, &track.id);
+    if !edit.update_length {
+        return Ok(vec![update_operation(track_ref, track_patch)]);
+    }
+
+    let geo_coordinates = match edit.representation {
+        GeometryRepresentation::Geo => &coordinates,
+        GeometryRepresentation::Sch => geo_coordinates,
+    };
+    let length = geographic_length(geo_coordinates);
+    track_patch.push(json!({ "op": "replace", "path": "/length", "value": length }));
+    if track.length <= 0. {
+        return Ok(vec![update_operation(track_ref, track_patch)]);
+    }
+
+    // Rescale the ranges of the track itself
+    let ratio = length / track.length;
+    let mut track_positions = vec![];
+    for (index, slope) in track.slopes.iter().enumerate() {
+        track_positions.push((format!("/slopes/{}/begin", index), slope.begin));
+        track_positions.push((format!("/slopes/{}/end", index), slope.end));
+    }
+    for (index, curve) in track.curves.iter().enumerate() {
+        track_positions.push((format!("/curves/{}/begin", index), curve.begin));
+        track_positions.push((format!("/curves/{}/end", index), curve.end));
+    }
+    for (index, limit) in track.loading_gauge_limits.iter().enumerate() {
+        track_positions.push((
+            format!("/loading_gauge_limits/{}/begin", index),
+            limit.begin,
+        ));
+        track_positions.push((format!("/loading_gauge_limits/{}/end", index), limit.end));
+    }
+    track_patch.extend(rescale_patch(&track_positions, ratio));
+    let mut operations = vec![update_operation(track_ref, track_patch)];
+
+    // Rescale the objects located on the track, sorted to keep the operations deterministic
+    let mut attached: Vec<_> = infra_cache
+        .track_sections_refs
+        .get(&track.id)
+        .map(|refs| refs.iter().collect())
+        .unwrap_or_default();
+    attached.sort_by(|a, b| {
+        a.obj_type
+            .get_table()
+            .cmp(b.obj_type.get_table())
+            .then_with(|| a.obj_id.cmp(&b.obj_id))
+    });
+    for obj_ref in attached {
+        let object = match infra_cache
+            .get_objects_by_type(obj_ref.obj_type)
+            .get(&obj_ref.obj_id)
+        {
+            Some(object) => object,
+            None => continue,
+        };
+        let positions = get_attached_positions(object, &track.id);
+        if !positions.is_empty() {
+            operations.push(update_operation(
+                obj_ref.clone(),
+                rescale_patch(&positions, ratio),
+            ));
+        }
+    }
+    Ok(operations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        apply_vertex_edits, geographic_length, get_track_geometry_operations,
+        GeometryRepresentation, TrackGeometryEdit, TrackGeometryError, VertexEdit,
+    };
+    use crate::infra_cache::tests::create_small_infra_cache;
+    use crate::objects::operation::Operation;
+    use crate::objects::{LineString, TrackSection};
+    use serde_json::json;
+
+    fn track_a() -> TrackSection {
+        TrackSection {
+            id: "A".into(),
+            length: 500.,
+            geo: LineString::LineString {
+                coordinates: vec![[0., 0.], [0., 1.]],
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn vertex_edits() {
+        let mut coordinates = vec![[0., 0.], [1., 1.]];
+        let edits = [
+            VertexEdit::Insert {
+                index: 1,
+                coordinates: [0.5, 0.],
+            },
+            VertexEdit::Move {
+                index: 2,
+                coordinates: [2., 2.],
+            },
+            VertexEdit::Insert {
+                index: 3,
+                coordinates: [3., 3.],
+            },
+            VertexEdit::Delete { index: 0 },
+        ];
+        apply_vertex_edits(&mut coordinates, &edits).unwrap();
+        assert_eq!(coordinates, vec![[0.5, 0.], [2., 2.], [3., 3.]]);
+    }
+
+    #[test]
+    fn invalid_vertex_edits() {
+        let mut coordinates = vec![[0., 0.], [1., 1.]];
+        assert!(matches!(
+            apply_vertex_edits(&mut coordinates, &[VertexEdit::Delete { index: 2 }]),
+            Err(TrackGeometryError::InvalidVertexIndex { index: 2, .. })
+        ));
+        let mut coordinates = vec![[0., 0.], [1., 1.]];
+        assert!(matches!(
+            apply_vertex_edits(&mut coordinates, &[VertexEdit::Delete { index: 0 }]),
+            Err(TrackGeometryError::NotEnoughVertices)
+        ));
+    }
+
+    #[test]
+    fn length_of_a_meridian_degree() {
+        let length = geographic_length(&[[0., 0.], [0., 0.5], [0., 1.]]);
+        assert!((length - 111_195.).abs() < 1.);
+    }
+
+    #[test]
+    fn geometry_without_length_update() {
+        let infra_cache = create_small_infra_cache();
+        let edit = TrackGeometryEdit {
+            representation: GeometryRepresentation::Sch,
+            edits: vec![VertexEdit::Move {
+                index: 0,
+                coordinates: [5., 5.],
+            }],
+            update_length: false,
+        };
+        let operations = get_track_geometry_operations(&track_a(), &edit, &infra_cache).unwrap();
+        assert_eq!(operations.len(), 1);
+        let Operation::Update(update) = &operations[0] else {
+            panic!("Expected an update operation")
+        };
+        assert_eq!(
+            serde_json::to_value(&update.railjson_patch).unwrap(),
+            json!([{ "op": "replace", "path": "/sch/coordinates", "value": [[5., 5.], [1., 1.]] }])
+        );
+    }
+
+    #[test]
+    fn rescale_attached_objects() {
+        let infra_cache = create_small_infra_cache();
+        // Halve the track length: a degree of meridian is about 111km long
+        let edit = TrackGeometryEdit {
+            representation: GeometryRepresentation::Geo,
+            edits: vec![VertexEdit::Move {
+                index: 1,
+                coordinates: [0., 250. / 111_195.],
+            }],
+            update_length: true,
+        };
+        let operations = get_track_geometry_operations(&track_a(), &edit, &infra_cache).unwrap();
+        let updates: Vec<_> = operations
+            .iter()
+            .map(|op| match op {
+                Operation::Update(update) => (
+                    update.obj_id.as_str(),
+                    serde_json::to_value(&update.railjson_patch).unwrap(),
+                ),
+                _ => panic!("Expected an update operation"),
+            })
+            .collect();
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates[0].0, "A");
+        assert_eq!(updates[0].1[1]["path"], "/length");
+        assert!((updates[0].1[1]["value"].as_f64().unwrap() - 250.).abs() < 0.1);
+
+        assert_eq!(updates[1].0, "BF1");
+        assert_eq!(updates[1].1[0]["path"], "/position");
+        assert!((updates[1].1[0]["value"].as_f64().unwrap() - 10.).abs() < 0.1);
+
+        // Only the range of the route on the edited track is rescaled
+        assert_eq!(updates[2].0, "R1");
+        let paths: Vec<_> = updates[2]
+            .1
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|op| op["path"].as_str().unwrap())
+            .collect();
+        assert_eq!(paths, vec!["/path/0/begin", "/path/0/end"]);
+    }
+}
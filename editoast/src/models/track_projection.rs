@@ -0,0 +1,235 @@
+use diesel::result::Error as DieselError;
+use diesel::sql_types::{Double, Integer, Text};
+use diesel::{sql_query, PgConnection, QueryableByName, RunQueryDsl};
+use rocket::http::Status;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::error::ApiError;
+
+#[derive(Debug, Error)]
+pub enum TrackProjectionError {
+    #[error("Infra has no track section to project on")]
+    NoTrackSection,
+    #[error("Track section '{0}' could not be found")]
+    TrackNotFound(String),
+    #[error(transparent)]
+    Database(#[from] DieselError),
+}
+
+impl ApiError for TrackProjectionError {
+    fn get_status(&self) -> Status {
+        match self {
+            TrackProjectionError::NoTrackSection | TrackProjectionError::TrackNotFound(_) => {
+                Status::NotFound
+            }
+            TrackProjectionError::Database(_) => Status::InternalServerError,
+        }
+    }
+
+    fn get_type(&self) -> &'static str {
+        match self {
+            TrackProjectionError::NoTrackSection => "editoast:track_projection:NoTrackSection",
+            TrackProjectionError::TrackNotFound(_) => "editoast:track_projection:TrackNotFound",
+            TrackProjectionError::Database(_) => "editoast:track_projection:Database",
+        }
+    }
+
+    fn extra(&self) -> Option<Map<String, Value>> {
+        match self {
+            TrackProjectionError::TrackNotFound(track) => {
+                json!({ "track": track }).as_object().cloned()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A GeoJSON point
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum Point {
+    Point { coordinates: [f64; 2] },
+}
+
+impl Point {
+    fn new(x: f64, y: f64) -> Self {
+        Self::Point {
+            coordinates: [x, y],
+        }
+    }
+}
+
+/// Projection of a geographic point on the closest track section
+#[derive(QueryableByName, Debug, Clone, Serialize, Deserialize)]
+pub struct TrackProjection {
+    #[sql_type = "Text"]
+    pub track: String,
+    /// Position of the projected point from the beginning of the track, in meters
+    #[sql_type = "Double"]
+    pub offset: f64,
+    /// Distance between the given point and the track, in meters
+    #[sql_type = "Double"]
+    pub distance: f64,
+}
+
+/// Position of a point of a track in both geographic and schematic representations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackLocation {
+    pub geo: Point,
+    pub sch: Point,
+}
+
+#[derive(QueryableByName, Debug, Clone)]
+struct TrackLocationRow {
+    #[sql_type = "Double"]
+    geo_x: f64,
+    #[sql_type = "Double"]
+    geo_y: f64,
+    #[sql_type = "Double"]
+    sch_x: f64,
+    #[sql_type = "Double"]
+    sch_y: f64,
+}
+
+/// Find the track section closest to a geographic point (WGS84 longitude and latitude),
+/// searching the generated track section layer.
+/// The offset is computed from the track `length`, scaled by the projected point location on `geo`.
+pub fn project_point(
+    conn: &PgConnection,
+    infra_id: i32,
+    lon: f64,
+    lat: f64,
+) -> Result<TrackProjection, TrackProjectionError> {
+    let mut projections = sql_query(include_str!("sql/project_point_on_track.sql"))
+        .bind::<Integer, _>(infra_id)
+        .bind::<Double, _>(lon)
+        .bind::<Double, _>(lat)
+        .load::<TrackProjection>(conn)?;
+    projections
+        .pop()
+        .ok_or(TrackProjectionError::NoTrackSection)
+}
+
+/// Compute the coordinates of a position on a track section of the generated layer,
+/// the same way layers place point objects
+pub fn locate_on_track(
+    conn: &PgConnection,
+    infra_id: i32,
+    track: &str,
+    offset: f64,
+) -> Result<TrackLocation, TrackProjectionError> {
+    let mut rows = sql_query(include_str!("sql/locate_on_track.sql"))
+        .bind::<Integer, _>(infra_id)
+        .bind::<Text, _>(track)
+        .bind::<Double, _>(offset)
+        .load::<TrackLocationRow>(conn)?;
+    let row = rows
+        .pop()
+        .ok_or_else(|| TrackProjectionError::TrackNotFound(track.into()))?;
+    Ok(TrackLocation {
+        geo: Point::new(row.geo_x, row.geo_y),
+        sch: Point::new(row.sch_x, row.sch_y),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{locate_on_track, project_point, Point, TrackProjectionError};
+    use crate::layer::Layer;
+    use crate::models::infra::tests::test_transaction;
+    use crate::objects::operation::create::tests::create_track;
+    use crate::objects::{LineString, TrackSection};
+
+    fn create_straight_track(conn: &diesel::PgConnection, infra_id: i32) {
+        create_track(
+            conn,
+            infra_id,
+            TrackSection {
+                id: "track_projection".into(),
+                length: 1000.,
+                geo: LineString::LineString {
+                    coordinates: vec![[2., 48.], [2., 49.]],
+                },
+                sch: LineString::LineString {
+                    coordinates: vec![[0., 0.], [0., 10.]],
+                },
+                ..Default::default()
+            },
+        );
+        TrackSection::refresh(conn, infra_id, &Default::default()).unwrap();
+    }
+
+    fn assert_near(coordinates: [f64; 2], expected: [f64; 2]) {
+        assert!(
+            (coordinates[0] - expected[0]).abs() < 1e-6
+                && (coordinates[1] - expected[1]).abs() < 1e-6,
+            "{coordinates:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn project_and_locate() {
+        test_transaction(|conn, infra| {
+            create_straight_track(conn, infra.id);
+
+            let projection = project_point(conn, infra.id, 2.01, 48.25).unwrap();
+            assert_eq!(projection.track, "track_projection");
+            assert!((projection.offset - 250.).abs() < 1e-6);
+            assert!(projection.distance > 500. && projection.distance < 1000.);
+
+            let location = locate_on_track(conn, infra.id, "track_projection", 250.).unwrap();
+            let Point::Point { coordinates } = location.sch;
+            assert_near(coordinates, [0., 2.5]);
+
+            // Offsets are clamped to the track bounds
+            let location = locate_on_track(conn, infra.id, "track_projection", 2000.).unwrap();
+            let Point::Point { coordinates } = location.geo;
+            assert_near(coordinates, [2., 49.]);
+        });
+    }
+
+    #[test]
+    fn closest_track_in_meters() {
+        test_transaction(|conn, infra| {
+            // At 70°N, a degree of longitude is about a third of a degree of latitude
+            for (id, coordinates) in [
+                ("east", vec![[20.02, 69.9], [20.02, 70.1]]),
+                ("north", vec![[19.9, 70.01], [20.1, 70.01]]),
+            ] {
+                create_track(
+                    conn,
+                    infra.id,
+                    TrackSection {
+                        id: id.into(),
+                        length: 1000.,
+                        geo: LineString::LineString {
+                            coordinates: coordinates.clone(),
+                        },
+                        sch: LineString::LineString { coordinates },
+                        ..Default::default()
+                    },
+                );
+            }
+            TrackSection::refresh(conn, infra.id, &Default::default()).unwrap();
+
+            let projection = project_point(conn, infra.id, 20., 70.).unwrap();
+            assert_eq!(projection.track, "east");
+        });
+    }
+
+    #[test]
+    fn missing_track() {
+        test_transaction(|conn, infra| {
+            assert!(matches!(
+                project_point(conn, infra.id, 2., 48.),
+                Err(TrackProjectionError::NoTrackSection)
+            ));
+            assert!(matches!(
+                locate_on_track(conn, infra.id, "unknown", 0.),
+                Err(TrackProjectionError::TrackNotFound(_))
+            ));
+        });
+    }
+}
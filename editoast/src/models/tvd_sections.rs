@@ -0,0 +1,391 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use diesel::result::Error as DieselError;
+use diesel::sql_types::{Array, Double, Integer, Json, Text};
+use diesel::{sql_query, PgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use serde_json::to_value;
+
+use super::errors::graph::Graph;
+use crate::client::ChartosConfig;
+use crate::infra_cache::InfraCache;
+use crate::layer::invalidate_chartos_layer;
+use crate::objects::operation::OperationResult;
+use crate::objects::{Endpoint, OSRDObject, ObjectType, TrackEndpoint};
+
+/// A track vacancy detection section: an area of the infra bounded by detectors and buffer stops
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TvdSection {
+    pub id: String,
+    pub detectors: Vec<String>,
+    pub buffer_stops: Vec<String>,
+    pub track_ranges: Vec<TrackRange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TrackRange {
+    pub track: String,
+    pub begin: f64,
+    pub end: f64,
+}
+
+/// A part of a track section between two waypoints (or a track extremity)
+#[derive(Debug)]
+struct Segment<'a> {
+    track: &'a String,
+    begin: f64,
+    end: f64,
+    detectors: BTreeSet<&'a String>,
+    buffer_stops: BTreeSet<&'a String>,
+}
+
+/// Union find structure used to merge connected segments
+struct DisjointSet(Vec<usize>);
+
+impl DisjointSet {
+    fn new(size: usize) -> Self {
+        Self((0..size).collect())
+    }
+
+    fn find(&mut self, index: usize) -> usize {
+        let parent = self.0[index];
+        if parent == index {
+            return index;
+        }
+        let root = self.find(parent);
+        self.0[index] = root;
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.0[a.max(b)] = a.min(b);
+    }
+}
+
+/// Compute the TVD sections of an infra.
+/// Track sections are cut at each detector and buffer stop, then the resulting segments
+/// that are connected (by a link or a switch) are merged into the same section.
+pub fn compute_tvd_sections(infra_cache: &InfraCache, graph: &Graph) -> Vec<TvdSection> {
+    // Retrieve waypoints of each track
+    let mut waypoints: HashMap<&String, Vec<(f64, &String, bool)>> = HashMap::new();
+    for detector in infra_cache.detectors().values() {
+        let detector = detector.unwrap_detector();
+        waypoints.entry(&detector.track).or_default().push((
+            detector.position,
+            &detector.obj_id,
+            true,
+        ));
+    }
+    for buffer_stop in infra_cache.buffer_stops().values() {
+        let buffer_stop = buffer_stop.unwrap_buffer_stop();
+        waypoints.entry(&buffer_stop.track).or_default().push((
+            buffer_stop.position,
+            &buffer_stop.obj_id,
+            false,
+        ));
+    }
+
+    // Cut tracks into segments
+    let mut track_ids: Vec<_> = infra_cache.track_sections().keys().collect();
+    track_ids.sort();
+    let mut segments: Vec<Segment> = vec![];
+    let mut track_bounds: HashMap<&String, (usize, usize)> = HashMap::new();
+    for track_id in track_ids {
+        let length = infra_cache
+            .track_sections()
+            .get(track_id)
+            .unwrap()
+            .unwrap_track_section()
+            .length;
+        let mut track_waypoints = waypoints.remove(track_id).unwrap_or_default();
+        track_waypoints.sort_by(|(a, a_id, _), (b, b_id, _)| a.total_cmp(b).then(a_id.cmp(b_id)));
+
+        let first_segment = segments.len();
+        let new_segment = |begin: f64| Segment {
+            track: track_id,
+            begin,
+            end: length,
+            detectors: BTreeSet::new(),
+            buffer_stops: BTreeSet::new(),
+        };
+        segments.push(new_segment(0.));
+        for (position, waypoint_id, is_detector) in track_waypoints {
+            let position = position.clamp(0., length);
+            let previous = segments.last_mut().unwrap();
+            previous.end = position;
+            let mut next = new_segment(position);
+            for segment in [previous, &mut next] {
+                if is_detector {
+                    segment.detectors.insert(waypoint_id);
+                } else {
+                    segment.buffer_stops.insert(waypoint_id);
+                }
+            }
+            segments.push(next);
+        }
+        track_bounds.insert(track_id, (first_segment, segments.len() - 1));
+    }
+
+    // Merge connected segments
+    let mut disjoint_set = DisjointSet::new(segments.len());
+    let segment_at = |track_endpoint: &TrackEndpoint| {
+        track_bounds
+            .get(&track_endpoint.track.obj_id)
+            .map(|(first, last)| match track_endpoint.endpoint {
+                Endpoint::Begin => *first,
+                Endpoint::End => *last,
+            })
+    };
+    for track_cache in infra_cache.track_sections().values() {
+        let track_cache = track_cache.unwrap_track_section();
+        for track_endpoint in [track_cache.get_begin(), track_cache.get_end()] {
+            let src = segment_at(&track_endpoint).unwrap();
+            for neighbour in graph.get_neighbours(&track_endpoint).into_iter().flatten() {
+                if let Some(dst) = segment_at(neighbour) {
+                    disjoint_set.union(src, dst);
+                }
+            }
+        }
+    }
+
+    // Group segments by section, ordered by their first segment
+    let mut sections: Vec<Vec<&Segment>> = vec![];
+    let mut section_indexes = HashMap::new();
+    for (index, segment) in segments.iter().enumerate() {
+        let root = disjoint_set.find(index);
+        let section_index = *section_indexes.entry(root).or_insert_with(|| {
+            sections.push(vec![]);
+            sections.len() - 1
+        });
+        sections[section_index].push(segment);
+    }
+
+    let mut used_ids = HashSet::new();
+    sections
+        .into_iter()
+        .map(|section_segments| {
+            let mut detectors = BTreeSet::new();
+            let mut buffer_stops = BTreeSet::new();
+            let mut track_ranges = vec![];
+            for segment in section_segments.iter() {
+                detectors.extend(segment.detectors.iter().cloned());
+                buffer_stops.extend(segment.buffer_stops.iter().cloned());
+                if segment.begin < segment.end {
+                    track_ranges.push(TrackRange {
+                        track: segment.track.clone(),
+                        begin: segment.begin,
+                        end: segment.end,
+                    });
+                }
+            }
+            let detectors: Vec<String> = detectors.into_iter().cloned().collect();
+            let buffer_stops: Vec<String> = buffer_stops.into_iter().cloned().collect();
+            let id = generate_section_id(
+                &detectors,
+                &buffer_stops,
+                section_segments[0].track,
+                &mut used_ids,
+            );
+            TvdSection {
+                id,
+                detectors,
+                buffer_stops,
+                track_ranges,
+            }
+        })
+        .collect()
+}
+
+/// Build a TVD section id from its waypoints, adding a suffix if it's already used
+fn generate_section_id(
+    detectors: &[String],
+    buffer_stops: &[String],
+    first_track: &str,
+    used_ids: &mut HashSet<String>,
+) -> String {
+    let base_id = if detectors.is_empty() && buffer_stops.is_empty() {
+        format!("tvd.{}", first_track)
+    } else {
+        let mut waypoints: Vec<_> = detectors.iter().chain(buffer_stops.iter()).collect();
+        waypoints.sort();
+        format!(
+            "tvd.{}",
+            waypoints
+                .into_iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join("-")
+        )
+    };
+    let mut id = base_id.clone();
+    let mut index = 1;
+    while !used_ids.insert(id.clone()) {
+        id = format!("{}.{}", base_id, index);
+        index += 1;
+    }
+    id
+}
+
+/// Whether operations change the TVD sections, which only depend on the track graph
+/// and on the detectors and buffer stops bounding them
+pub fn is_affected_by(operations: &[OperationResult]) -> bool {
+    operations.iter().any(|op| {
+        let obj_type = match op {
+            OperationResult::Create(railjson) | OperationResult::Update(railjson) => {
+                railjson.get_type()
+            }
+            OperationResult::Delete(obj_ref) => obj_ref.obj_type,
+        };
+        matches!(
+            obj_type,
+            ObjectType::TrackSection
+                | ObjectType::TrackSectionLink
+                | ObjectType::Switch
+                | ObjectType::SwitchType
+                | ObjectType::Detector
+                | ObjectType::BufferStop
+        )
+    })
+}
+
+/// Clear and regenerate the TVD section layer of an infra
+pub fn refresh_layer(
+    conn: &PgConnection,
+    infra_id: i32,
+    infra_cache: &InfraCache,
+    chartos_config: &ChartosConfig,
+) -> Result<(), DieselError> {
+    sql_query("DELETE FROM osrd_infra_tvdsectionlayer WHERE infra_id = $1")
+        .bind::<Integer, _>(infra_id)
+        .execute(conn)?;
+
+    let graph = Graph::load(infra_cache);
+    let tvd_sections = compute_tvd_sections(infra_cache, &graph);
+
+    let mut section_ids = vec![];
+    let mut data = vec![];
+    let mut range_section_ids = vec![];
+    let mut range_tracks = vec![];
+    let mut range_begins = vec![];
+    let mut range_ends = vec![];
+    for tvd_section in tvd_sections {
+        for range in tvd_section.track_ranges.iter() {
+            range_section_ids.push(tvd_section.id.clone());
+            range_tracks.push(range.track.clone());
+            range_begins.push(range.begin);
+            range_ends.push(range.end);
+        }
+        section_ids.push(tvd_section.id.clone());
+        data.push(to_value(tvd_section).unwrap());
+    }
+
+    sql_query(include_str!("../layer/sql/insert_tvd_section_layer.sql"))
+        .bind::<Integer, _>(infra_id)
+        .bind::<Array<Text>, _>(&section_ids)
+        .bind::<Array<Json>, _>(&data)
+        .bind::<Array<Text>, _>(&range_section_ids)
+        .bind::<Array<Text>, _>(&range_tracks)
+        .bind::<Array<Double>, _>(&range_begins)
+        .bind::<Array<Double>, _>(&range_ends)
+        .execute(conn)?;
+
+    invalidate_chartos_layer(infra_id, "tvd_sections", chartos_config);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compute_tvd_sections, is_affected_by, TrackRange, TvdSection};
+    use crate::infra_cache::tests::{create_detector_cache, create_small_infra_cache};
+    use crate::models::errors::graph::Graph;
+    use crate::objects::operation::OperationResult;
+    use crate::objects::{ObjectRef, ObjectType};
+
+    fn track_range(track: &str, begin: f64, end: f64) -> TrackRange {
+        TrackRange {
+            track: track.into(),
+            begin,
+            end,
+        }
+    }
+
+    #[test]
+    fn small_infra_tvd_sections() {
+        let infra_cache = create_small_infra_cache();
+        let graph = Graph::load(&infra_cache);
+        let tvd_sections = compute_tvd_sections(&infra_cache, &graph);
+        assert_eq!(
+            tvd_sections,
+            vec![
+                TvdSection {
+                    id: "tvd.BF1".into(),
+                    detectors: vec![],
+                    buffer_stops: vec!["BF1".into()],
+                    track_ranges: vec![track_range("A", 0., 20.)],
+                },
+                TvdSection {
+                    id: "tvd.BF1-D1".into(),
+                    detectors: vec!["D1".into()],
+                    buffer_stops: vec!["BF1".into()],
+                    track_ranges: vec![track_range("A", 20., 500.), track_range("B", 0., 250.)],
+                },
+                TvdSection {
+                    id: "tvd.BF2-BF3-D1".into(),
+                    detectors: vec!["D1".into()],
+                    buffer_stops: vec!["BF2".into(), "BF3".into()],
+                    track_ranges: vec![
+                        track_range("B", 250., 500.),
+                        track_range("C", 0., 480.),
+                        track_range("D", 0., 480.),
+                    ],
+                },
+                TvdSection {
+                    id: "tvd.BF2".into(),
+                    detectors: vec![],
+                    buffer_stops: vec!["BF2".into()],
+                    track_ranges: vec![track_range("C", 480., 500.)],
+                },
+                TvdSection {
+                    id: "tvd.BF3".into(),
+                    detectors: vec![],
+                    buffer_stops: vec!["BF3".into()],
+                    track_ranges: vec![track_range("D", 480., 500.)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn detector_splits_branch() {
+        let mut infra_cache = create_small_infra_cache();
+        infra_cache.apply_delete(&ObjectRef::new(ObjectType::BufferStop, "BF1"));
+        infra_cache.add(create_detector_cache("D2", "C", 100.));
+        let graph = Graph::load(&infra_cache);
+        let tvd_sections = compute_tvd_sections(&infra_cache, &graph);
+        let section = tvd_sections
+            .iter()
+            .find(|section| section.id == "tvd.BF3-D1-D2")
+            .unwrap();
+        assert_eq!(
+            section.track_ranges,
+            vec![
+                track_range("B", 250., 500.),
+                track_range("C", 0., 100.),
+                track_range("D", 0., 480.),
+            ]
+        );
+        assert!(tvd_sections.iter().any(|section| section.id == "tvd.D1"));
+    }
+
+    #[test]
+    fn affecting_operations() {
+        let delete = |obj_type| vec![OperationResult::Delete(ObjectRef::new(obj_type, "id"))];
+        assert!(is_affected_by(&delete(ObjectType::Detector)));
+        assert!(is_affected_by(&delete(ObjectType::TrackSectionLink)));
+        assert!(!is_affected_by(&delete(ObjectType::Signal)));
+        assert!(!is_affected_by(&delete(ObjectType::Route)));
+    }
+}
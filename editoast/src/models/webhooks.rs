@@ -0,0 +1,401 @@
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use colored::Colorize;
+use diesel::result::Error as DieselError;
+use diesel::sql_types::{BigInt, Integer};
+use diesel::{
+    delete, insert_into, sql_query, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+};
+use hmac::{Hmac, Mac, NewMac};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rocket::http::Status;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::error::ApiError;
+use crate::objects::operation::OperationResult;
+use crate::objects::{OSRDObject, ObjectRef};
+use crate::schema::osrd_infra_infrawebhook;
+use crate::schema::osrd_infra_infrawebhook::dsl;
+
+/// Header holding the signature of webhook payloads
+pub const SIGNATURE_HEADER: &str = "X-Editoast-Signature";
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("Webhook '{0}' could not be found")]
+    NotFound(i32),
+    #[error("Webhook url '{0}' must start with 'http://' or 'https://'")]
+    InvalidUrl(String),
+    #[error(transparent)]
+    Database(#[from] DieselError),
+}
+
+impl ApiError for WebhookError {
+    fn get_status(&self) -> Status {
+        match self {
+            WebhookError::NotFound(_) => Status::NotFound,
+            WebhookError::InvalidUrl(_) => Status::BadRequest,
+            WebhookError::Database(_) => Status::InternalServerError,
+        }
+    }
+
+    fn get_type(&self) -> &'static str {
+        match self {
+            WebhookError::NotFound(_) => "editoast:webhooks:NotFound",
+            WebhookError::InvalidUrl(_) => "editoast:webhooks:InvalidUrl",
+            WebhookError::Database(_) => "editoast:webhooks:Database",
+        }
+    }
+
+    fn extra(&self) -> Option<Map<String, Value>> {
+        match self {
+            WebhookError::NotFound(webhook) => json!({ "webhook": webhook }),
+            WebhookError::InvalidUrl(url) => json!({ "url": url }),
+            WebhookError::Database(_) => return None,
+        }
+        .as_object()
+        .cloned()
+    }
+}
+
+/// An url notified of the changes of an infra.
+/// The secret used to sign payloads is only given at creation.
+#[derive(Debug, Clone, Queryable, Serialize)]
+pub struct Webhook {
+    pub id: i32,
+    pub infra_id: i32,
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateWebhook {
+    pub url: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "osrd_infra_infrawebhook"]
+struct NewWebhook<'a> {
+    infra_id: i32,
+    url: &'a str,
+    secret: &'a str,
+}
+
+impl Webhook {
+    pub fn list(conn: &PgConnection, infra_id: i32) -> Result<Vec<Webhook>, WebhookError> {
+        Ok(dsl::osrd_infra_infrawebhook
+            .filter(dsl::infra_id.eq(infra_id))
+            .order(dsl::id)
+            .load(conn)?)
+    }
+
+    /// Register a webhook with a random secret
+    pub fn create(conn: &PgConnection, infra_id: i32, url: &str) -> Result<Webhook, WebhookError> {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(WebhookError::InvalidUrl(url.into()));
+        }
+        let secret: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        Ok(insert_into(dsl::osrd_infra_infrawebhook)
+            .values(NewWebhook {
+                infra_id,
+                url,
+                secret: &secret,
+            })
+            .get_result(conn)?)
+    }
+
+    pub fn delete(conn: &PgConnection, infra_id: i32, webhook_id: i32) -> Result<(), WebhookError> {
+        let deleted = delete(
+            dsl::osrd_infra_infrawebhook
+                .filter(dsl::id.eq(webhook_id))
+                .filter(dsl::infra_id.eq(infra_id)),
+        )
+        .execute(conn)?;
+        match deleted {
+            0 => Err(WebhookError::NotFound(webhook_id)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// What triggered a webhook notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    Edit,
+    Refresh,
+}
+
+/// Summary of an infra change sent to webhooks
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPayload {
+    pub infra_id: i32,
+    pub event: WebhookEvent,
+    pub version: String,
+    pub changed: Vec<ObjectRef>,
+    /// Difference between the number of errors of the infra after and before the change
+    pub error_count_delta: i64,
+}
+
+impl WebhookPayload {
+    pub fn new(
+        infra_id: i32,
+        event: WebhookEvent,
+        version: String,
+        operations: &[OperationResult],
+        error_count_delta: i64,
+    ) -> Self {
+        let changed = operations
+            .iter()
+            .map(|op| match op {
+                OperationResult::Create(railjson) | OperationResult::Update(railjson) => {
+                    railjson.get_ref()
+                }
+                OperationResult::Delete(obj_ref) => obj_ref.clone(),
+            })
+            .collect();
+        Self {
+            infra_id,
+            event,
+            version,
+            changed,
+            error_count_delta,
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct ErrorCount {
+    #[sql_type = "BigInt"]
+    count: i64,
+}
+
+/// Count the errors and warnings of an infra
+pub fn count_infra_errors(conn: &PgConnection, infra_id: i32) -> Result<i64, DieselError> {
+    let count =
+        sql_query("SELECT COUNT(*) AS count FROM osrd_infra_errorlayer WHERE infra_id = $1")
+            .bind::<Integer, _>(infra_id)
+            .get_result::<ErrorCount>(conn)?;
+    Ok(count.count)
+}
+
+/// Sign a payload with a webhook secret, as `sha256=<hex encoded HMAC-SHA256>`
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC accepts any key");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", hex)
+}
+
+/// Post a signed payload to a webhook
+pub fn deliver(
+    client: &reqwest::blocking::Client,
+    webhook: &Webhook,
+    payload: &WebhookPayload,
+) -> Result<(), reqwest::Error> {
+    let body = serde_json::to_vec(payload).unwrap();
+    client
+        .post(&webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, sign(&webhook.secret, &body))
+        .body(body)
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Maximum number of notifications waiting to be delivered, newer ones are dropped beyond it
+const NOTIFICATION_QUEUE_SIZE: usize = 256;
+
+/// Timeout of a webhook delivery, not to hold the worker on unreachable receivers
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Deliver webhook notifications from a single background worker, failures are only reported
+pub struct WebhookNotifier {
+    sender: Mutex<SyncSender<(Vec<Webhook>, WebhookPayload)>>,
+}
+
+impl Default for WebhookNotifier {
+    fn default() -> Self {
+        Self::new(NOTIFICATION_QUEUE_SIZE)
+    }
+}
+
+impl WebhookNotifier {
+    /// Spawn the delivery worker, which stops when the notifier is dropped
+    pub fn new(queue_size: usize) -> Self {
+        let (sender, receiver) = sync_channel::<(Vec<Webhook>, WebhookPayload)>(queue_size);
+        thread::spawn(move || {
+            let client = reqwest::blocking::Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .build()
+                .expect("Failed to build the webhook client");
+            for (webhooks, payload) in receiver {
+                for webhook in webhooks {
+                    if let Err(err) = deliver(&client, &webhook, &payload) {
+                        eprintln!(
+                            "{}",
+                            format!("Failed to notify webhook {}: {}", webhook.url, err).red()
+                        );
+                    }
+                }
+            }
+        });
+        Self {
+            sender: Mutex::new(sender),
+        }
+    }
+
+    /// Queue the notification of the webhooks of an infra.
+    /// Notifications are sent once the change is committed, so they must not fail the request.
+    pub fn notify(&self, conn: &PgConnection, payload: WebhookPayload) {
+        match Webhook::list(conn, payload.infra_id) {
+            Ok(webhooks) => self.enqueue(webhooks, payload),
+            Err(err) => eprintln!(
+                "{}",
+                format!(
+                    "Failed to list the webhooks of infra {}: {}",
+                    payload.infra_id, err
+                )
+                .red()
+            ),
+        }
+    }
+
+    fn enqueue(&self, webhooks: Vec<Webhook>, payload: WebhookPayload) {
+        if webhooks.is_empty() {
+            return;
+        }
+        let infra_id = payload.infra_id;
+        if let Err(TrySendError::Full(_)) =
+            self.sender.lock().unwrap().try_send((webhooks, payload))
+        {
+            eprintln!(
+                "{}",
+                format!("Too many pending webhook notifications, dropping one of infra {infra_id}")
+                    .red()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        deliver, sign, Webhook, WebhookEvent, WebhookNotifier, WebhookPayload, SIGNATURE_HEADER,
+    };
+    use crate::objects::operation::OperationResult;
+    use crate::objects::{ObjectRef, ObjectType};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn signature() {
+        // Reference value computed with `openssl dgst -sha256 -hmac secret`
+        assert_eq!(
+            sign("secret", b"payload"),
+            "sha256=b82fcb791acec57859b989b430a826488ce2e479fdf92326bd0a2e8375a42ba4"
+        );
+    }
+
+    /// Accept a single HTTP request and return its headers and body
+    fn receive_one(listener: TcpListener) -> thread::JoinHandle<(Vec<String>, String)> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut headers = vec![];
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_string();
+                if line.is_empty() {
+                    break;
+                }
+                headers.push(line);
+            }
+            let length: usize = headers
+                .iter()
+                .find_map(|h| {
+                    h.to_lowercase()
+                        .strip_prefix("content-length: ")
+                        .map(|l| l.parse().unwrap())
+                })
+                .unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            (headers, String::from_utf8(body).unwrap())
+        })
+    }
+
+    #[test]
+    fn deliver_signed_payload() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let receiver = receive_one(listener);
+
+        let webhook = Webhook {
+            id: 1,
+            infra_id: 2,
+            url,
+            secret: "secret".into(),
+        };
+        let operations = vec![OperationResult::Delete(ObjectRef::new(
+            ObjectType::Signal,
+            "S1",
+        ))];
+        let payload = WebhookPayload::new(2, WebhookEvent::Edit, "3".into(), &operations, -1);
+        deliver(&reqwest::blocking::Client::new(), &webhook, &payload).unwrap();
+
+        let (headers, body) = receiver.join().unwrap();
+        let body_json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body_json["event"], "edit");
+        assert_eq!(body_json["version"], "3");
+        assert_eq!(body_json["error_count_delta"], -1);
+        assert_eq!(body_json["changed"][0]["id"], "S1");
+        let signature = format!(
+            "{}: {}",
+            SIGNATURE_HEADER.to_lowercase(),
+            sign("secret", body.as_bytes())
+        );
+        assert!(headers.iter().any(|h| h.to_lowercase() == signature));
+    }
+
+    #[test]
+    fn notifier_delivers_from_worker() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let receiver = receive_one(listener);
+
+        let webhook = Webhook {
+            id: 1,
+            infra_id: 2,
+            url,
+            secret: "secret".into(),
+        };
+        let payload = WebhookPayload::new(2, WebhookEvent::Refresh, "3".into(), &[], 0);
+        WebhookNotifier::new(1).enqueue(vec![webhook], payload);
+
+        let (_, body) = receiver.join().unwrap();
+        let body_json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body_json["event"], "refresh");
+    }
+}
@@ -8,7 +8,6 @@ use super::OSRDObject;
 use super::ObjectRef;
 use super::ObjectType;
 use derivative::Derivative;
-use diesel::sql_types::{Double, Text};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Derivative, Clone, Deserialize, Serialize)]
@@ -55,16 +54,13 @@ impl Layer for Detector {
     }
 }
 
-#[derive(QueryableByName, Debug, Clone, Derivative)]
+#[derive(Debug, Clone, Derivative, Deserialize, Serialize)]
 #[derivative(Hash, PartialEq)]
 pub struct DetectorCache {
-    #[sql_type = "Text"]
     pub obj_id: String,
     #[derivative(Hash = "ignore", PartialEq = "ignore")]
-    #[sql_type = "Text"]
     pub track: String,
     #[derivative(Hash = "ignore", PartialEq = "ignore")]
-    #[sql_type = "Double"]
     pub position: f64,
 }
 
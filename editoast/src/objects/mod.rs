@@ -80,6 +80,23 @@ impl ObjectType {
             ObjectType::Catenary => "osrd_infra_catenarymodel",
         }
     }
+
+    /// Layer table holding the geometry of the objects, if any
+    pub fn get_layer_table(&self) -> Option<&str> {
+        match *self {
+            ObjectType::TrackSection => Some("osrd_infra_tracksectionlayer"),
+            ObjectType::Signal => Some("osrd_infra_signallayer"),
+            ObjectType::SpeedSection => Some("osrd_infra_speedsectionlayer"),
+            ObjectType::Detector => Some("osrd_infra_detectorlayer"),
+            ObjectType::TrackSectionLink => Some("osrd_infra_tracksectionlinklayer"),
+            ObjectType::Switch => Some("osrd_infra_switchlayer"),
+            ObjectType::SwitchType => None,
+            ObjectType::BufferStop => Some("osrd_infra_bufferstoplayer"),
+            ObjectType::Route => Some("osrd_infra_routelayer"),
+            ObjectType::OperationalPoint => Some("osrd_infra_operationalpointlayer"),
+            ObjectType::Catenary => Some("osrd_infra_catenarylayer"),
+        }
+    }
 }
 
 #[derive(Deserialize, Derivative, Serialize, Clone, Debug, PartialEq, Eq, Hash)]
@@ -167,7 +184,7 @@ pub enum ApplicableDirections {
     Both,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Endpoint {
     #[serde(rename = "BEGIN")]
     Begin,
@@ -7,7 +7,7 @@ use diesel::RunQueryDsl;
 use diesel::{sql_query, PgConnection};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 /// A delete operation. Contains same information as a object ref but has another serialization.
 pub struct DeleteOperation {
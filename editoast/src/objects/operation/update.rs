@@ -8,12 +8,12 @@ use json_patch::Patch;
 use serde::{Deserialize, Serialize};
 use serde_json::{from_value, Value};
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct UpdateOperation {
     pub obj_id: String,
     pub obj_type: ObjectType,
-    railjson_patch: Patch,
+    pub railjson_patch: Patch,
 }
 
 impl UpdateOperation {
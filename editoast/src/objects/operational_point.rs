@@ -65,23 +65,45 @@ impl Layer for OperationalPoint {
     }
 }
 
-#[derive(Debug, Clone, Derivative)]
+#[derive(Debug, Clone, Derivative, Deserialize, Serialize)]
 #[derivative(Hash, PartialEq)]
 pub struct OperationalPointCache {
     pub obj_id: String,
     #[derivative(Hash = "ignore", PartialEq = "ignore")]
     pub parts: Vec<OperationalPointPart>,
+    #[derivative(Hash = "ignore", PartialEq = "ignore")]
+    pub uic: i64,
+    #[derivative(Hash = "ignore", PartialEq = "ignore")]
+    pub trigram: String,
+    #[derivative(Hash = "ignore", PartialEq = "ignore")]
+    pub name: String,
+    #[derivative(Hash = "ignore", PartialEq = "ignore")]
+    pub ch: String,
 }
 
 impl OperationalPointCache {
     pub fn new(obj_id: String, parts: Vec<OperationalPointPart>) -> Self {
-        Self { obj_id, parts }
+        Self {
+            obj_id,
+            parts,
+            uic: 0,
+            trigram: String::new(),
+            name: String::new(),
+            ch: String::new(),
+        }
     }
 }
 
 impl From<OperationalPoint> for OperationalPointCache {
     fn from(op: OperationalPoint) -> Self {
-        Self::new(op.id, op.parts)
+        Self {
+            obj_id: op.id,
+            parts: op.parts,
+            uic: op.uic,
+            trigram: op.trigram,
+            name: op.name,
+            ch: op.ch,
+        }
     }
 }
 
@@ -56,7 +56,7 @@ impl Layer for Switch {
     }
 }
 
-#[derive(Debug, Clone, Derivative)]
+#[derive(Debug, Clone, Derivative, Deserialize, Serialize)]
 #[derivative(Hash, PartialEq)]
 pub struct SwitchCache {
     pub obj_id: String,
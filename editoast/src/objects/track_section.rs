@@ -153,7 +153,7 @@ impl Layer for TrackSection {
         infra: i32,
         operations: &Vec<super::operation::OperationResult>,
         _: &crate::infra_cache::InfraCache,
-        invalid_zone: &crate::layer::InvalidationZone,
+        invalid_zones: &crate::layer::LayerInvalidationZones,
         chartos_config: &crate::client::ChartosConfig,
     ) -> Result<(), diesel::result::Error> {
         let mut update_obj_ids = HashSet::new();
@@ -181,18 +181,20 @@ impl Layer for TrackSection {
         Self::delete_list(conn, infra, delete_obj_ids)?;
         Self::insert_update_list(conn, infra, update_obj_ids)?;
 
-        crate::layer::invalidate_bbox_chartos_layer(
-            infra,
-            Self::layer_name(),
-            invalid_zone,
-            chartos_config,
-        );
+        for zone in invalid_zones.get(Self::get_obj_type()) {
+            crate::layer::invalidate_bbox_chartos_layer(
+                infra,
+                Self::layer_name(),
+                zone,
+                chartos_config,
+            );
+        }
 
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, Derivative)]
+#[derive(Debug, Clone, Derivative, Deserialize, Serialize)]
 #[derivative(Hash, PartialEq)]
 pub struct TrackSectionCache {
     pub obj_id: String,
@@ -2,9 +2,13 @@ table! {
     osrd_infra_infra {
         id -> Integer,
         name -> Text,
+        owner -> Uuid,
         version -> Text,
         generated_version -> Nullable<Text>,
         locked -> Bool,
+        locked_by -> Nullable<Text>,
+        lock_reason -> Nullable<Text>,
+        locked_until -> Nullable<BigInt>,
     }
 }
 
@@ -80,6 +84,14 @@ table! {
     }
 }
 
+table! {
+    osrd_infra_tvdsectionlayer {
+        id -> Integer,
+        obj_id -> Text,
+        infra_id -> Integer,
+    }
+}
+
 table! {
     osrd_infra_catenarylayer {
         id -> Integer,
@@ -87,3 +99,34 @@ table! {
         infra_id -> Integer,
     }
 }
+
+table! {
+    osrd_infra_infrawebhook {
+        id -> Integer,
+        infra_id -> Integer,
+        url -> Text,
+        secret -> Text,
+    }
+}
+
+table! {
+    osrd_infra_infrapermission {
+        id -> Integer,
+        infra_id -> Integer,
+        user -> Uuid,
+        role -> Text,
+    }
+}
+
+table! {
+    osrd_infra_infraauditentry {
+        id -> Integer,
+        infra_id -> Integer,
+        user -> Uuid,
+        timestamp -> BigInt,
+        action -> Text,
+        version_before -> Nullable<Text>,
+        version_after -> Nullable<Text>,
+        summary -> Jsonb,
+    }
+}
@@ -0,0 +1,39 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::models::audit::get_paginated_audit;
+use crate::models::DBConnection;
+use crate::views::params::UuidParam;
+use rocket::{routes, Route};
+use rocket_contrib::json::JsonValue;
+
+pub fn routes() -> Vec<Route> {
+    routes![list]
+}
+
+/// Return the audit log of an infra, latest changes first.
+/// Entries can be filtered by user and by action. The log of a deleted infra remains
+/// readable by its owner.
+#[get("/<infra>/audit?<page>&<page_size>&<user_id>&<action>")]
+fn list(
+    user: User,
+    infra: i32,
+    page: Option<i64>,
+    page_size: Option<i64>,
+    user_id: Option<UuidParam>,
+    action: Option<String>,
+    conn: DBConnection,
+) -> ApiResult<JsonValue> {
+    user.check_audit_access(&conn, infra)?;
+    let page = page.unwrap_or_default().max(1);
+    let per_page = page_size.unwrap_or(25).max(10);
+    let user_id = user_id.map(|user_id| user_id.0);
+    let (entries, count) = get_paginated_audit(&conn, infra, page, per_page, user_id, action)?;
+    let previous = if page == 1 { None } else { Some(page - 1) };
+    let max_page = (count as f64 / per_page as f64).ceil() as i64;
+    let next = if page >= max_page {
+        None
+    } else {
+        Some(page + 1)
+    };
+    Ok(json!({ "count": count, "previous": previous, "next": next, "results": entries }))
+}
@@ -0,0 +1,43 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::infra_cache::{CacheDiff, InfraCache, InfraCacheError};
+use crate::infra_caches::InfraCaches;
+use crate::models::permissions::Role;
+use crate::models::DBConnection;
+use rocket::{routes, Route, State};
+use rocket_contrib::json::Json;
+
+pub fn routes() -> Vec<Route> {
+    routes![reload, diff]
+}
+
+/// Reload the cache of an infra from the database.
+/// Return the differences the previous cache had with the database.
+#[post("/<infra>/cache/reload")]
+fn reload(
+    user: User,
+    infra: i32,
+    conn: DBConnection,
+    infra_caches: State<InfraCaches>,
+) -> ApiResult<Json<CacheDiff>> {
+    user.check_role(&conn, infra, Role::Admin)?;
+    Ok(Json(infra_caches.reload(&conn, infra)?))
+}
+
+/// Compare the cache of an infra with the database, without changing it.
+/// A cache that isn't loaded is reported as such rather than loaded for the comparison.
+#[get("/<infra>/cache/diff")]
+fn diff(
+    user: User,
+    infra: i32,
+    conn: DBConnection,
+    infra_caches: State<InfraCaches>,
+) -> ApiResult<Json<CacheDiff>> {
+    user.check_role(&conn, infra, Role::Admin)?;
+    // Keep the cache locked for it not to be edited while loading the reference
+    let infra_cache = infra_caches
+        .get_loaded(infra)
+        .ok_or(InfraCacheError::NotLoaded(infra))?;
+    let reference = InfraCache::load(&conn, infra)?;
+    Ok(Json(infra_cache.diff(&reference)))
+}
@@ -0,0 +1,31 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::events::{EventStream, InfraEventBus};
+use crate::models::permissions::Role;
+use crate::models::{DBConnection, Infra};
+use rocket::http::ContentType;
+use rocket::response::{Content, Stream};
+use rocket::{routes, Route, State};
+
+pub fn routes() -> Vec<Route> {
+    routes![events]
+}
+
+/// Stream the events of an infra (applied operations, version bumps, lock changes and
+/// errors regenerations) as Server-Sent Events.
+/// Each open stream holds a server worker thread, so their number is capped: beyond it a
+/// 503 is returned.
+#[get("/<infra>/events")]
+fn events(
+    user: User,
+    infra: i32,
+    conn: DBConnection,
+    events: State<InfraEventBus>,
+) -> ApiResult<Content<Stream<EventStream>>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    let infra = Infra::retrieve(&conn, infra)?;
+    // Release the database connection for the lifetime of the stream
+    drop(conn);
+    let stream = Stream::chunked(events.subscribe(infra.id)?, 4096);
+    Ok(Content(ContentType::new("text", "event-stream"), stream))
+}
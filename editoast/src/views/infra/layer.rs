@@ -0,0 +1,72 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::layer::{get_layer_definition, parse_bbox, LayerView};
+use crate::models::permissions::Role;
+use crate::models::DBConnection;
+use crate::views::params::List;
+use rocket::http::{ContentType, RawStr};
+use rocket::request::FromParam;
+use rocket::response::Content;
+use rocket::{routes, Route};
+
+pub fn routes() -> Vec<Route> {
+    routes![export_geojson]
+}
+
+/// Layer name given with the `.geojson` extension
+#[derive(Debug, PartialEq, Eq)]
+pub struct GeoJsonLayerName(String);
+
+impl<'r> FromParam<'r> for GeoJsonLayerName {
+    type Error = &'r RawStr;
+
+    fn from_param(param: &'r RawStr) -> Result<Self, Self::Error> {
+        match param.strip_suffix(".geojson") {
+            Some(name) if !name.is_empty() => Ok(GeoJsonLayerName(name.into())),
+            _ => Err(param),
+        }
+    }
+}
+
+/// Export a layer as a GeoJSON feature collection, optionally restricted to a WGS84 bounding box
+#[get("/<infra>/layers/<layer>?<view>&<bbox>")]
+fn export_geojson(
+    user: User,
+    infra: i32,
+    layer: GeoJsonLayerName,
+    view: Option<String>,
+    bbox: List<f64>,
+    conn: DBConnection,
+) -> ApiResult<Content<String>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    let layer = get_layer_definition(&layer.0)?;
+    let view = LayerView::parse(view.as_deref().unwrap_or("geo"))?;
+    let bbox = bbox.0?;
+    let bbox = if bbox.is_empty() {
+        None
+    } else {
+        Some(parse_bbox(&bbox)?)
+    };
+    let geojson = layer.get_geojson(&conn, infra, view, bbox.as_ref())?;
+    Ok(Content(
+        ContentType::new("application", "geo+json"),
+        geojson,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GeoJsonLayerName;
+    use rocket::http::RawStr;
+    use rocket::request::FromParam;
+
+    #[test]
+    fn parse_layer_name() {
+        assert_eq!(
+            GeoJsonLayerName::from_param(RawStr::from_str("signals.geojson")),
+            Ok(GeoJsonLayerName("signals".into()))
+        );
+        assert!(GeoJsonLayerName::from_param(RawStr::from_str("signals")).is_err());
+        assert!(GeoJsonLayerName::from_param(RawStr::from_str(".geojson")).is_err());
+    }
+}
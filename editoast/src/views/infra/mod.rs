@@ -0,0 +1,1094 @@
+mod audit;
+mod cache;
+mod events;
+mod layer;
+mod object_search;
+mod operational_point;
+mod pathfinding;
+mod permissions;
+mod route;
+mod schematic;
+mod track_geometry;
+mod track_projection;
+mod tvd_section;
+mod webhooks;
+
+use super::params::List;
+use crate::auth::User;
+use crate::client::ChartosConfig;
+use crate::error::{ApiError, ApiResult, EditoastError, InfraLockedError};
+use crate::events::{InfraEvent, InfraEventBus};
+use crate::generate;
+use crate::infra_cache::{InfraCache, ObjectCache};
+use crate::infra_caches::InfraCaches;
+use crate::layer::LayerInvalidationZones;
+use crate::models::audit::{operations_summary, record_audit, AuditAction};
+use crate::models::errors::fix::{get_selected_fixes, ErrorFixSelector};
+use crate::models::errors::generate_errors;
+use crate::models::infra_errors::get_paginated_infra_errors;
+use crate::models::permissions::Role;
+use crate::models::tvd_sections;
+use crate::models::webhooks::{count_infra_errors, WebhookEvent, WebhookNotifier, WebhookPayload};
+use crate::models::{CreateInfra, DBConnection, Infra, LockInfra, UnlockInfra};
+use crate::objects::operation::{Operation, OperationResult};
+use crate::objects::SwitchType;
+use diesel::result::Error as DieselError;
+use diesel::Connection;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::{routes, Route, State};
+use rocket_contrib::json::{Json, JsonError, JsonValue};
+
+pub fn routes() -> Vec<Route> {
+    let mut routes = routes![
+        list,
+        get,
+        edit,
+        create,
+        delete,
+        refresh,
+        list_errors,
+        fix_errors,
+        get_switch_types,
+        lock,
+        unlock
+    ];
+    routes.extend(audit::routes());
+    routes.extend(cache::routes());
+    routes.extend(events::routes());
+    routes.extend(layer::routes());
+    routes.extend(object_search::routes());
+    routes.extend(operational_point::routes());
+    routes.extend(pathfinding::routes());
+    routes.extend(permissions::routes());
+    routes.extend(route::routes());
+    routes.extend(schematic::routes());
+    routes.extend(track_geometry::routes());
+    routes.extend(track_projection::routes());
+    routes.extend(tvd_section::routes());
+    routes.extend(webhooks::routes());
+    routes
+}
+
+/// Refresh infra generated data
+#[allow(clippy::too_many_arguments)]
+#[post("/refresh?<infras>&<force>")]
+fn refresh(
+    user: User,
+    conn: DBConnection,
+    infras: List<i32>,
+    force: bool,
+    chartos_config: State<ChartosConfig>,
+    infra_caches: State<InfraCaches>,
+    events: State<InfraEventBus>,
+    webhooks: State<WebhookNotifier>,
+) -> ApiResult<JsonValue> {
+    // Layer invalidations are sent once the refresh is committed
+    let deferred_chartos_config = chartos_config.deferred();
+
+    // Use a transaction to give scope to infra list lock
+    let (refreshed_infra, broken_infra) =
+        conn.build_transaction().run::<_, EditoastError, _>(|| {
+            let mut infras_list = vec![];
+            let infras = infras.0?;
+
+            if infras.is_empty() {
+                // Retrieve all infras the user can edit
+                let infras = Infra::list_for_update(&conn);
+                infras_list.extend(user.filter_infras(&conn, infras, Role::Edit)?);
+            } else {
+                // Retrieve given infras
+                for id in infras.iter() {
+                    user.check_role(&conn, *id, Role::Edit)?;
+                    infras_list.push(Infra::retrieve_for_update(&conn, *id)?);
+                }
+            }
+
+            // Refresh each infras, skipping those whose cache fails to load
+            let mut refreshed_infra = vec![];
+            let mut broken_infra = vec![];
+
+            for infra in infras_list {
+                // Load the cache in a savepoint for a failed query not to abort the refresh
+                let mut load_error = None;
+                let infra_cache = conn.transaction(|| {
+                    infra_caches.get(&conn, infra.id).map_err(|err| {
+                        load_error = Some(err);
+                        DieselError::RollbackTransaction
+                    })
+                });
+                let infra_cache = match (infra_cache, load_error) {
+                    (_, Some(err)) => {
+                        broken_infra.push(json!({
+                            "infra": infra.id,
+                            "type": err.get_type(),
+                            "message": err.to_string(),
+                        }));
+                        continue;
+                    }
+                    (infra_cache, None) => infra_cache?,
+                };
+                let errors_before = count_infra_errors(&conn, infra.id)?;
+                if generate::refresh(&conn, &infra, force, &deferred_chartos_config, &infra_cache)?
+                {
+                    let errors_after = count_infra_errors(&conn, infra.id)?;
+                    record_audit(
+                        &conn,
+                        infra.id,
+                        &user.id,
+                        AuditAction::Refresh,
+                        Some(&infra.version),
+                        Some(&infra.version),
+                        json!({
+                            "force": force,
+                            "generated_version_before": infra.generated_version,
+                            "error_count_delta": errors_after - errors_before,
+                        })
+                        .into(),
+                    )?;
+                    refreshed_infra.push((infra, errors_after - errors_before));
+                }
+            }
+            Ok((refreshed_infra, broken_infra))
+        })?;
+    chartos_config.send_deferred(deferred_chartos_config);
+
+    for (infra, error_count_delta) in refreshed_infra.iter() {
+        events.publish(
+            infra.id,
+            &[InfraEvent::ErrorsGenerated {
+                generated_version: infra.version.clone(),
+            }],
+        );
+        let payload = WebhookPayload::new(
+            infra.id,
+            WebhookEvent::Refresh,
+            infra.version.clone(),
+            &[],
+            *error_count_delta,
+        );
+        webhooks.notify(&conn, payload);
+    }
+    let refreshed_infra: Vec<_> = refreshed_infra.iter().map(|(infra, _)| infra.id).collect();
+    Ok(json!({ "infra_refreshed": refreshed_infra, "infra_broken": broken_infra }))
+}
+
+/// Return the list of infras the user can read
+#[get("/")]
+fn list(user: User, conn: DBConnection) -> ApiResult<Json<Vec<Infra>>> {
+    let infras = user.filter_infras(&conn, Infra::list(&conn), Role::Read)?;
+    Ok(Json(infras))
+}
+
+/// Return a specific infra
+#[get("/<infra>")]
+fn get(user: User, conn: DBConnection, infra: i32) -> ApiResult<Custom<Json<Infra>>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    Ok(Custom(Status::Ok, Json(Infra::retrieve(&conn, infra)?)))
+}
+
+/// Create an infra, owned by the user
+#[post("/", data = "<data>")]
+fn create(
+    user: User,
+    data: Result<Json<CreateInfra>, JsonError>,
+    conn: DBConnection,
+    infra_caches: State<InfraCaches>,
+) -> ApiResult<Custom<Json<Infra>>> {
+    let data = data?;
+    let infra = conn.build_transaction().run::<_, EditoastError, _>(|| {
+        let infra = Infra::create(&data.name, &user.id, &conn)?;
+        let summary = json!({ "name": infra.name });
+        record_audit(
+            &conn,
+            infra.id,
+            &user.id,
+            AuditAction::Create,
+            None,
+            Some(&infra.version),
+            summary.into(),
+        )?;
+        Ok(infra)
+    })?;
+    infra_caches.insert_new(infra.id, InfraCache::default());
+    Ok(Custom(Status::Created, Json(infra)))
+}
+
+/// Delete an infra
+#[delete("/<infra>")]
+fn delete(
+    user: User,
+    infra: i32,
+    conn: DBConnection,
+    infra_caches: State<InfraCaches>,
+) -> ApiResult<Custom<()>> {
+    user.check_role(&conn, infra, Role::Admin)?;
+    conn.build_transaction().run::<_, EditoastError, _>(|| {
+        let infra = Infra::retrieve_for_update(&conn, infra)?;
+        Infra::delete(infra.id, &conn)?;
+        let summary = json!({ "name": infra.name });
+        record_audit(
+            &conn,
+            infra.id,
+            &user.id,
+            AuditAction::Delete,
+            Some(&infra.version),
+            None,
+            summary.into(),
+        )?;
+        Ok(())
+    })?;
+    infra_caches.remove(infra);
+    Ok(Custom(Status::NoContent, ()))
+}
+
+/// CRUD for edit an infrastructure. Takes a batch of operations.
+#[allow(clippy::too_many_arguments)]
+#[post("/<infra>", data = "<operations>")]
+fn edit(
+    user: User,
+    infra: i32,
+    operations: Result<Json<Vec<Operation>>, JsonError>,
+    infra_caches: State<InfraCaches>,
+    chartos_config: State<ChartosConfig>,
+    events: State<InfraEventBus>,
+    webhooks: State<WebhookNotifier>,
+    conn: DBConnection,
+) -> ApiResult<Json<Vec<OperationResult>>> {
+    let operations = operations?;
+    user.check_role(&conn, infra, Role::Edit)?;
+    let operation_results = apply_edit(
+        &conn,
+        &user,
+        infra,
+        &operations,
+        &infra_caches,
+        &chartos_config,
+        &events,
+        &webhooks,
+    )?;
+    Ok(Json(operation_results))
+}
+
+/// Apply the suggested fixes of the selected errors
+#[allow(clippy::too_many_arguments)]
+#[post("/<infra>/errors/fix", data = "<selectors>")]
+fn fix_errors(
+    user: User,
+    infra: i32,
+    selectors: Result<Json<Vec<ErrorFixSelector>>, JsonError>,
+    infra_caches: State<InfraCaches>,
+    chartos_config: State<ChartosConfig>,
+    events: State<InfraEventBus>,
+    webhooks: State<WebhookNotifier>,
+    conn: DBConnection,
+) -> ApiResult<Json<Vec<OperationResult>>> {
+    let selectors = selectors?;
+    user.check_role(&conn, infra, Role::Edit)?;
+    let infra = Infra::retrieve(&conn, infra)?;
+
+    let operations = {
+        let infra_cache = infra_caches.get(&conn, infra.id)?;
+        get_selected_fixes(&infra_cache, &selectors)?
+    };
+
+    let operation_results = apply_edit(
+        &conn,
+        &user,
+        infra.id,
+        &operations,
+        &infra_caches,
+        &chartos_config,
+        &events,
+        &webhooks,
+    )?;
+    Ok(Json(operation_results))
+}
+
+/// Apply a batch of operations to an infra then update its cache and generated data
+#[allow(clippy::too_many_arguments)]
+fn apply_edit(
+    conn: &DBConnection,
+    user: &User,
+    infra: i32,
+    operations: &[Operation],
+    infra_caches: &InfraCaches,
+    chartos_config: &ChartosConfig,
+    events: &InfraEventBus,
+    webhooks: &WebhookNotifier,
+) -> Result<Vec<OperationResult>, EditoastError> {
+    // Layer invalidations are sent once the edit is committed
+    let deferred_chartos_config = chartos_config.deferred();
+
+    // Use a transaction to give scope to the infra lock
+    let (infra, operation_results, error_count_delta, infra_cache) =
+        conn.build_transaction().run::<_, EditoastError, _>(|| {
+            // Retrieve and lock infra
+            let infra = Infra::retrieve_for_update(conn, infra as i32)?;
+
+            // Check if the infra is locked
+            if infra.is_locked() {
+                return Err(InfraLockedError {
+                    infra_id: infra.id,
+                    locked_by: infra.locked_by,
+                    lock_reason: infra.lock_reason,
+                    locked_until: infra.locked_until,
+                }
+                .into());
+            }
+
+            // Retrieve infra cache before changing the infra, a cache loaded afterwards
+            // would already contain the changes. It is kept locked until the edit is committed
+            // for the cache not to be evicted or reloaded without it.
+            let mut infra_cache = infra_caches.get_mut(conn, infra.id)?;
+
+            let errors_before = count_infra_errors(conn, infra.id)?;
+
+            // Apply modifications
+            let mut operation_results = vec![];
+            for operation in operations.iter() {
+                let operation = operation.clone();
+                let infra_id = infra.id;
+                operation_results.push(operation.apply(infra_id, conn)?);
+            }
+
+            // Bump version
+            let version_before = infra.version.clone();
+            let infra = infra.bump_version(conn)?;
+            record_audit(
+                conn,
+                infra.id,
+                &user.id,
+                AuditAction::Edit,
+                Some(&version_before),
+                Some(&infra.version),
+                operations_summary(&operation_results),
+            )?;
+
+            // Compute cache invalidation zones of each layer
+            let invalid_zones = LayerInvalidationZones::compute(&infra_cache, &operation_results);
+
+            // Apply operations to infra cache
+            infra_cache.apply_operations(&operation_results);
+
+            // Refresh layers if needed
+            if !invalid_zones.is_empty() {
+                generate::update(
+                    conn,
+                    infra.id,
+                    &operation_results,
+                    &infra_cache,
+                    &invalid_zones,
+                    &deferred_chartos_config,
+                )
+                .expect("Update generated data failed");
+            }
+
+            // Generate errors
+            generate_errors(conn, infra.id, &infra_cache, &deferred_chartos_config)?;
+
+            // Regenerate TVD sections if their boundaries or the track graph changed
+            if tvd_sections::is_affected_by(&operation_results) {
+                tvd_sections::refresh_layer(
+                    conn,
+                    infra.id,
+                    &infra_cache,
+                    &deferred_chartos_config,
+                )?;
+            }
+
+            // Bump infra generated version to the infra version
+            let infra = infra.bump_generated_version(conn)?;
+
+            // Check for warnings and errors
+            let errors_after = count_infra_errors(conn, infra.id)?;
+            Ok((
+                infra,
+                operation_results,
+                errors_after - errors_before,
+                infra_cache,
+            ))
+        })?;
+    drop(infra_cache);
+    chartos_config.send_deferred(deferred_chartos_config);
+
+    // Notify once the edit is committed
+    events.publish(
+        infra.id,
+        &[
+            InfraEvent::Operations {
+                operations: operation_results.clone(),
+            },
+            InfraEvent::Version {
+                version: infra.version.clone(),
+            },
+            InfraEvent::ErrorsGenerated {
+                generated_version: infra.version.clone(),
+            },
+        ],
+    );
+    let payload = WebhookPayload::new(
+        infra.id,
+        WebhookEvent::Edit,
+        infra.version,
+        &operation_results,
+        error_count_delta,
+    );
+    webhooks.notify(conn, payload);
+    Ok(operation_results)
+}
+
+/// Return the list of errors of an infra
+#[get("/<infra>/errors?<page>&<exclude_warnings>&<page_size>")]
+fn list_errors(
+    user: User,
+    infra: i32,
+    page: Option<i64>,
+    page_size: Option<i64>,
+    exclude_warnings: bool,
+    conn: DBConnection,
+) -> ApiResult<Custom<JsonValue>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    let page = page.unwrap_or_default().max(1);
+    let per_page = page_size.unwrap_or(25).max(10);
+    let (infra_errors, count) =
+        get_paginated_infra_errors(&conn, infra, page, per_page, exclude_warnings)?;
+    let previous = if page == 1 { None } else { Some(page - 1) };
+    let max_page = (count as f64 / per_page as f64).ceil() as i64;
+    let next = if page >= max_page {
+        None
+    } else {
+        Some(page + 1)
+    };
+    Ok(Custom(
+        Status::Ok,
+        json!({ "count": count, "previous": previous, "next": next, "results": infra_errors }),
+    ))
+}
+
+/// Return the railjson list of switch types
+#[get("/<infra>/switch_types")]
+fn get_switch_types(
+    user: User,
+    infra: i32,
+    conn: DBConnection,
+    infra_caches: State<InfraCaches>,
+) -> ApiResult<Custom<Json<Vec<SwitchType>>>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    let infra = infra_caches.get(&conn, infra)?;
+
+    Ok(Custom(
+        Status::Ok,
+        Json(
+            infra
+                .switch_types()
+                .values()
+                .map(ObjectCache::unwrap_switch_type)
+                .cloned()
+                .collect(),
+        ),
+    ))
+}
+
+/// Parse a JSON body that may be omitted
+fn optional_body<T: Default>(data: Result<Json<T>, JsonError>) -> ApiResult<T> {
+    match data {
+        Ok(data) => Ok(data.into_inner()),
+        Err(JsonError::Parse(body, _)) if body.trim().is_empty() => Ok(T::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Lock an infra, optionally with a reason and a duration in seconds.
+/// The lock is owned by the user, the owner can only be chosen if authentication is disabled.
+#[post("/<infra>/lock", data = "<data>")]
+fn lock(
+    user: User,
+    infra: i32,
+    data: Result<Json<LockInfra>, JsonError>,
+    conn: DBConnection,
+    events: State<InfraEventBus>,
+) -> ApiResult<Custom<JsonValue>> {
+    let mut data = optional_body(data)?;
+    user.check_role(&conn, infra, Role::Edit)?;
+    data.owner = user.lock_owner(data.owner);
+    let infra = conn.build_transaction().run::<_, EditoastError, _>(|| {
+        let infra = Infra::retrieve_for_update(&conn, infra)?;
+        let infra = infra.lock(&data, &conn)?;
+        let summary = json!({
+            "owner": infra.locked_by,
+            "reason": infra.lock_reason,
+            "locked_until": infra.locked_until,
+        });
+        record_audit(
+            &conn,
+            infra.id,
+            &user.id,
+            AuditAction::Lock,
+            Some(&infra.version),
+            Some(&infra.version),
+            summary.into(),
+        )?;
+        Ok(infra)
+    })?;
+    events.publish(infra.id, &[InfraEvent::Lock { locked: true }]);
+    Ok(Custom(Status::NoContent, json!(null)))
+}
+
+/// Unlock an infra. Only the lock owner may unlock it, unless an administrator sets `force`.
+#[post("/<infra>/unlock", data = "<data>")]
+fn unlock(
+    user: User,
+    infra: i32,
+    data: Result<Json<UnlockInfra>, JsonError>,
+    conn: DBConnection,
+    events: State<InfraEventBus>,
+) -> ApiResult<Custom<JsonValue>> {
+    let mut data = optional_body(data)?;
+    let required = if data.force { Role::Admin } else { Role::Edit };
+    user.check_role(&conn, infra, required)?;
+    data.owner = user.lock_owner(data.owner);
+    let infra = conn.build_transaction().run::<_, EditoastError, _>(|| {
+        let infra = Infra::retrieve_for_update(&conn, infra)?;
+        infra.unlock(&data, &conn)?;
+        let summary = json!({
+            "previous_owner": infra.locked_by,
+            "force": data.force,
+        });
+        record_audit(
+            &conn,
+            infra.id,
+            &user.id,
+            AuditAction::Unlock,
+            Some(&infra.version),
+            Some(&infra.version),
+            summary.into(),
+        )?;
+        Ok(infra)
+    })?;
+    events.publish(infra.id, &[InfraEvent::Lock { locked: false }]);
+    Ok(Custom(Status::NoContent, json!(null)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::client::AuthConfig;
+    use crate::create_server;
+    use crate::infra_cache::tests::{create_switch_connection, create_switch_type_cache};
+    use crate::models::Infra;
+    use crate::objects::operation::{Operation, RailjsonObject};
+    use crate::objects::SwitchType;
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::Client;
+    use serde::Deserialize;
+
+    #[test]
+    fn infra_list() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        let client = Client::new(rocket).expect("valid rocket instance");
+        let response = client.get("/infra").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn infra_permissions() {
+        let auth_config = AuthConfig {
+            auth_tokens: vec![
+                "owner_token:11111111-1111-1111-1111-111111111111"
+                    .parse()
+                    .unwrap(),
+                "other_token:22222222-2222-2222-2222-222222222222"
+                    .parse()
+                    .unwrap(),
+            ],
+            disable_auth: false,
+            ..Default::default()
+        };
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            auth_config,
+        );
+        let client = Client::new(rocket).expect("valid rocket instance");
+        let owner = Header::new("Authorization", "Bearer owner_token");
+        let other = Header::new("Authorization", "Bearer other_token");
+
+        let mut response = client.get("/infra").dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert!(response
+            .body_string()
+            .unwrap()
+            .contains("editoast:auth:MissingCredentials"));
+
+        let mut create_infra = client
+            .post("/infra")
+            .header(ContentType::JSON)
+            .header(owner.clone())
+            .body(r#"{"name":"test"}"#)
+            .dispatch();
+        assert_eq!(create_infra.status(), Status::Created);
+        let infra: Infra = serde_json::from_str(&create_infra.body_string().unwrap()).unwrap();
+        let infra_url = format!("/infra/{}", infra.id);
+
+        let response = client.get(&infra_url).header(other.clone()).dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+
+        // Grant the read role to the other user
+        let response = client
+            .put(format!(
+                "{}/permissions/22222222-2222-2222-2222-222222222222",
+                infra_url
+            ))
+            .header(ContentType::JSON)
+            .header(owner.clone())
+            .body(r#"{"role":"read"}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get(&infra_url).header(other.clone()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let response = client
+            .post(format!("{}/lock", infra_url))
+            .header(other.clone())
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client.delete(&infra_url).header(other).dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+
+        let response = client.delete(&infra_url).header(owner).dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+    }
+
+    #[test]
+    fn infra_audit() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+        let client = Client::new(rocket).expect("valid rocket instance");
+        let mut create_infra = client
+            .post("/infra")
+            .header(ContentType::JSON)
+            .body(r#"{"name":"test"}"#)
+            .dispatch();
+        let infra: Infra = serde_json::from_str(&create_infra.body_string().unwrap()).unwrap();
+
+        let response = client
+            .post(format!("/infra/{}/lock", infra.id))
+            .header(ContentType::JSON)
+            .body(r#"{"reason":"study"}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+
+        let mut response = client.get(format!("/infra/{}/audit", infra.id)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let audit: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(audit["count"], 2);
+        assert_eq!(audit["results"][0]["action"], "lock");
+        assert_eq!(audit["results"][0]["summary"]["reason"], "study");
+        assert_eq!(audit["results"][1]["action"], "create");
+
+        let mut response = client
+            .get(format!("/infra/{}/audit?action=create", infra.id))
+            .dispatch();
+        let audit: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(audit["count"], 1);
+
+        // The audit log outlives the infra
+        let response = client.delete(format!("/infra/{}", infra.id)).dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+        let mut response = client.get(format!("/infra/{}/audit", infra.id)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let audit: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(audit["count"], 3);
+        assert_eq!(audit["results"][0]["action"], "delete");
+    }
+
+    #[test]
+    fn infra_cache_reload_and_diff() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+        let client = Client::new(rocket).expect("valid rocket instance");
+        let mut create_infra = client
+            .post("/infra")
+            .header(ContentType::JSON)
+            .body(r#"{"name":"test"}"#)
+            .dispatch();
+        let infra: Infra = serde_json::from_str(&create_infra.body_string().unwrap()).unwrap();
+
+        let mut response = client
+            .get(format!("/infra/{}/cache/diff", infra.id))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let diff: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(
+            diff,
+            serde_json::json!({"missing": [], "extra": [], "mismatched": []})
+        );
+
+        let response = client
+            .post(format!("/infra/{}/cache/reload", infra.id))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        // Clean up
+        let response = client.delete(format!("/infra/{}", infra.id)).dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+    }
+
+    #[test]
+    fn infra_create_delete() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        let client = Client::new(rocket).expect("valid rocket instance");
+        let mut create_infra = client
+            .post("/infra")
+            .header(ContentType::JSON)
+            .body(r#"{"name":"test"}"#)
+            .dispatch();
+
+        let body = create_infra.body_string();
+        assert!(body.is_some());
+
+        let infra: Infra = serde_json::from_str(body.unwrap().as_str()).unwrap();
+
+        assert_eq!(create_infra.status(), Status::Created);
+        assert_eq!(infra.name, "test");
+
+        let delete_infra = client.delete(format!("/infra/{}", infra.id)).dispatch();
+
+        assert_eq!(delete_infra.status(), Status::NoContent);
+    }
+
+    #[test]
+    fn infra_get() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        let client = Client::new(rocket).expect("valid rocket instance");
+        let mut create_infra = client
+            .post("/infra")
+            .header(ContentType::JSON)
+            .body(r#"{"name":"test"}"#)
+            .dispatch();
+
+        let body = create_infra.body_string();
+        assert!(body.is_some());
+
+        let infra: Infra = serde_json::from_str(body.unwrap().as_str()).unwrap();
+        assert_eq!(create_infra.status(), Status::Created);
+        assert_eq!(infra.name, "test");
+
+        let response = client.get(format!("/infra/{}", infra.id)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let delete_infra = client.delete(format!("/infra/{}", infra.id)).dispatch();
+        assert_eq!(delete_infra.status(), Status::NoContent);
+
+        let responsedel = client.get(format!("/infra/{}", infra.id)).dispatch();
+        assert_eq!(responsedel.status(), Status::NotFound);
+    }
+
+    #[derive(Deserialize)]
+    struct InfraRefreshedResponse {
+        infra_refreshed: Vec<i32>,
+        infra_broken: Vec<serde_json::Value>,
+    }
+
+    #[test]
+    fn infra_refresh() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        let client = Client::new(rocket).expect("valid rocket instance");
+
+        let mut create_infra = client
+            .post("/infra")
+            .header(ContentType::JSON)
+            .body(r#"{"name":"refresh_test"}"#)
+            .dispatch();
+
+        assert_eq!(create_infra.status(), Status::Created);
+
+        let body_infra = create_infra.body_string();
+        assert!(body_infra.is_some());
+        let infra: Infra = serde_json::from_str(body_infra.unwrap().as_str()).unwrap();
+        assert_eq!(infra.name, "refresh_test");
+
+        let mut infra_no_refresh = client
+            .post(format!("/infra/refresh/?infras={}", infra.id))
+            .dispatch();
+        assert_eq!(infra_no_refresh.status(), Status::Ok);
+
+        let body_refresh = infra_no_refresh.body_string();
+        assert!(body_refresh.is_some());
+
+        let refresh: InfraRefreshedResponse =
+            serde_json::from_str(body_refresh.unwrap().as_str()).unwrap();
+        assert!(refresh.infra_refreshed.is_empty());
+        assert!(refresh.infra_broken.is_empty());
+
+        let delete_infra = client.delete(format!("/infra/{}", infra.id)).dispatch();
+        assert_eq!(delete_infra.status(), Status::NoContent);
+    }
+
+    #[test]
+    fn infra_refresh_force() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        let client = Client::new(rocket).expect("valid rocket instance");
+
+        let mut create_infra = client
+            .post("/infra")
+            .header(ContentType::JSON)
+            .body(r#"{"name":"refresh_test"}"#)
+            .dispatch();
+
+        assert_eq!(create_infra.status(), Status::Created);
+
+        let body_infra = create_infra.body_string();
+        assert!(body_infra.is_some());
+        let infra: Infra = serde_json::from_str(body_infra.unwrap().as_str()).unwrap();
+        assert_eq!(infra.name, "refresh_test");
+
+        let mut infra_no_refresh = client
+            .post(format!("/infra/refresh/?force=true&infras={}", infra.id))
+            .dispatch();
+        assert_eq!(infra_no_refresh.status(), Status::Ok);
+
+        let body_refresh = infra_no_refresh.body_string();
+        assert!(body_refresh.is_some());
+
+        let refresh: InfraRefreshedResponse =
+            serde_json::from_str(body_refresh.unwrap().as_str()).unwrap();
+        assert!(refresh.infra_refreshed.contains(&infra.id));
+
+        let delete_infra = client.delete(format!("/infra/{}", infra.id)).dispatch();
+        assert_eq!(delete_infra.status(), Status::NoContent);
+    }
+
+    #[test]
+    fn infra_get_switch_types() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        let client = Client::new(rocket).expect("valid rocket instance");
+
+        let mut create_infra = client
+            .post("/infra")
+            .header(ContentType::JSON)
+            .body(r#"{"name":"get_switch_types"}"#)
+            .dispatch();
+
+        assert_eq!(create_infra.status(), Status::Created);
+
+        let body_infra = create_infra.body_string();
+        assert!(body_infra.is_some());
+        let infra: Infra = serde_json::from_str(body_infra.unwrap().as_str()).unwrap();
+
+        let switch_type = create_switch_type_cache(
+            "point",
+            vec!["BASE".into(), "LEFT".into(), "RIGHT".into()],
+            HashMap::from([
+                (
+                    "LEFT".into(),
+                    vec![create_switch_connection("BASE".into(), "LEFT".into())],
+                ),
+                (
+                    "RIGHT".into(),
+                    vec![create_switch_connection("BASE".into(), "RIGHT".into())],
+                ),
+            ]),
+        );
+        let operation = Operation::Create(Box::new(RailjsonObject::SwitchType {
+            railjson: switch_type.clone(),
+        }));
+
+        let create_switch_type = client
+            .post(format!("/infra/{}/", infra.id))
+            .header(ContentType::JSON)
+            .body(serde_json::to_string(&vec![operation]).unwrap())
+            .dispatch();
+        assert_eq!(create_switch_type.status(), Status::Ok);
+
+        let mut get_switch_types = client
+            .get(format!("/infra/{}/switch_types/", infra.id))
+            .dispatch();
+        assert_eq!(get_switch_types.status(), Status::Ok);
+        let body_switch_types = get_switch_types.body_string();
+        assert!(body_switch_types.is_some());
+
+        let switch_types: Vec<SwitchType> =
+            serde_json::from_str(body_switch_types.unwrap().as_str()).unwrap();
+        assert!(switch_types.len() == 1);
+        assert_eq!(switch_types[0].id, switch_type.id);
+
+        let delete_infra = client.delete(format!("/infra/{}", infra.id)).dispatch();
+        assert_eq!(delete_infra.status(), Status::NoContent);
+    }
+
+    #[test]
+    fn infra_fix_errors() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        let client = Client::new(rocket).expect("valid rocket instance");
+
+        let mut create_infra = client
+            .post("/infra")
+            .header(ContentType::JSON)
+            .body(r#"{"name":"fix_errors"}"#)
+            .dispatch();
+        assert_eq!(create_infra.status(), Status::Created);
+        let infra: Infra =
+            serde_json::from_str(create_infra.body_string().unwrap().as_str()).unwrap();
+
+        let switch_type = create_switch_type_cache(
+            "point",
+            vec!["BASE".into(), "LEFT".into(), "UNUSED".into()],
+            HashMap::from([(
+                "LEFT".into(),
+                vec![create_switch_connection("BASE".into(), "LEFT".into())],
+            )]),
+        );
+        let operation = Operation::Create(Box::new(RailjsonObject::SwitchType {
+            railjson: switch_type,
+        }));
+        let create_switch_type = client
+            .post(format!("/infra/{}/", infra.id))
+            .header(ContentType::JSON)
+            .body(serde_json::to_string(&vec![operation]).unwrap())
+            .dispatch();
+        assert_eq!(create_switch_type.status(), Status::Ok);
+
+        let selectors = r#"[{"obj_id":"point","obj_type":"SwitchType","error_type":"unused_port","field":"ports.2"}]"#;
+        let fix_errors = client
+            .post(format!("/infra/{}/errors/fix", infra.id))
+            .header(ContentType::JSON)
+            .body(selectors)
+            .dispatch();
+        assert_eq!(fix_errors.status(), Status::Ok);
+
+        let mut get_switch_types = client
+            .get(format!("/infra/{}/switch_types/", infra.id))
+            .dispatch();
+        let switch_types: Vec<SwitchType> =
+            serde_json::from_str(get_switch_types.body_string().unwrap().as_str()).unwrap();
+        assert_eq!(switch_types[0].ports, vec!["BASE", "LEFT"]);
+
+        // The error is fixed, it can't be selected anymore
+        let fix_errors = client
+            .post(format!("/infra/{}/errors/fix", infra.id))
+            .header(ContentType::JSON)
+            .body(selectors)
+            .dispatch();
+        assert_eq!(fix_errors.status(), Status::NotFound);
+
+        let delete_infra = client.delete(format!("/infra/{}", infra.id)).dispatch();
+        assert_eq!(delete_infra.status(), Status::NoContent);
+    }
+
+    #[test]
+    fn infra_lock() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        let client = Client::new(rocket).expect("valid rocket instance");
+
+        let mut create_infra = client
+            .post("/infra")
+            .header(ContentType::JSON)
+            .body(r#"{"name":"lock test"}"#)
+            .dispatch();
+        assert_eq!(create_infra.status(), Status::Created);
+
+        let body_infra = create_infra.body_string();
+        let infra: Infra = serde_json::from_str(body_infra.unwrap().as_str()).unwrap();
+        assert!(!infra.locked);
+
+        let lock_query = client.post(format!("/infra/{}/lock/", infra.id)).dispatch();
+        assert_eq!(lock_query.status(), Status::NoContent);
+
+        let body_infra = client
+            .get(format!("/infra/{}", infra.id))
+            .dispatch()
+            .body_string();
+        let infra: Infra = serde_json::from_str(body_infra.unwrap().as_str()).unwrap();
+        assert!(infra.locked);
+
+        let unlock_query = client
+            .post(format!("/infra/{}/unlock/", infra.id))
+            .dispatch();
+        assert_eq!(unlock_query.status(), Status::NoContent);
+
+        let body_infra = client
+            .get(format!("/infra/{}", infra.id))
+            .dispatch()
+            .body_string();
+        let infra: Infra = serde_json::from_str(body_infra.unwrap().as_str()).unwrap();
+        assert!(!infra.locked);
+
+        let delete_infra = client.delete(format!("/infra/{}", infra.id)).dispatch();
+        assert_eq!(delete_infra.status(), Status::NoContent);
+    }
+}
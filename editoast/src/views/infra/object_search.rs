@@ -0,0 +1,30 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::models::object_search::{parse_object_type, search_objects, ObjectSearchResult};
+use crate::models::permissions::Role;
+use crate::models::DBConnection;
+use rocket::{routes, Route};
+use rocket_contrib::json::Json;
+
+pub fn routes() -> Vec<Route> {
+    routes![search]
+}
+
+/// Search the objects of an infra by id, label, line or track name
+#[get("/<infra>/objects/search?<q>&<obj_type>&<limit>")]
+fn search(
+    user: User,
+    infra: i32,
+    q: String,
+    obj_type: Option<String>,
+    limit: Option<i64>,
+    conn: DBConnection,
+) -> ApiResult<Json<Vec<ObjectSearchResult>>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    let obj_type = match obj_type {
+        Some(obj_type) => Some(parse_object_type(&obj_type)?),
+        None => None,
+    };
+    let limit = limit.unwrap_or(20).clamp(1, 100);
+    Ok(Json(search_objects(&conn, infra, &q, obj_type, limit)?))
+}
@@ -0,0 +1,29 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::infra_caches::InfraCaches;
+use crate::models::operational_point_search::{search_operational_points, OperationalPointMatch};
+use crate::models::permissions::Role;
+use crate::models::DBConnection;
+use rocket::{routes, Route, State};
+use rocket_contrib::json::Json;
+
+pub fn routes() -> Vec<Route> {
+    routes![search]
+}
+
+/// Search operational points by uic, trigram, name or ch, best matches first
+#[get("/<infra>/operational_points/search?<q>&<limit>")]
+fn search(
+    user: User,
+    infra: i32,
+    q: String,
+    limit: Option<usize>,
+    infra_caches: State<InfraCaches>,
+    conn: DBConnection,
+) -> ApiResult<Json<Vec<OperationalPointMatch>>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    let infra_cache = infra_caches.get(&conn, infra)?;
+
+    let limit = limit.unwrap_or(20).clamp(1, 100);
+    Ok(Json(search_operational_points(&infra_cache, &q, limit)))
+}
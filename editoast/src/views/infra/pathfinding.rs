@@ -0,0 +1,77 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::infra_caches::InfraCaches;
+use crate::models::errors::graph::Graph;
+use crate::models::pathfinding::{compute_path, Path, PathWaypoint};
+use crate::models::permissions::Role;
+use crate::models::DBConnection;
+use rocket::{routes, Route, State};
+use rocket_contrib::json::{Json, JsonError};
+use serde::Deserialize;
+
+pub fn routes() -> Vec<Route> {
+    routes![pathfinding]
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PathfindingInput {
+    waypoints: Vec<PathWaypoint>,
+}
+
+/// Compute the shortest path going through the given waypoints
+#[post("/<infra>/pathfinding", data = "<input>")]
+fn pathfinding(
+    user: User,
+    infra: i32,
+    input: Result<Json<PathfindingInput>, JsonError>,
+    infra_caches: State<InfraCaches>,
+    conn: DBConnection,
+) -> ApiResult<Json<Path>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    let input = input?;
+    let infra_cache = infra_caches.get(&conn, infra)?;
+
+    let graph = Graph::load(&infra_cache);
+    Ok(Json(compute_path(&infra_cache, &graph, &input.waypoints)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_server;
+    use crate::models::Infra;
+    use rocket::http::{ContentType, Status};
+    use rocket::local::Client;
+
+    #[test]
+    fn pathfinding_unknown_track() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        let client = Client::new(rocket).expect("valid rocket instance");
+
+        let mut create_infra = client
+            .post("/infra")
+            .header(ContentType::JSON)
+            .body(r#"{"name":"pathfinding"}"#)
+            .dispatch();
+        assert_eq!(create_infra.status(), Status::Created);
+        let infra: Infra =
+            serde_json::from_str(create_infra.body_string().unwrap().as_str()).unwrap();
+
+        let pathfinding = client
+            .post(format!("/infra/{}/pathfinding", infra.id))
+            .header(ContentType::JSON)
+            .body(r#"{"waypoints":[{"track":"A","offset":0},{"track":"B","offset":0}]}"#)
+            .dispatch();
+        assert_eq!(pathfinding.status(), Status::BadRequest);
+
+        let delete_infra = client.delete(format!("/infra/{}", infra.id)).dispatch();
+        assert_eq!(delete_infra.status(), Status::NoContent);
+    }
+}
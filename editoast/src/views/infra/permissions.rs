@@ -0,0 +1,43 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::models::permissions::{InfraPermission, Role, SetPermission};
+use crate::models::DBConnection;
+use crate::views::params::UuidParam;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::{routes, Route};
+use rocket_contrib::json::{Json, JsonError};
+
+pub fn routes() -> Vec<Route> {
+    routes![list, set, delete]
+}
+
+/// Return the roles granted on an infra, its owner isn't listed
+#[get("/<infra>/permissions")]
+fn list(user: User, infra: i32, conn: DBConnection) -> ApiResult<Json<Vec<InfraPermission>>> {
+    user.check_role(&conn, infra, Role::Admin)?;
+    Ok(Json(InfraPermission::list(&conn, infra)?))
+}
+
+/// Grant a role on an infra to a user
+#[put("/<infra>/permissions/<target>", data = "<data>")]
+fn set(
+    user: User,
+    infra: i32,
+    target: UuidParam,
+    data: Result<Json<SetPermission>, JsonError>,
+    conn: DBConnection,
+) -> ApiResult<Json<InfraPermission>> {
+    user.check_role(&conn, infra, Role::Admin)?;
+    let data = data?;
+    let permission = InfraPermission::set(&conn, infra, &target, data.role)?;
+    Ok(Json(permission))
+}
+
+/// Revoke the role of a user on an infra
+#[delete("/<infra>/permissions/<target>")]
+fn delete(user: User, infra: i32, target: UuidParam, conn: DBConnection) -> ApiResult<Custom<()>> {
+    user.check_role(&conn, infra, Role::Admin)?;
+    InfraPermission::delete(&conn, infra, &target)?;
+    Ok(Custom(Status::NoContent, ()))
+}
@@ -0,0 +1,89 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::infra_caches::InfraCaches;
+use crate::models::permissions::Role;
+use crate::models::route_conflicts::{compute_conflict_matrix, ConflictMatrix};
+use crate::models::routes_generation::generate_routes;
+use crate::models::DBConnection;
+use crate::objects::operation::{Operation, RailjsonObject};
+use rocket::{routes, Route, State};
+use rocket_contrib::json::Json;
+
+pub fn routes() -> Vec<Route> {
+    routes![generate, conflicts]
+}
+
+/// Return the creation operations of the routes missing between detectors and buffer stops
+#[get("/<infra>/routes/generate")]
+fn generate(
+    user: User,
+    infra: i32,
+    infra_caches: State<InfraCaches>,
+    conn: DBConnection,
+) -> ApiResult<Json<Vec<Operation>>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    let infra_cache = infra_caches.get(&conn, infra)?;
+
+    Ok(Json(
+        generate_routes(&infra_cache)
+            .into_iter()
+            .map(|route| Operation::Create(Box::new(RailjsonObject::Route { railjson: route })))
+            .collect(),
+    ))
+}
+
+/// Return for each route of the infra the routes it conflicts with
+#[get("/<infra>/routes/conflicts")]
+fn conflicts(
+    user: User,
+    infra: i32,
+    infra_caches: State<InfraCaches>,
+    conn: DBConnection,
+) -> ApiResult<Json<ConflictMatrix>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    let infra_cache = infra_caches.get(&conn, infra)?;
+
+    Ok(Json(compute_conflict_matrix(&infra_cache)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_server;
+    use crate::models::Infra;
+    use crate::objects::operation::Operation;
+    use rocket::http::{ContentType, Status};
+    use rocket::local::Client;
+
+    #[test]
+    fn generate_routes_empty_infra() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        let client = Client::new(rocket).expect("valid rocket instance");
+
+        let mut create_infra = client
+            .post("/infra")
+            .header(ContentType::JSON)
+            .body(r#"{"name":"generate_routes"}"#)
+            .dispatch();
+        assert_eq!(create_infra.status(), Status::Created);
+        let infra: Infra =
+            serde_json::from_str(create_infra.body_string().unwrap().as_str()).unwrap();
+
+        let mut generate = client
+            .get(format!("/infra/{}/routes/generate", infra.id))
+            .dispatch();
+        assert_eq!(generate.status(), Status::Ok);
+        let operations: Vec<Operation> =
+            serde_json::from_str(generate.body_string().unwrap().as_str()).unwrap();
+        assert!(operations.is_empty());
+
+        let delete_infra = client.delete(format!("/infra/{}", infra.id)).dispatch();
+        assert_eq!(delete_infra.status(), Status::NoContent);
+    }
+}
@@ -0,0 +1,28 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::infra_caches::InfraCaches;
+use crate::models::permissions::Role;
+use crate::models::schematic_generation::generate_schematic;
+use crate::models::DBConnection;
+use crate::objects::operation::Operation;
+use rocket::{routes, Route, State};
+use rocket_contrib::json::Json;
+
+pub fn routes() -> Vec<Route> {
+    routes![generate]
+}
+
+/// Return the update operations replacing the schematic line strings with a generated layout
+#[get("/<infra>/schematic/generate?<only_duplicated>")]
+fn generate(
+    user: User,
+    infra: i32,
+    only_duplicated: bool,
+    infra_caches: State<InfraCaches>,
+    conn: DBConnection,
+) -> ApiResult<Json<Vec<Operation>>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    let infra_cache = infra_caches.get(&conn, infra)?;
+
+    Ok(Json(generate_schematic(&infra_cache, only_duplicated)))
+}
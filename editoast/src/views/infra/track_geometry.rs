@@ -0,0 +1,55 @@
+use super::apply_edit;
+use crate::auth::User;
+use crate::client::ChartosConfig;
+use crate::error::ApiResult;
+use crate::events::InfraEventBus;
+use crate::infra_caches::InfraCaches;
+use crate::models::permissions::Role;
+use crate::models::track_geometry::{
+    get_track_geometry_operations, load_track_section, TrackGeometryEdit,
+};
+use crate::models::webhooks::WebhookNotifier;
+use crate::models::DBConnection;
+use crate::objects::operation::OperationResult;
+use rocket::{routes, Route, State};
+use rocket_contrib::json::{Json, JsonError};
+
+pub fn routes() -> Vec<Route> {
+    routes![edit_geometry]
+}
+
+/// Move, insert or delete vertices of a track section line string
+#[allow(clippy::too_many_arguments)]
+#[post("/<infra>/track_sections/<track>/geometry", data = "<edit>")]
+fn edit_geometry(
+    user: User,
+    infra: i32,
+    track: String,
+    edit: Result<Json<TrackGeometryEdit>, JsonError>,
+    infra_caches: State<InfraCaches>,
+    chartos_config: State<ChartosConfig>,
+    events: State<InfraEventBus>,
+    webhooks: State<WebhookNotifier>,
+    conn: DBConnection,
+) -> ApiResult<Json<Vec<OperationResult>>> {
+    user.check_role(&conn, infra, Role::Edit)?;
+    let edit = edit?;
+    let track = load_track_section(&conn, infra, &track)?;
+
+    let operations = {
+        let infra_cache = infra_caches.get(&conn, infra)?;
+        get_track_geometry_operations(&track, &edit, &infra_cache)?
+    };
+
+    let operation_results = apply_edit(
+        &conn,
+        &user,
+        infra,
+        &operations,
+        &infra_caches,
+        &chartos_config,
+        &events,
+        &webhooks,
+    )?;
+    Ok(Json(operation_results))
+}
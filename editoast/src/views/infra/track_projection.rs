@@ -0,0 +1,39 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::models::permissions::Role;
+use crate::models::track_projection::{
+    locate_on_track, project_point, TrackLocation, TrackProjection,
+};
+use crate::models::DBConnection;
+use rocket::{routes, Route};
+use rocket_contrib::json::Json;
+
+pub fn routes() -> Vec<Route> {
+    routes![project, locate]
+}
+
+/// Project a geographic point (WGS84) on the closest track section
+#[get("/<infra>/track_projection?<lon>&<lat>")]
+fn project(
+    user: User,
+    infra: i32,
+    lon: f64,
+    lat: f64,
+    conn: DBConnection,
+) -> ApiResult<Json<TrackProjection>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    Ok(Json(project_point(&conn, infra, lon, lat)?))
+}
+
+/// Return the geographic and schematic coordinates of a position on a track section
+#[get("/<infra>/track_location?<track>&<offset>")]
+fn locate(
+    user: User,
+    infra: i32,
+    track: String,
+    offset: f64,
+    conn: DBConnection,
+) -> ApiResult<Json<TrackLocation>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    Ok(Json(locate_on_track(&conn, infra, &track, offset)?))
+}
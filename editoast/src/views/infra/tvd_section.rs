@@ -0,0 +1,50 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::infra_caches::InfraCaches;
+use crate::models::errors::graph::Graph;
+use crate::models::permissions::Role;
+use crate::models::tvd_sections::{compute_tvd_sections, TvdSection};
+use crate::models::DBConnection;
+use rocket::{routes, Route, State};
+use rocket_contrib::json::Json;
+
+pub fn routes() -> Vec<Route> {
+    routes![list]
+}
+
+/// Return the TVD sections of an infra
+#[get("/<infra>/tvd_sections")]
+fn list(
+    user: User,
+    infra: i32,
+    infra_caches: State<InfraCaches>,
+    conn: DBConnection,
+) -> ApiResult<Json<Vec<TvdSection>>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    let infra_cache = infra_caches.get(&conn, infra)?;
+
+    let graph = Graph::load(&infra_cache);
+    Ok(Json(compute_tvd_sections(&infra_cache, &graph)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_server;
+    use rocket::http::Status;
+    use rocket::local::Client;
+
+    #[test]
+    fn tvd_sections_unknown_infra() {
+        let rocket = create_server(
+            Default::default(),
+            6000,
+            &Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        let client = Client::new(rocket).expect("valid rocket instance");
+        let response = client.get("/infra/-1/tvd_sections").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::models::permissions::Role;
+use crate::models::webhooks::{CreateWebhook, Webhook};
+use crate::models::{DBConnection, Infra};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::{routes, Route};
+use rocket_contrib::json::{Json, JsonError, JsonValue};
+
+pub fn routes() -> Vec<Route> {
+    routes![list, create, delete]
+}
+
+/// Return the webhooks of an infra
+#[get("/<infra>/webhooks")]
+fn list(user: User, infra: i32, conn: DBConnection) -> ApiResult<Json<Vec<Webhook>>> {
+    user.check_role(&conn, infra, Role::Admin)?;
+    Ok(Json(Webhook::list(&conn, infra)?))
+}
+
+/// Register a webhook notified after each edit and refresh of an infra.
+/// The secret signing the payloads is only returned here.
+#[post("/<infra>/webhooks", data = "<data>")]
+fn create(
+    user: User,
+    infra: i32,
+    data: Result<Json<CreateWebhook>, JsonError>,
+    conn: DBConnection,
+) -> ApiResult<Custom<JsonValue>> {
+    user.check_role(&conn, infra, Role::Admin)?;
+    let data = data?;
+    let infra = Infra::retrieve(&conn, infra)?;
+    let webhook = Webhook::create(&conn, infra.id, &data.url)?;
+    Ok(Custom(
+        Status::Created,
+        json!({
+            "id": webhook.id,
+            "infra_id": webhook.infra_id,
+            "url": webhook.url,
+            "secret": webhook.secret,
+        }),
+    ))
+}
+
+/// Unregister a webhook
+#[delete("/<infra>/webhooks/<webhook>")]
+fn delete(user: User, infra: i32, webhook: i32, conn: DBConnection) -> ApiResult<Custom<()>> {
+    user.check_role(&conn, infra, Role::Admin)?;
+    Webhook::delete(&conn, infra, webhook)?;
+    Ok(Custom(Status::NoContent, ()))
+}
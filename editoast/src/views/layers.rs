@@ -0,0 +1,67 @@
+use crate::auth::User;
+use crate::error::ApiResult;
+use crate::layer::{get_layer_definition, LayerView};
+use crate::models::permissions::Role;
+use crate::models::DBConnection;
+use rocket::http::{ContentType, RawStr};
+use rocket::request::FromParam;
+use rocket::response::Content;
+use rocket::{routes, Route};
+
+/// Routes of the in-process tile server, only mounted with `--tile-server`
+pub fn routes() -> Vec<Route> {
+    routes![tile]
+}
+
+/// Tile ordinate given with the `.pbf` extension
+#[derive(Debug, PartialEq, Eq)]
+pub struct PbfTileY(u32);
+
+impl<'r> FromParam<'r> for PbfTileY {
+    type Error = &'r RawStr;
+
+    fn from_param(param: &'r RawStr) -> Result<Self, Self::Error> {
+        param
+            .strip_suffix(".pbf")
+            .and_then(|y| y.parse().ok())
+            .map(PbfTileY)
+            .ok_or(param)
+    }
+}
+
+/// Return a mapbox vector tile of a layer
+#[allow(clippy::too_many_arguments)]
+#[get("/<layer>/tiles/<infra>/<z>/<x>/<y>?<view>")]
+fn tile(
+    user: User,
+    layer: String,
+    infra: i32,
+    z: u32,
+    x: u32,
+    y: PbfTileY,
+    view: Option<String>,
+    conn: DBConnection,
+) -> ApiResult<Content<Vec<u8>>> {
+    user.check_role(&conn, infra, Role::Read)?;
+    let layer = get_layer_definition(&layer)?;
+    let view = LayerView::parse(view.as_deref().unwrap_or("geo"))?;
+    let tile = layer.get_mvt_tile(&conn, infra, view, z, x, y.0)?;
+    Ok(Content(ContentType::new("application", "x-protobuf"), tile))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PbfTileY;
+    use rocket::http::RawStr;
+    use rocket::request::FromParam;
+
+    #[test]
+    fn parse_tile_y() {
+        assert_eq!(
+            PbfTileY::from_param(RawStr::from_str("12.pbf")),
+            Ok(PbfTileY(12))
+        );
+        assert!(PbfTileY::from_param(RawStr::from_str("12")).is_err());
+        assert!(PbfTileY::from_param(RawStr::from_str("a.pbf")).is_err());
+    }
+}
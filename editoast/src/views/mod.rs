@@ -1,4 +1,5 @@
 mod infra;
+pub mod layers;
 pub mod pagination;
 pub mod params;
 
@@ -32,6 +33,7 @@ mod tests {
             6000,
             &Default::default(),
             Default::default(),
+            Default::default(),
         );
         let client = Client::new(rocket).expect("valid rocket instance");
         let response = client.get("/health").dispatch();
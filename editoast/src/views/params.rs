@@ -1,8 +1,10 @@
 use std::marker::PhantomData;
+use std::ops::Deref;
 
 use crate::error::{ApiResult, EditoastError};
 use rocket::http::{RawStr, Status};
-use rocket::request::FromFormValue;
+use rocket::request::{FromFormValue, FromParam};
+use uuid::Uuid;
 
 /// This parameter is used to deserialized a list of `T`
 #[derive(Debug)]
@@ -45,3 +47,53 @@ impl<'f, T: FromFormValue<'f>> FromFormValue<'f> for List<'f, T> {
         Some(List::new(vec![]))
     }
 }
+
+/// Uuid path or query parameter.
+/// Parsed with the same `uuid` crate as the models, which `rocket_contrib::uuid` may not use.
+#[derive(Debug, Clone, Copy)]
+pub struct UuidParam(pub Uuid);
+
+impl UuidParam {
+    fn parse(param: &RawStr) -> Result<Self, uuid::Error> {
+        Ok(Self(param.percent_decode_lossy().parse()?))
+    }
+}
+
+impl Deref for UuidParam {
+    type Target = Uuid;
+
+    fn deref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl<'r> FromParam<'r> for UuidParam {
+    type Error = uuid::Error;
+
+    fn from_param(param: &'r RawStr) -> Result<Self, Self::Error> {
+        Self::parse(param)
+    }
+}
+
+impl<'f> FromFormValue<'f> for UuidParam {
+    type Error = uuid::Error;
+
+    fn from_form_value(form_value: &'f RawStr) -> Result<Self, Self::Error> {
+        Self::parse(form_value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UuidParam;
+    use rocket::http::RawStr;
+    use rocket::request::FromParam;
+
+    #[test]
+    fn parse_uuid_param() {
+        let param = RawStr::from_str("123e4567-e89b-12d3-a456-426614174000");
+        let uuid = UuidParam::from_param(param).unwrap();
+        assert_eq!(uuid.to_string(), "123e4567-e89b-12d3-a456-426614174000");
+        assert!(UuidParam::from_param(RawStr::from_str("not-a-uuid")).is_err());
+    }
+}